@@ -0,0 +1,2 @@
+-- Track when a subscriber confirmed, either via the link or manually by an admin
+ALTER TABLE subscriptions ADD COLUMN confirmed_at timestamptz NULL;
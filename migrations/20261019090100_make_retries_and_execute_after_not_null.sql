@@ -0,0 +1,13 @@
+BEGIN;
+    -- Backfill the columns added without defaults
+    UPDATE issue_delivery_queue
+        SET n_retries = 0
+        WHERE n_retries IS NULL;
+    UPDATE issue_delivery_queue
+        SET execute_after = now()
+        WHERE execute_after IS NULL;
+    ALTER TABLE issue_delivery_queue ALTER COLUMN n_retries SET DEFAULT 0;
+    ALTER TABLE issue_delivery_queue ALTER COLUMN n_retries SET NOT NULL;
+    ALTER TABLE issue_delivery_queue ALTER COLUMN execute_after SET DEFAULT now();
+    ALTER TABLE issue_delivery_queue ALTER COLUMN execute_after SET NOT NULL;
+COMMIT;
@@ -0,0 +1,8 @@
+-- One row per delivery attempt made by the issue delivery worker
+CREATE TABLE issue_delivery_log (
+    newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_email TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    attempted_at timestamptz NOT NULL
+);
+CREATE INDEX issue_delivery_log_subscriber_email_idx ON issue_delivery_log (subscriber_email);
@@ -0,0 +1,2 @@
+-- Where consent was obtained for subscribers added outside the subscribe form
+ALTER TABLE subscriptions ADD COLUMN consent_source TEXT NULL;
@@ -0,0 +1,21 @@
+-- Create Lists Table
+CREATE TABLE lists(
+    list_id uuid NOT NULL,
+    PRIMARY KEY (list_id),
+    slug TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    created_at timestamptz NOT NULL
+);
+-- Everybody subscribed so far belongs to the original newsletter
+INSERT INTO lists (list_id, slug, name, created_at) VALUES (
+    '8c1c6c3e-3f9f-4a5e-9d4b-1f2a7b6e0d11',
+    'newsletter',
+    'Newsletter',
+    now()
+);
+ALTER TABLE subscriptions ADD COLUMN list_id uuid NULL REFERENCES lists (list_id);
+UPDATE subscriptions SET list_id = '8c1c6c3e-3f9f-4a5e-9d4b-1f2a7b6e0d11';
+ALTER TABLE subscriptions ALTER COLUMN list_id SET NOT NULL;
+-- An address can now subscribe to several lists, once per list
+ALTER TABLE subscriptions DROP CONSTRAINT subscriptions_email_key;
+ALTER TABLE subscriptions ADD CONSTRAINT subscriptions_list_id_email_key UNIQUE (list_id, email);
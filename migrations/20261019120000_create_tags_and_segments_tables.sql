@@ -0,0 +1,42 @@
+-- Create Subscriber Tags Table
+CREATE TABLE subscriber_tags(
+    subscriber_id uuid NOT NULL REFERENCES subscriptions (id),
+    tag TEXT NOT NULL,
+    PRIMARY KEY (subscriber_id, tag)
+);
+CREATE INDEX subscriber_tags_tag_idx ON subscriber_tags (tag);
+-- Create Segments Table
+CREATE TABLE segments(
+    segment_id uuid NOT NULL,
+    PRIMARY KEY (segment_id),
+    name TEXT NOT NULL UNIQUE,
+    -- A subscriber must carry every tag to be part of the segment
+    tags TEXT[] NOT NULL,
+    status TEXT NULL,
+    subscribed_from DATE NULL,
+    subscribed_until DATE NULL,
+    subscribed_within_days INTEGER NULL,
+    created_at timestamptz NOT NULL
+);
+-- Single definition of segment membership, shared by the recipient
+-- count preview and the delivery queue
+CREATE FUNCTION subscription_in_segment(subscription subscriptions, segment segments)
+RETURNS BOOLEAN
+LANGUAGE SQL STABLE
+AS $$
+    SELECT (segment.status IS NULL OR subscription.status = segment.status)
+        AND (segment.subscribed_from IS NULL
+            OR subscription.subscribed_at::date >= segment.subscribed_from)
+        AND (segment.subscribed_until IS NULL
+            OR subscription.subscribed_at::date <= segment.subscribed_until)
+        AND (segment.subscribed_within_days IS NULL
+            OR subscription.subscribed_at >= now() - make_interval(days => segment.subscribed_within_days))
+        AND NOT EXISTS (
+            SELECT 1 FROM unnest(segment.tags) AS required(tag)
+            WHERE NOT EXISTS (
+                SELECT 1 FROM subscriber_tags
+                WHERE subscriber_tags.subscriber_id = subscription.id
+                  AND subscriber_tags.tag = required.tag
+            )
+        )
+$$;
@@ -0,0 +1,11 @@
+-- Create Subscriber Attributes Table
+CREATE TABLE subscriber_attributes(
+    subscriber_id uuid NOT NULL REFERENCES subscriptions (id),
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (subscriber_id, key)
+);
+-- The subscription an issue is personalised for.
+-- Not a foreign key: the subscription may be deleted while the address
+-- is still a confirmed member of another list.
+ALTER TABLE issue_delivery_queue ADD COLUMN subscriber_id uuid NULL;
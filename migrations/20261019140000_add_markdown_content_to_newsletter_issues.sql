@@ -0,0 +1,2 @@
+-- Source of issues authored in Markdown
+ALTER TABLE newsletter_issues ADD COLUMN markdown_content TEXT NULL;
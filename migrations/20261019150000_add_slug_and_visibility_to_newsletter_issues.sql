@@ -0,0 +1,10 @@
+-- Issues are browsable at /issues/{slug} unless private
+ALTER TABLE newsletter_issues ADD COLUMN slug TEXT NULL;
+UPDATE newsletter_issues
+SET slug = trim(BOTH '-' FROM lower(regexp_replace(title, '[^a-zA-Z0-9]+', '-', 'g')))
+    || '-' || left(newsletter_issue_id::text, 8);
+ALTER TABLE newsletter_issues ALTER COLUMN slug SET NOT NULL;
+ALTER TABLE newsletter_issues ADD CONSTRAINT newsletter_issues_slug_key UNIQUE (slug);
+-- Issues sent before the archive existed stay private
+ALTER TABLE newsletter_issues ADD COLUMN visibility TEXT NOT NULL DEFAULT 'private'
+    CHECK (visibility IN ('public', 'private'));
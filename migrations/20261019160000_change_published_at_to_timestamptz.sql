@@ -0,0 +1,3 @@
+-- Issues were stored with `now()` cast to text, which Postgres parses back.
+ALTER TABLE newsletter_issues
+    ALTER COLUMN published_at TYPE timestamptz USING published_at::timestamptz;
@@ -0,0 +1,11 @@
+ALTER TABLE newsletter_issues ADD COLUMN track_clicks BOOLEAN NOT NULL DEFAULT false;
+
+CREATE TABLE issue_clicks (
+    newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_id uuid NOT NULL,
+    url TEXT NOT NULL,
+    n_clicks INTEGER NOT NULL,
+    first_clicked_at timestamptz NOT NULL,
+    last_clicked_at timestamptz NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, subscriber_id, url)
+);
@@ -0,0 +1,15 @@
+-- Soft bounces are counted per address, whatever the list.
+CREATE TABLE email_soft_bounces (
+    email TEXT NOT NULL PRIMARY KEY,
+    n_soft_bounces INTEGER NOT NULL,
+    last_bounced_at timestamptz NOT NULL
+);
+
+-- Providers retry webhooks they did not get a response for:
+-- remember which events were processed to count each only once.
+CREATE TABLE processed_webhook_events (
+    provider TEXT NOT NULL,
+    event_id TEXT NOT NULL,
+    processed_at timestamptz NOT NULL,
+    PRIMARY KEY (provider, event_id)
+);
@@ -0,0 +1,18 @@
+-- Addresses are only kept hashed: an entry outlives the subscriber's data.
+CREATE FUNCTION email_hash(email TEXT) RETURNS TEXT AS $$
+    SELECT encode(sha256(convert_to(lower(trim(email)), 'UTF8')), 'hex')
+$$ LANGUAGE SQL IMMUTABLE;
+
+CREATE TABLE suppressions (
+    email_hash TEXT NOT NULL PRIMARY KEY,
+    reason TEXT NOT NULL CHECK (reason IN ('bounced', 'complained', 'legal_request', 'manual')),
+    -- Who added the entry, e.g. `postmark` or `admin`.
+    source TEXT NOT NULL,
+    suppressed_at timestamptz NOT NULL
+);
+
+INSERT INTO suppressions (email_hash, reason, source, suppressed_at)
+SELECT DISTINCT ON (email_hash(email)) email_hash(email), status, 'backfill', now()
+FROM subscriptions
+WHERE status IN ('bounced', 'complained')
+ORDER BY email_hash(email), status = 'complained' DESC;
@@ -0,0 +1,10 @@
+-- Confirmation links expire: record when each token was issued.
+-- Existing tokens are treated as issued now.
+ALTER TABLE subscription_tokens ADD COLUMN created_at timestamptz NOT NULL DEFAULT now();
+
+-- Confirmation emails sent to each address, to rate-limit them.
+CREATE TABLE confirmation_emails(
+    email TEXT NOT NULL,
+    sent_at timestamptz NOT NULL
+);
+CREATE INDEX confirmation_emails_email_sent_at_idx ON confirmation_emails (email, sent_at);
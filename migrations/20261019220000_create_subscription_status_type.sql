@@ -0,0 +1,36 @@
+-- Subscription statuses become a closed set, transitions between them are
+-- enforced by `domain::SubscriptionStatus`.
+CREATE TYPE subscription_status AS ENUM (
+    'pending_confirmation',
+    'confirmed',
+    'unsubscribed',
+    'bounced',
+    'complained'
+);
+ALTER TABLE subscriptions
+    ALTER COLUMN status TYPE subscription_status USING status::subscription_status;
+
+-- Segments keep their status filter as TEXT.
+CREATE OR REPLACE FUNCTION subscription_in_segment(subscription subscriptions, segment segments)
+RETURNS BOOLEAN
+LANGUAGE SQL STABLE
+AS $$
+    SELECT (segment.status IS NULL OR subscription.status::TEXT = segment.status)
+        AND (segment.subscribed_from IS NULL
+            OR subscription.subscribed_at::date >= segment.subscribed_from)
+        AND (segment.subscribed_until IS NULL
+            OR subscription.subscribed_at::date <= segment.subscribed_until)
+        AND (segment.subscribed_within_days IS NULL
+            OR subscription.subscribed_at >= now() - make_interval(days => segment.subscribed_within_days))
+        AND NOT EXISTS (
+            SELECT 1 FROM unnest(segment.tags) AS required(tag)
+            WHERE NOT EXISTS (
+                SELECT 1 FROM subscriber_tags
+                WHERE subscriber_tags.subscriber_id = subscription.id
+                  AND subscriber_tags.tag = required.tag
+            )
+        )
+$$;
+
+-- Confirmation tokens can only be used once.
+ALTER TABLE subscription_tokens ADD COLUMN consumed_at timestamptz NULL;
@@ -0,0 +1,38 @@
+-- The evidence of how each subscriber opted in and out.
+CREATE TABLE consent_events (
+    consent_event_id uuid NOT NULL PRIMARY KEY,
+    subscriber_id uuid NOT NULL REFERENCES subscriptions (id),
+    action TEXT NOT NULL CHECK (action IN (
+        'subscribe_requested', 'confirmed', 'unsubscribed', 'bounced', 'complained',
+        'imported', 'admin_confirmed', 'admin_unsubscribed'
+    )),
+    -- Where consent was given, e.g. the form a subscriber used.
+    source TEXT NULL,
+    ip_address TEXT NULL,
+    user_agent TEXT NULL,
+    -- The admin who made the change, if any.
+    actor TEXT NULL,
+    occurred_at timestamptz NOT NULL
+);
+CREATE INDEX consent_events_subscriber_id_idx ON consent_events (subscriber_id, occurred_at);
+
+-- Events are never rewritten. They only go away with the subscriber,
+-- when it is deleted or erased.
+CREATE FUNCTION reject_consent_event_update() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'consent events are append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER consent_events_append_only
+BEFORE UPDATE ON consent_events
+FOR EACH ROW EXECUTE FUNCTION reject_consent_event_update();
+
+-- What we know of past subscriptions.
+INSERT INTO consent_events (consent_event_id, subscriber_id, action, source, occurred_at)
+SELECT gen_random_uuid(), id, 'subscribe_requested', consent_source, subscribed_at
+FROM subscriptions;
+INSERT INTO consent_events (consent_event_id, subscriber_id, action, occurred_at)
+SELECT gen_random_uuid(), id, 'confirmed', confirmed_at
+FROM subscriptions
+WHERE confirmed_at IS NOT NULL;
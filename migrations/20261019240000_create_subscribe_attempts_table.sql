@@ -0,0 +1,8 @@
+-- Recent requests to subscribe, to rate limit them per IP and per address.
+CREATE TABLE subscribe_attempts (
+    email TEXT NOT NULL,
+    ip_address TEXT NULL,
+    attempted_at timestamptz NOT NULL
+);
+CREATE INDEX subscribe_attempts_email_idx ON subscribe_attempts (lower(email), attempted_at);
+CREATE INDEX subscribe_attempts_ip_address_idx ON subscribe_attempts (ip_address, attempted_at);
@@ -0,0 +1,89 @@
+-- `Alice@Example.com` and `alice@example.com` are the same subscriber.
+-- Subscriptions to a list whose addresses only differ by case or surrounding
+-- whitespace are merged into the confirmed one, or else the oldest one.
+CREATE TEMPORARY TABLE merged_subscriptions AS
+SELECT id AS duplicate_id, first_value(id) OVER (
+    PARTITION BY list_id, lower(trim(email))
+    ORDER BY status = 'confirmed' DESC, subscribed_at, id
+) AS survivor_id
+FROM subscriptions;
+DELETE FROM merged_subscriptions WHERE duplicate_id = survivor_id;
+
+UPDATE subscription_tokens t
+SET subscriber_id = m.survivor_id
+FROM merged_subscriptions m
+WHERE t.subscriber_id = m.duplicate_id;
+
+-- Tags and attributes of the survivor win.
+INSERT INTO subscriber_tags (subscriber_id, tag)
+SELECT m.survivor_id, t.tag
+FROM subscriber_tags t JOIN merged_subscriptions m ON t.subscriber_id = m.duplicate_id
+ON CONFLICT DO NOTHING;
+DELETE FROM subscriber_tags WHERE subscriber_id IN (SELECT duplicate_id FROM merged_subscriptions);
+
+INSERT INTO subscriber_attributes (subscriber_id, key, value)
+SELECT DISTINCT ON (m.survivor_id, a.key) m.survivor_id, a.key, a.value
+FROM subscriber_attributes a JOIN merged_subscriptions m ON a.subscriber_id = m.duplicate_id
+ORDER BY m.survivor_id, a.key
+ON CONFLICT DO NOTHING;
+DELETE FROM subscriber_attributes WHERE subscriber_id IN (SELECT duplicate_id FROM merged_subscriptions);
+
+INSERT INTO issue_opens (newsletter_issue_id, subscriber_id, first_opened_at, last_opened_at)
+SELECT o.newsletter_issue_id, m.survivor_id, min(o.first_opened_at), max(o.last_opened_at)
+FROM issue_opens o JOIN merged_subscriptions m ON o.subscriber_id = m.duplicate_id
+GROUP BY o.newsletter_issue_id, m.survivor_id
+ON CONFLICT (newsletter_issue_id, subscriber_id) DO UPDATE
+SET first_opened_at = LEAST(issue_opens.first_opened_at, EXCLUDED.first_opened_at),
+    last_opened_at = GREATEST(issue_opens.last_opened_at, EXCLUDED.last_opened_at);
+DELETE FROM issue_opens WHERE subscriber_id IN (SELECT duplicate_id FROM merged_subscriptions);
+
+INSERT INTO issue_clicks (
+    newsletter_issue_id, subscriber_id, url, n_clicks, first_clicked_at, last_clicked_at
+)
+SELECT c.newsletter_issue_id, m.survivor_id, c.url,
+    sum(c.n_clicks), min(c.first_clicked_at), max(c.last_clicked_at)
+FROM issue_clicks c JOIN merged_subscriptions m ON c.subscriber_id = m.duplicate_id
+GROUP BY c.newsletter_issue_id, m.survivor_id, c.url
+ON CONFLICT (newsletter_issue_id, subscriber_id, url) DO UPDATE
+SET n_clicks = issue_clicks.n_clicks + EXCLUDED.n_clicks,
+    first_clicked_at = LEAST(issue_clicks.first_clicked_at, EXCLUDED.first_clicked_at),
+    last_clicked_at = GREATEST(issue_clicks.last_clicked_at, EXCLUDED.last_clicked_at);
+DELETE FROM issue_clicks WHERE subscriber_id IN (SELECT duplicate_id FROM merged_subscriptions);
+
+-- Each issue is delivered once per address.
+DELETE FROM issue_delivery_queue q
+USING issue_delivery_queue other
+WHERE q.newsletter_issue_id = other.newsletter_issue_id
+  AND lower(trim(q.subscriber_email)) = lower(trim(other.subscriber_email))
+  AND q.subscriber_email > other.subscriber_email;
+UPDATE issue_delivery_queue q
+SET subscriber_id = m.survivor_id, subscriber_email = s.email
+FROM merged_subscriptions m JOIN subscriptions s ON s.id = m.survivor_id
+WHERE q.subscriber_id = m.duplicate_id;
+
+-- The consent history of the duplicates is evidence too: it moves to the
+-- survivor rather than being rewritten.
+ALTER TABLE consent_events DISABLE TRIGGER consent_events_append_only;
+UPDATE consent_events e
+SET subscriber_id = m.survivor_id
+FROM merged_subscriptions m
+WHERE e.subscriber_id = m.duplicate_id;
+ALTER TABLE consent_events ENABLE TRIGGER consent_events_append_only;
+
+DELETE FROM subscriptions WHERE id IN (SELECT duplicate_id FROM merged_subscriptions);
+DROP TABLE merged_subscriptions;
+
+-- Addresses are stored trimmed with a lowercase domain, as `SubscriberEmail`
+-- now parses them. International domains are converted to punycode by the
+-- application only.
+UPDATE subscriptions
+SET email = left(trim(email), -length(substring(trim(email) from '@[^@]*$')))
+    || lower(substring(trim(email) from '@[^@]*$'))
+WHERE email LIKE '%@%';
+UPDATE issue_delivery_queue
+SET subscriber_email = left(trim(subscriber_email), -length(substring(trim(subscriber_email) from '@[^@]*$')))
+    || lower(substring(trim(subscriber_email) from '@[^@]*$'))
+WHERE subscriber_email LIKE '%@%';
+
+ALTER TABLE subscriptions DROP CONSTRAINT subscriptions_list_id_email_key;
+CREATE UNIQUE INDEX subscriptions_list_id_lower_email_key ON subscriptions (list_id, lower(email));
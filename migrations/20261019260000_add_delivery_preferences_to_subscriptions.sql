@@ -0,0 +1,13 @@
+-- Subscribers can get issues as they are published, or batched at the start
+-- of the next day or week (UTC), and take a break from them.
+CREATE TYPE delivery_frequency AS ENUM ('immediate', 'daily', 'weekly');
+ALTER TABLE subscriptions
+    ADD COLUMN delivery_frequency delivery_frequency NOT NULL DEFAULT 'immediate',
+    ADD COLUMN paused_until timestamptz NULL;
+
+-- Subscribers can move their subscription to another address.
+ALTER TABLE consent_events DROP CONSTRAINT consent_events_action_check;
+ALTER TABLE consent_events ADD CONSTRAINT consent_events_action_check CHECK (action IN (
+    'subscribe_requested', 'confirmed', 'unsubscribed', 'bounced', 'complained',
+    'imported', 'admin_confirmed', 'admin_unsubscribed', 'email_changed'
+));
@@ -0,0 +1,3 @@
+-- Whether subscribing to the list takes a confirmation email.
+-- NULL follows `subscriptions.double_opt_in` in the configuration.
+ALTER TABLE lists ADD COLUMN double_opt_in BOOLEAN NULL;
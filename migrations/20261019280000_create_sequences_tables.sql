@@ -0,0 +1,28 @@
+-- Emails sent one after the other to the subscribers of a list once they
+-- confirm, e.g. an onboarding series.
+CREATE TABLE sequences (
+    sequence_id uuid PRIMARY KEY,
+    list_id uuid NOT NULL REFERENCES lists (list_id),
+    name TEXT NOT NULL,
+    created_at timestamptz NOT NULL
+);
+
+-- Each step is an issue, delivered by the issue delivery worker.
+CREATE TABLE sequence_steps (
+    sequence_id uuid NOT NULL REFERENCES sequences (sequence_id),
+    position INT NOT NULL,
+    -- Counted from the previous step, or from the confirmation for the first one.
+    delay_hours INT NOT NULL CHECK (delay_hours >= 0),
+    newsletter_issue_id uuid NOT NULL UNIQUE REFERENCES newsletter_issues (newsletter_issue_id),
+    PRIMARY KEY (sequence_id, position)
+);
+
+CREATE TABLE sequence_enrollments (
+    sequence_id uuid NOT NULL REFERENCES sequences (sequence_id),
+    subscriber_id uuid NOT NULL REFERENCES subscriptions (id),
+    -- The step queued last, NULL before the first one is.
+    position INT NULL,
+    enrolled_at timestamptz NOT NULL,
+    completed_at timestamptz NULL,
+    PRIMARY KEY (sequence_id, subscriber_id)
+);
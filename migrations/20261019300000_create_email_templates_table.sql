@@ -0,0 +1,13 @@
+-- Versions of the transactional emails saved by admins, the latest one is
+-- sent. Emails without any fall back to the templates in templates/email/.
+CREATE TABLE email_templates(
+    email TEXT NOT NULL,
+    locale TEXT NOT NULL,
+    version INT NOT NULL,
+    subject TEXT NOT NULL,
+    html TEXT NOT NULL,
+    text TEXT NOT NULL,
+    created_by TEXT NOT NULL,
+    created_at timestamptz NOT NULL,
+    PRIMARY KEY (email, locale, version)
+);
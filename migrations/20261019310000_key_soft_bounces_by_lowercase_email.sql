@@ -0,0 +1,12 @@
+-- Soft bounces are counted per address whatever its case, like subscriptions.
+WITH merged AS (
+    DELETE FROM email_soft_bounces RETURNING *
+)
+INSERT INTO email_soft_bounces (email, n_soft_bounces, last_bounced_at)
+SELECT lower(email), sum(n_soft_bounces)::INTEGER, max(last_bounced_at)
+FROM merged
+GROUP BY lower(email);
+
+-- Confirmation emails are rate limited per address whatever its case.
+DROP INDEX confirmation_emails_email_sent_at_idx;
+CREATE INDEX confirmation_emails_lower_email_sent_at_idx ON confirmation_emails (lower(email), sent_at);
@@ -0,0 +1,7 @@
+-- Issues queued for daily and weekly subscribers are sent together, in a
+-- single digest email.
+ALTER TABLE issue_delivery_queue ADD COLUMN digest BOOLEAN NOT NULL DEFAULT false;
+UPDATE issue_delivery_queue q
+SET digest = true
+FROM subscriptions s
+WHERE s.id = q.subscriber_id AND s.delivery_frequency <> 'immediate';
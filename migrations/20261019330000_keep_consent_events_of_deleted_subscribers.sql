@@ -0,0 +1,8 @@
+-- Consent events are evidence: they outlive subscribers deleted by an admin,
+-- together with a record of the deletion. Only erasing an address removes them.
+ALTER TABLE consent_events DROP CONSTRAINT consent_events_subscriber_id_fkey;
+ALTER TABLE consent_events DROP CONSTRAINT consent_events_action_check;
+ALTER TABLE consent_events ADD CONSTRAINT consent_events_action_check CHECK (action IN (
+    'subscribe_requested', 'confirmed', 'unsubscribed', 'bounced', 'complained',
+    'imported', 'admin_confirmed', 'admin_unsubscribed', 'email_changed', 'admin_deleted'
+));
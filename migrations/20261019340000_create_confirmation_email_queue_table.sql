@@ -0,0 +1,7 @@
+-- Confirmation emails of imported subscribers, sent by the background worker
+-- rather than within the upload request.
+CREATE TABLE confirmation_email_queue (
+    subscriber_id uuid PRIMARY KEY REFERENCES subscriptions (id),
+    n_retries SMALLINT NOT NULL DEFAULT 0,
+    execute_after timestamptz NOT NULL DEFAULT now()
+);
@@ -0,0 +1,6 @@
+-- Privacy links sent to each address, to rate-limit them.
+CREATE TABLE privacy_emails(
+    email TEXT NOT NULL,
+    sent_at timestamptz NOT NULL
+);
+CREATE INDEX privacy_emails_lower_email_sent_at_idx ON privacy_emails (lower(email), sent_at);
@@ -0,0 +1,253 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::configuration::{AbuseProtectionSettings, ChallengeProvider, ChallengeSettings};
+use crate::startup::HmacSecret;
+
+/// Checks the answer to a challenge shown on the subscription form,
+/// e.g. by asking a CAPTCHA provider.
+#[async_trait]
+pub trait ChallengeVerifier: Send + Sync {
+    async fn verify(&self, response: &str, ip_address: Option<&str>)
+        -> Result<bool, anyhow::Error>;
+}
+
+/// Accepts a fixed answer, for local development and tests.
+pub struct LocalChallengeVerifier {
+    answer: Secret<String>,
+}
+
+impl LocalChallengeVerifier {
+    pub fn new(answer: Secret<String>) -> Self {
+        Self { answer }
+    }
+}
+
+#[async_trait]
+impl ChallengeVerifier for LocalChallengeVerifier {
+    async fn verify(
+        &self,
+        response: &str,
+        _ip_address: Option<&str>,
+    ) -> Result<bool, anyhow::Error> {
+        Ok(response == self.answer.expose_secret())
+    }
+}
+
+impl ChallengeSettings {
+    pub fn verifier(&self) -> Box<dyn ChallengeVerifier> {
+        match self.provider {
+            ChallengeProvider::Local => Box::new(LocalChallengeVerifier::new(self.secret.clone())),
+        }
+    }
+}
+
+/// The fields of the subscription form that are only there to stop bots.
+#[derive(serde::Deserialize, Default)]
+pub struct AntiBotFields {
+    /// Hidden from humans, only bots fill it in.
+    pub website: Option<String>,
+    /// When the form was rendered, signed. See [`AbuseProtection::form_token`].
+    /// Required from browsers.
+    pub form_token: Option<String>,
+    pub challenge_response: Option<String>,
+}
+
+/// What to do with a subscription request.
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    Accept,
+    /// Most likely a bot: pretend to accept the request, but drop it.
+    Discard,
+    RateLimited,
+    ChallengeFailed,
+}
+
+pub struct AbuseProtection {
+    settings: AbuseProtectionSettings,
+    hmac_secret: HmacSecret,
+    challenge_verifier: Option<Box<dyn ChallengeVerifier>>,
+}
+
+impl AbuseProtection {
+    pub fn new(settings: AbuseProtectionSettings, hmac_secret: HmacSecret) -> Self {
+        let challenge_verifier = settings.challenge.as_ref().map(ChallengeSettings::verifier);
+        Self {
+            settings,
+            hmac_secret,
+            challenge_verifier,
+        }
+    }
+
+    pub fn has_challenge(&self) -> bool {
+        self.challenge_verifier.is_some()
+    }
+
+    /// A token to embed in the subscription form, to tell how long it took to fill in.
+    pub fn form_token(&self) -> String {
+        sign_form_token(chrono::Utc::now().timestamp(), &self.hmac_secret)
+    }
+
+    /// Screen a subscription request, and count it towards the rate limits if it is accepted.
+    /// Every request must come with a form token from the subscription form,
+    /// whatever response format it asks for.
+    #[tracing::instrument(name = "Screen a subscription request", skip(self, pool, fields))]
+    pub async fn check(
+        &self,
+        pool: &PgPool,
+        email: &str,
+        fields: &AntiBotFields,
+        ip_address: Option<&str>,
+    ) -> Result<Verdict, anyhow::Error> {
+        if matches!(fields.website.as_deref(), Some(website) if !website.is_empty()) {
+            tracing::warn!("The honeypot field was filled in.");
+            return Ok(Verdict::Discard);
+        }
+        let now = chrono::Utc::now().timestamp();
+        let age = fields
+            .form_token
+            .as_deref()
+            .and_then(|form_token| form_token_age(form_token, now, &self.hmac_secret));
+        match age {
+            Some(age) if age < self.settings.min_form_fill_seconds => {
+                tracing::warn!("The form was filled in too fast.");
+                return Ok(Verdict::Discard);
+            }
+            Some(age) if age > self.settings.form_token_ttl_hours * 3600 => {
+                tracing::warn!("The form token has expired.");
+                return Ok(Verdict::Discard);
+            }
+            Some(_) => {}
+            None => {
+                tracing::warn!("The form token is missing or not valid.");
+                return Ok(Verdict::Discard);
+            }
+        }
+        if self.is_rate_limited(pool, email, ip_address).await? {
+            return Ok(Verdict::RateLimited);
+        }
+        if let Some(verifier) = &self.challenge_verifier {
+            let response = fields.challenge_response.as_deref().unwrap_or_default();
+            if response.is_empty() || !verifier.verify(response, ip_address).await? {
+                return Ok(Verdict::ChallengeFailed);
+            }
+        }
+        record_subscribe_attempt(pool, email, ip_address).await?;
+        Ok(Verdict::Accept)
+    }
+
+    async fn is_rate_limited(
+        &self,
+        pool: &PgPool,
+        email: &str,
+        ip_address: Option<&str>,
+    ) -> Result<bool, anyhow::Error> {
+        let counts = sqlx::query!(
+            r#"
+            SELECT
+                COUNT(*) FILTER (WHERE lower(email) = lower($1)) AS "by_email!",
+                COUNT(*) FILTER (WHERE ip_address = $2) AS "by_ip_address!"
+            FROM subscribe_attempts
+            WHERE attempted_at > now() - interval '1 hour'
+            "#,
+            email,
+            ip_address
+        )
+        .fetch_one(pool)
+        .await
+        .context("Failed to count recent subscription requests.")?;
+        Ok(
+            counts.by_email >= self.settings.max_requests_per_email_per_hour
+                || counts.by_ip_address >= self.settings.max_requests_per_ip_per_hour,
+        )
+    }
+}
+
+#[tracing::instrument(name = "Record a subscription request", skip(pool))]
+async fn record_subscribe_attempt(
+    pool: &PgPool,
+    email: &str,
+    ip_address: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    // Only the last hour matters, older attempts would just pile up.
+    sqlx::query!(r#"DELETE FROM subscribe_attempts WHERE attempted_at < now() - interval '1 day'"#)
+        .execute(pool)
+        .await
+        .context("Failed to delete old subscription requests.")?;
+    sqlx::query!(
+        r#"
+        INSERT INTO subscribe_attempts (email, ip_address, attempted_at)
+        VALUES ($1, $2, now())
+        "#,
+        email,
+        ip_address
+    )
+    .execute(pool)
+    .await
+    .context("Failed to record a subscription request.")?;
+    Ok(())
+}
+
+pub fn sign_form_token(issued_at: i64, hmac_secret: &HmacSecret) -> String {
+    let signature = hmac_secret.sign("subscription-form", issued_at.to_string().as_bytes());
+    format!("{}.{}", issued_at, hex::encode(signature))
+}
+
+/// Seconds since the form token was issued, `None` if it was not issued by us.
+fn form_token_age(form_token: &str, now: i64, hmac_secret: &HmacSecret) -> Option<i64> {
+    let (issued_at, signature) = form_token.split_once('.')?;
+    let signature = hex::decode(signature).ok()?;
+    if !hmac_secret.verify("subscription-form", issued_at.as_bytes(), &signature) {
+        return None;
+    }
+    let issued_at: i64 = issued_at.parse().ok()?;
+    Some(now - issued_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{form_token_age, sign_form_token, ChallengeVerifier, LocalChallengeVerifier};
+    use crate::startup::HmacSecret;
+    use claim::{assert_none, assert_ok_eq, assert_some_eq};
+    use secrecy::Secret;
+
+    fn hmac_secret() -> HmacSecret {
+        HmacSecret(Secret::new("a-secret".into()))
+    }
+
+    #[test]
+    fn form_tokens_tell_how_long_ago_they_were_issued() {
+        let form_token = sign_form_token(1_000, &hmac_secret());
+        assert_some_eq!(form_token_age(&form_token, 1_005, &hmac_secret()), 5);
+    }
+
+    #[test]
+    fn form_tokens_with_a_forged_timestamp_are_rejected() {
+        let form_token = sign_form_token(1_000, &hmac_secret());
+        let forged = form_token.replacen("1000", "900", 1);
+        assert_none!(form_token_age(&forged, 1_005, &hmac_secret()));
+    }
+
+    #[test]
+    fn malformed_form_tokens_are_rejected() {
+        for form_token in ["", "1000", "1000.", "abc.def", "1000.zz"] {
+            assert_none!(form_token_age(form_token, 1_005, &hmac_secret()));
+        }
+    }
+
+    #[test]
+    fn form_tokens_signed_with_another_secret_are_rejected() {
+        let other_secret = HmacSecret(Secret::new("another-secret".into()));
+        let form_token = sign_form_token(1_000, &other_secret);
+        assert_none!(form_token_age(&form_token, 1_005, &hmac_secret()));
+    }
+
+    #[tokio::test]
+    async fn the_local_verifier_only_accepts_its_answer() {
+        let verifier = LocalChallengeVerifier::new(Secret::new("42".into()));
+        assert_ok_eq!(verifier.verify("42", None).await, true);
+        assert_ok_eq!(verifier.verify("41", None).await, false);
+    }
+}
@@ -3,6 +3,7 @@ use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::postgres::PgConnectOptions;
 use sqlx::postgres::PgSslMode;
 use sqlx::ConnectOptions;
+use std::net::IpAddr;
 
 use crate::domain::SubscriberEmail;
 use crate::email_client::EmailClient;
@@ -13,6 +14,8 @@ pub struct Settings {
     pub application: ApplicationSettings,
     pub email_client: EmailClientSettings,
     pub worker: WorkerSettings,
+    pub webhooks: WebhookSettings,
+    pub subscriptions: SubscriptionSettings,
     pub redis_uri: Secret<String>,
 }
 
@@ -23,6 +26,10 @@ pub struct ApplicationSettings {
     pub host: String,
     pub base_url: String,
     pub hmac_secret: Secret<String>,
+    /// The proxies in front of the application, e.g. a load balancer. The
+    /// `X-Forwarded-For` header is ignored unless it comes from one of them.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -38,6 +45,84 @@ pub struct WorkerSettings {
     pub max_retries: u64,
 }
 
+#[derive(serde::Deserialize, Clone)]
+pub struct WebhookSettings {
+    pub username: String,
+    pub password: Secret<String>,
+    /// Soft bounces an address can get before being treated as bounced.
+    pub soft_bounce_threshold: i32,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SubscriptionSettings {
+    /// How long a confirmation link stays valid.
+    pub confirmation_token_ttl_hours: i64,
+    /// Confirmation emails an address can be sent per hour.
+    pub max_confirmation_emails_per_hour: i64,
+    /// Whether new subscribers must confirm their address before getting
+    /// issues. Lists can override it.
+    pub double_opt_in: bool,
+    pub abuse_protection: AbuseProtectionSettings,
+    pub email_policy: EmailPolicySettings,
+}
+
+/// Limits on `POST /subscriptions`, which would otherwise let anyone mail
+/// confirmation emails to arbitrary addresses.
+#[derive(serde::Deserialize, Clone)]
+pub struct AbuseProtectionSettings {
+    /// Subscription requests accepted per IP address and per hour.
+    pub max_requests_per_ip_per_hour: i64,
+    /// Subscription requests accepted per email address and per hour.
+    pub max_requests_per_email_per_hour: i64,
+    /// Forms submitted faster than this are assumed to be filled in by bots.
+    pub min_form_fill_seconds: i64,
+    /// How long the token of a subscription form stays valid, so that one
+    /// token cannot be replayed forever.
+    pub form_token_ttl_hours: i64,
+    /// A challenge to pass before subscribing, e.g. a CAPTCHA.
+    pub challenge: Option<ChallengeSettings>,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct ChallengeSettings {
+    pub provider: ChallengeProvider,
+    pub secret: Secret<String>,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ChallengeProvider {
+    /// Checks answers against `secret`, for local development and tests.
+    Local,
+}
+
+/// Which addresses the subscription form accepts, on top of them being valid.
+#[derive(serde::Deserialize, Clone)]
+pub struct EmailPolicySettings {
+    /// One domain per line, e.g. a copy of a community-maintained list.
+    pub disposable_domains_path: String,
+    pub disposable: PolicyAction,
+    /// Addresses like `noreply@` or `postmaster@`.
+    pub role_accounts: PolicyAction,
+    /// Likely misspellings of common domains, e.g. `gmial.com`.
+    pub typos: PolicyAction,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    Allow,
+    /// Accept the address, but tag the subscriber for review.
+    Flag,
+    Reject,
+}
+
+impl SubscriptionSettings {
+    pub fn confirmation_token_ttl(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.confirmation_token_ttl_hours)
+    }
+}
+
 impl EmailClientSettings {
     pub fn client(self) -> EmailClient {
         let sender_email = self.sender().expect("Invalid sender email address.");
@@ -0,0 +1,153 @@
+use crate::{
+    domain::{ListSlug, Locale, SubscriberEmail, SubscriptionStatus},
+    email_client::EmailClient,
+    issue_delivery_worker::ExecutionOutcome,
+    routes::{reuse_or_rotate_token, send_confirmation_email},
+};
+use chrono::Utc;
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{field::display, Span};
+use uuid::Uuid;
+
+type PgTransaction = Transaction<'static, Postgres>;
+
+/// Have the background worker ask a pending subscriber to confirm.
+#[tracing::instrument(name = "Enqueue a confirmation email", skip(transaction))]
+pub async fn enqueue_confirmation_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO confirmation_email_queue (subscriber_id)
+        VALUES ($1)
+        ON CONFLICT DO NOTHING
+        "#,
+        subscriber_id
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+/// Send the next queued confirmation email, with the pending token of the
+/// subscriber or a new one if it expired.
+#[tracing::instrument(skip_all, fields(subscriber_id=tracing::field::Empty), err)]
+pub async fn try_send_confirmation_email(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &str,
+    confirmation_token_ttl: chrono::Duration,
+    max_retries: u64,
+    execute_after_seconds: u64,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let task = sqlx::query!(
+        r#"
+        SELECT
+            confirmation_email_queue.subscriber_id,
+            subscriptions.email,
+            subscriptions.status AS "status: SubscriptionStatus",
+            lists.slug AS list_slug,
+            subscriptions.locale
+        FROM confirmation_email_queue
+        JOIN subscriptions ON subscriptions.id = confirmation_email_queue.subscriber_id
+        JOIN lists ON lists.list_id = subscriptions.list_id
+        WHERE confirmation_email_queue.n_retries <= $1
+          AND confirmation_email_queue.execute_after <= CURRENT_TIMESTAMP
+        FOR UPDATE OF confirmation_email_queue
+        SKIP LOCKED
+        LIMIT 1
+        "#,
+        max_retries as i64
+    )
+    .fetch_optional(&mut transaction)
+    .await?;
+    let task = match task {
+        Some(task) => task,
+        None => return Ok(ExecutionOutcome::EmptyQueue),
+    };
+    Span::current().record("subscriber_id", &display(task.subscriber_id));
+    if task.status != SubscriptionStatus::PendingConfirmation {
+        tracing::info!("Skipping a subscriber who is no longer pending confirmation.");
+        delete_task(transaction, task.subscriber_id).await?;
+        return Ok(ExecutionOutcome::TaskCompleted);
+    }
+    let (email, list_slug) = match (
+        SubscriberEmail::parse(task.email),
+        ListSlug::parse(task.list_slug),
+    ) {
+        (Ok(email), Ok(list_slug)) => (email, list_slug),
+        (Err(e), _) | (_, Err(e)) => {
+            tracing::error!(
+                error.message = %e,
+                "Skipping a confirmation email. Their stored contact details are invalid",
+            );
+            delete_task(transaction, task.subscriber_id).await?;
+            return Ok(ExecutionOutcome::TaskCompleted);
+        }
+    };
+    let locale = Locale::parse(&task.locale).unwrap_or_default();
+    let subscription_token =
+        reuse_or_rotate_token(&mut transaction, task.subscriber_id, confirmation_token_ttl).await?;
+    match send_confirmation_email(
+        pool,
+        email_client,
+        &email,
+        base_url,
+        &list_slug,
+        &subscription_token,
+        &locale,
+    )
+    .await
+    {
+        Ok(()) => delete_task(transaction, task.subscriber_id).await?,
+        Err(e) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to send a confirmation email. Retrying later",
+            );
+            retry_later_task(transaction, task.subscriber_id, execute_after_seconds).await?;
+        }
+    }
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+#[tracing::instrument(skip_all)]
+async fn retry_later_task(
+    mut transaction: PgTransaction,
+    subscriber_id: Uuid,
+    seconds: u64,
+) -> Result<(), anyhow::Error> {
+    let execute_after = Utc::now() + chrono::Duration::seconds(seconds as i64);
+    sqlx::query!(
+        r#"
+        UPDATE confirmation_email_queue
+        SET n_retries = n_retries + 1,
+            execute_after = $2
+        WHERE subscriber_id = $1
+        "#,
+        subscriber_id,
+        execute_after
+    )
+    .execute(&mut transaction)
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn delete_task(
+    mut transaction: PgTransaction,
+    subscriber_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"DELETE FROM confirmation_email_queue WHERE subscriber_id = $1"#,
+        subscriber_id
+    )
+    .execute(&mut transaction)
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use actix_web::http::header::USER_AGENT;
+use actix_web::{web, HttpRequest};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgExecutor;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+/// What happened to a subscription, as recorded in `consent_events`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsentAction {
+    SubscribeRequested,
+    Confirmed,
+    Unsubscribed,
+    Bounced,
+    Complained,
+    Imported,
+    AdminConfirmed,
+    AdminUnsubscribed,
+    AdminDeleted,
+    EmailChanged,
+}
+
+impl ConsentAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConsentAction::SubscribeRequested => "subscribe_requested",
+            ConsentAction::Confirmed => "confirmed",
+            ConsentAction::Unsubscribed => "unsubscribed",
+            ConsentAction::Bounced => "bounced",
+            ConsentAction::Complained => "complained",
+            ConsentAction::Imported => "imported",
+            ConsentAction::AdminConfirmed => "admin_confirmed",
+            ConsentAction::AdminUnsubscribed => "admin_unsubscribed",
+            ConsentAction::AdminDeleted => "admin_deleted",
+            ConsentAction::EmailChanged => "email_changed",
+        }
+    }
+}
+
+/// Who made a change and from where.
+#[derive(Debug, Default)]
+pub struct ConsentContext {
+    pub source: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub actor: Option<String>,
+}
+
+impl ConsentContext {
+    /// The client of a subscriber-facing request.
+    pub fn from_request(request: &HttpRequest) -> Self {
+        Self {
+            source: None,
+            ip_address: client_ip(
+                request.peer_addr().map(|peer| peer.ip()),
+                request
+                    .headers()
+                    .get("X-Forwarded-For")
+                    .and_then(|forwarded_for| forwarded_for.to_str().ok()),
+                request
+                    .app_data::<web::Data<TrustedProxies>>()
+                    .map(|proxies| proxies.0.as_slice())
+                    .unwrap_or_default(),
+            ),
+            user_agent: request
+                .headers()
+                .get(USER_AGENT)
+                .and_then(|user_agent| user_agent.to_str().ok())
+                .map(str::to_owned),
+            actor: None,
+        }
+    }
+
+    /// A change made by an admin from the back office.
+    pub fn admin(username: String) -> Self {
+        Self {
+            actor: Some(username),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+}
+
+/// The proxies in front of the application, which are trusted to tell the
+/// address of the client in `X-Forwarded-For`.
+pub struct TrustedProxies(pub Vec<IpAddr>);
+
+/// The address of the client: the peer of the connection, unless it is a
+/// trusted proxy. Only the last address of `X-Forwarded-For` is added by the
+/// proxy, the ones before it are whatever the client sent.
+fn client_ip(
+    peer: Option<IpAddr>,
+    forwarded_for: Option<&str>,
+    trusted_proxies: &[IpAddr],
+) -> Option<String> {
+    let peer = peer?;
+    if trusted_proxies.contains(&peer) {
+        let forwarded = forwarded_for
+            .and_then(|forwarded_for| forwarded_for.rsplit(',').next())
+            .and_then(|client| client.trim().parse::<IpAddr>().ok());
+        if let Some(client) = forwarded {
+            return Some(client.to_string());
+        }
+    }
+    Some(peer.to_string())
+}
+
+/// Append an event to the consent audit trail of a subscriber.
+#[tracing::instrument(name = "Record a consent event", skip(executor))]
+pub async fn record_consent_event(
+    executor: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+    action: ConsentAction,
+    context: &ConsentContext,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO consent_events (
+            consent_event_id, subscriber_id, action, source, ip_address, user_agent, actor, occurred_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, now())
+        "#,
+        Uuid::new_v4(),
+        subscriber_id,
+        action.as_str(),
+        context.source,
+        context.ip_address,
+        context.user_agent,
+        context.actor
+    )
+    .execute(executor)
+    .await
+    .context("Failed to record a consent event.")?;
+    Ok(())
+}
+
+#[derive(serde::Serialize)]
+pub struct ConsentEvent {
+    pub action: String,
+    pub source: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub actor: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// The consent audit trail of a subscriber, oldest event first.
+#[tracing::instrument(name = "Get consent events", skip(executor))]
+pub async fn get_consent_events(
+    executor: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+) -> Result<Vec<ConsentEvent>, anyhow::Error> {
+    let events = sqlx::query_as!(
+        ConsentEvent,
+        r#"
+        SELECT action, source, ip_address, user_agent, actor, occurred_at
+        FROM consent_events
+        WHERE subscriber_id = $1
+        ORDER BY occurred_at, consent_event_id
+        "#,
+        subscriber_id
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to retrieve consent events.")?;
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::client_ip;
+    use claim::{assert_none, assert_some_eq};
+    use std::net::IpAddr;
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn forwarded_addresses_are_ignored_without_a_trusted_proxy() {
+        assert_some_eq!(
+            client_ip(Some(ip("203.0.113.7")), Some("198.51.100.1"), &[]),
+            "203.0.113.7"
+        );
+    }
+
+    #[test]
+    fn the_address_added_by_a_trusted_proxy_is_used() {
+        let proxy = ip("10.0.0.1");
+        assert_some_eq!(
+            client_ip(Some(proxy), Some("198.51.100.1, 203.0.113.7"), &[proxy]),
+            "203.0.113.7"
+        );
+    }
+
+    #[test]
+    fn trusted_proxies_without_a_valid_forwarded_address_are_the_client() {
+        let proxy = ip("10.0.0.1");
+        for forwarded_for in [None, Some(""), Some("not-an-ip")] {
+            assert_some_eq!(client_ip(Some(proxy), forwarded_for, &[proxy]), "10.0.0.1");
+        }
+    }
+
+    #[test]
+    fn there_is_no_address_without_a_peer() {
+        assert_none!(client_ip(None, Some("198.51.100.1"), &[]));
+    }
+}
@@ -0,0 +1,64 @@
+#[derive(Debug, Clone)]
+pub struct AttributeKey(String);
+
+impl AsRef<str> for AttributeKey {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for AttributeKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl AttributeKey {
+    /// Returns an instance of `AttributeKey` if the input is a non-empty string
+    /// of at most 32 lowercase ASCII letters, digits and underscores that does
+    /// not start with a digit, so that it can be used as-is in a merge tag,
+    /// e.g. `{{ subscriber.attributes.company }}`.
+    pub fn parse(s: String) -> Result<AttributeKey, String> {
+        let is_empty = s.is_empty();
+        let is_too_long = s.len() > 32;
+        let contains_forbidden_characters = s
+            .chars()
+            .any(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'));
+        let starts_with_digit = s.starts_with(|c: char| c.is_ascii_digit());
+
+        if is_empty || is_too_long || contains_forbidden_characters || starts_with_digit {
+            Err(format!("{} is not a valid attribute name.", s))
+        } else {
+            Ok(Self(s))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::AttributeKey;
+    use claim::{assert_err, assert_ok};
+
+    #[test]
+    fn empty_string_is_rejected() {
+        assert_err!(AttributeKey::parse("".to_string()));
+    }
+    #[test]
+    fn a_key_longer_than_32_characters_is_rejected() {
+        assert_err!(AttributeKey::parse("a".repeat(33)));
+    }
+    #[test]
+    fn keys_with_forbidden_characters_are_rejected() {
+        for key in &["first name", "first-name", "Company", "company.name"] {
+            assert_err!(AttributeKey::parse(key.to_string()));
+        }
+    }
+    #[test]
+    fn keys_starting_with_a_digit_are_rejected() {
+        assert_err!(AttributeKey::parse("2fa".to_string()));
+    }
+    #[test]
+    fn a_valid_key_is_parsed_successfully() {
+        assert_ok!(AttributeKey::parse("favourite_book_2".to_string()));
+    }
+}
@@ -0,0 +1,59 @@
+/// How often a subscriber gets issues, backed by the `delivery_frequency`
+/// Postgres enum.
+/// Issues for daily and weekly subscribers wait for the start of the next
+/// day or week, in UTC, and are sent together in a single digest email.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, serde::Serialize)]
+#[sqlx(type_name = "delivery_frequency", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryFrequency {
+    Immediate,
+    Daily,
+    Weekly,
+}
+
+impl DeliveryFrequency {
+    pub const ALL: [DeliveryFrequency; 3] = [
+        DeliveryFrequency::Immediate,
+        DeliveryFrequency::Daily,
+        DeliveryFrequency::Weekly,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryFrequency::Immediate => "immediate",
+            DeliveryFrequency::Daily => "daily",
+            DeliveryFrequency::Weekly => "weekly",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<DeliveryFrequency, String> {
+        Self::ALL
+            .into_iter()
+            .find(|frequency| frequency.as_str() == s)
+            .ok_or_else(|| format!("{} is not a valid delivery frequency.", s))
+    }
+}
+
+impl std::fmt::Display for DeliveryFrequency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeliveryFrequency;
+    use claim::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn frequencies_are_parsed_from_their_names() {
+        for frequency in DeliveryFrequency::ALL {
+            assert_ok_eq!(DeliveryFrequency::parse(frequency.as_str()), frequency);
+        }
+    }
+
+    #[test]
+    fn unknown_frequencies_are_rejected() {
+        assert_err!(DeliveryFrequency::parse("hourly"));
+    }
+}
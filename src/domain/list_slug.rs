@@ -0,0 +1,79 @@
+#[derive(Debug, Clone)]
+pub struct ListSlug(String);
+
+impl AsRef<str> for ListSlug {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for ListSlug {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl ListSlug {
+    /// Returns an instance of `ListSlug` if the input is a non-empty string
+    /// of at most 64 lowercase ASCII letters, digits and inner dashes,
+    /// i.e. something that can be used as a URL path segment as-is.
+    pub fn parse(s: String) -> Result<ListSlug, String> {
+        let is_empty = s.is_empty();
+        let is_too_long = s.len() > 64;
+        let contains_forbidden_characters = s
+            .chars()
+            .any(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-'));
+        let has_outer_dash = s.starts_with('-') || s.ends_with('-');
+
+        if is_empty || is_too_long || contains_forbidden_characters || has_outer_dash {
+            Err(format!("{} is not a valid list slug.", s))
+        } else {
+            Ok(Self(s))
+        }
+    }
+}
+
+/// The list every subscriber belonged to before multiple lists were introduced.
+impl Default for ListSlug {
+    fn default() -> Self {
+        Self("newsletter".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::ListSlug;
+    use claim::{assert_err, assert_ok};
+
+    #[test]
+    fn empty_string_is_rejected() {
+        assert_err!(ListSlug::parse("".to_string()));
+    }
+    #[test]
+    fn a_slug_longer_than_64_characters_is_rejected() {
+        assert_err!(ListSlug::parse("a".repeat(65)));
+    }
+    #[test]
+    fn slugs_with_uppercase_letters_are_rejected() {
+        assert_err!(ListSlug::parse("Weekly".to_string()));
+    }
+    #[test]
+    fn slugs_with_forbidden_characters_are_rejected() {
+        for slug in &["weekly digest", "weekly/digest", "weekly_digest", "wéekly"] {
+            assert_err!(ListSlug::parse(slug.to_string()));
+        }
+    }
+    #[test]
+    fn slugs_starting_or_ending_with_a_dash_are_rejected() {
+        assert_err!(ListSlug::parse("-weekly".to_string()));
+        assert_err!(ListSlug::parse("weekly-".to_string()));
+    }
+    #[test]
+    fn a_valid_slug_is_parsed_successfully() {
+        assert_ok!(ListSlug::parse("weekly-digest-2".to_string()));
+    }
+    #[test]
+    fn the_default_slug_is_valid() {
+        assert_ok!(ListSlug::parse(ListSlug::default().as_ref().to_string()));
+    }
+}
@@ -0,0 +1,135 @@
+/// A subscriber's preferred language, e.g. `fr` or `pt-BR`: a language code
+/// with an optional region, in their usual case.
+/// Locales name template variants, e.g. `home.pt-BR.html`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale(String);
+
+impl AsRef<str> for Locale {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Locale {
+    /// Returns an instance of `Locale` if the input is a 2 or 3 letter
+    /// language code, optionally followed by a dash or an underscore and a
+    /// 2 letter or 3 digit region code.
+    pub fn parse(s: &str) -> Result<Locale, String> {
+        let tag = s.trim().replace('_', "-");
+        let (language, region) = match tag.split_once('-') {
+            Some((language, region)) => (language, Some(region)),
+            None => (tag.as_str(), None),
+        };
+        let is_valid_language =
+            (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_alphabetic());
+        let is_valid_region = match region {
+            None => true,
+            Some(region) => {
+                (region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic()))
+                    || (region.len() == 3 && region.chars().all(|c| c.is_ascii_digit()))
+            }
+        };
+        if !(is_valid_language && is_valid_region) {
+            return Err(format!("{} is not a valid locale.", s));
+        }
+        let language = language.to_ascii_lowercase();
+        Ok(match region {
+            Some(region) => Self(format!("{}-{}", language, region.to_ascii_uppercase())),
+            None => Self(language),
+        })
+    }
+
+    /// The most preferred locale of an `Accept-Language` header, skipping
+    /// wildcards and the ranges we cannot parse.
+    pub fn from_accept_language(header: &str) -> Option<Locale> {
+        let mut ranges: Vec<(Locale, f32)> = header
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let locale = Locale::parse(parts.next()?).ok()?;
+                let quality = parts
+                    .filter_map(|parameter| parameter.trim().strip_prefix("q="))
+                    .find_map(|q| q.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                Some((locale, quality))
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .collect();
+        // A stable sort: ties keep the order of the header.
+        ranges.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        ranges.into_iter().next().map(|(locale, _)| locale)
+    }
+
+    /// The locales to look for, from the most to the least specific,
+    /// e.g. `pt-BR` then `pt`.
+    pub fn fallbacks(&self) -> Vec<&str> {
+        match self.0.split_once('-') {
+            Some((language, _)) => vec![&self.0, language],
+            None => vec![&self.0],
+        }
+    }
+}
+
+/// The language the templates without a locale suffix are written in.
+impl Default for Locale {
+    fn default() -> Self {
+        Self("en".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Locale;
+    use claim::{assert_err, assert_none, assert_ok_eq, assert_some_eq};
+
+    #[test]
+    fn locales_are_normalised() {
+        assert_ok_eq!(Locale::parse("FR"), Locale("fr".into()));
+        assert_ok_eq!(Locale::parse("pt_br"), Locale("pt-BR".into()));
+        assert_ok_eq!(Locale::parse(" es-419 "), Locale("es-419".into()));
+    }
+
+    #[test]
+    fn invalid_locales_are_rejected() {
+        for locale in &[
+            "", "f", "french", "fr-", "fr-CAN", "fr-1", "../fr", "fr-CA-x",
+        ] {
+            assert_err!(Locale::parse(locale));
+        }
+    }
+
+    #[test]
+    fn the_default_locale_is_valid() {
+        assert_ok_eq!(Locale::parse(Locale::default().as_ref()), Locale::default());
+    }
+
+    #[test]
+    fn the_most_preferred_language_is_picked() {
+        assert_some_eq!(
+            Locale::from_accept_language("en;q=0.5, fr-CH, fr;q=0.9, *;q=0.1"),
+            Locale("fr-CH".into())
+        );
+        assert_some_eq!(
+            Locale::from_accept_language("*, de;q=0.8, it;q=0.8"),
+            Locale("de".into())
+        );
+    }
+
+    #[test]
+    fn refused_and_unparseable_languages_are_skipped() {
+        assert_none!(Locale::from_accept_language("fr;q=0, *"));
+        assert_none!(Locale::from_accept_language(""));
+    }
+
+    #[test]
+    fn regional_locales_fall_back_to_their_language() {
+        assert_eq!(Locale("pt-BR".into()).fallbacks(), vec!["pt-BR", "pt"]);
+        assert_eq!(Locale("pt".into()).fallbacks(), vec!["pt"]);
+    }
+}
@@ -1,11 +1,23 @@
+mod attribute_key;
+mod delivery_frequency;
+mod list_slug;
+mod locale;
 mod new_password;
 mod new_subscriber;
 mod subscriber_email;
 mod subscriber_name;
+mod subscriber_tag;
 mod subscriber_token;
+mod subscription_status;
 
+pub use attribute_key::AttributeKey;
+pub use delivery_frequency::DeliveryFrequency;
+pub use list_slug::ListSlug;
+pub use locale::Locale;
 pub use new_password::NewPassword;
 pub use new_subscriber::NewSubscriber;
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
+pub use subscriber_tag::SubscriberTag;
 pub use subscriber_token::SubscriberToken;
+pub use subscription_status::SubscriptionStatus;
@@ -4,11 +4,19 @@ use validator::validate_email;
 pub struct SubscriberEmail(String);
 
 impl SubscriberEmail {
+    /// Returns the address trimmed, with its domain lowercased and converted
+    /// to ASCII (punycode), if it is valid.
+    /// The local part keeps its case: only the mail server of the domain
+    /// knows whether it matters, addresses are compared case-insensitively.
     pub fn parse(s: String) -> Result<SubscriberEmail, String> {
-        if validate_email(&s) {
-            Ok(Self(s))
+        let invalid = || format!("{} is not a valid subscriber email.", s);
+        let (local_part, domain) = s.trim().rsplit_once('@').ok_or_else(invalid)?;
+        let domain = idna::domain_to_ascii(domain).map_err(|_| invalid())?;
+        let email = format!("{}@{}", local_part, domain);
+        if validate_email(&email) {
+            Ok(Self(email))
         } else {
-            Err(format!("{} is not a valid subscriber email.", s))
+            Err(invalid())
         }
     }
 }
@@ -29,7 +37,7 @@ impl AsRef<str> for SubscriberEmail {
 #[cfg(test)]
 mod tests {
     use super::SubscriberEmail;
-    use claim::assert_err;
+    use claim::{assert_err, assert_ok};
     use fake::faker::internet::en::SafeEmail;
     use fake::Fake;
 
@@ -49,6 +57,17 @@ mod tests {
         assert_err!(SubscriberEmail::parse(email));
     }
 
+    #[test]
+    fn email_is_trimmed_and_its_domain_lowercased() {
+        let email = assert_ok!(SubscriberEmail::parse(" Ursula@Domain.COM ".to_string()));
+        assert_eq!(email.as_ref(), "Ursula@domain.com");
+    }
+    #[test]
+    fn international_domains_are_converted_to_punycode() {
+        let email = assert_ok!(SubscriberEmail::parse("ursula@Bücher.example".to_string()));
+        assert_eq!(email.as_ref(), "ursula@xn--bcher-kva.example");
+    }
+
     #[derive(Debug, Clone)]
     struct ValidEmailFixture(pub String);
 
@@ -0,0 +1,64 @@
+#[derive(Debug, Clone)]
+pub struct SubscriberTag(String);
+
+impl AsRef<str> for SubscriberTag {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for SubscriberTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl SubscriberTag {
+    /// Returns an instance of `SubscriberTag` if the input, once trimmed and
+    /// lowercased, is a non-empty string of at most 32 ASCII letters, digits,
+    /// dashes and underscores.
+    pub fn parse(s: String) -> Result<SubscriberTag, String> {
+        let tag = s.trim().to_lowercase();
+        let is_empty = tag.is_empty();
+        let is_too_long = tag.len() > 32;
+        let contains_forbidden_characters = tag
+            .chars()
+            .any(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_'));
+
+        if is_empty || is_too_long || contains_forbidden_characters {
+            Err(format!("{} is not a valid tag.", s))
+        } else {
+            Ok(Self(tag))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::SubscriberTag;
+    use claim::{assert_err, assert_ok};
+
+    #[test]
+    fn whitespace_only_tags_are_rejected() {
+        assert_err!(SubscriberTag::parse(" ".to_string()));
+    }
+    #[test]
+    fn a_tag_longer_than_32_characters_is_rejected() {
+        assert_err!(SubscriberTag::parse("a".repeat(33)));
+    }
+    #[test]
+    fn tags_with_forbidden_characters_are_rejected() {
+        for tag in &["early adopter", "beta/2", "bêta", "beta,alpha"] {
+            assert_err!(SubscriberTag::parse(tag.to_string()));
+        }
+    }
+    #[test]
+    fn tags_are_trimmed_and_lowercased() {
+        let tag = SubscriberTag::parse(" Beta_Testers ".to_string()).unwrap();
+        assert_eq!(tag.as_ref(), "beta_testers");
+    }
+    #[test]
+    fn a_valid_tag_is_parsed_successfully() {
+        assert_ok!(SubscriberTag::parse("early-adopter".to_string()));
+    }
+}
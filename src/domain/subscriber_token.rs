@@ -21,10 +21,7 @@ impl AsRef<str> for SubscriberToken {
 }
 
 fn check_alphanumeric(ch: char) -> bool {
-    if ('0'..='9').contains(&ch) || ('a'..='z').contains(&ch) || ('A'..='Z').contains(&ch) {
-        return true;
-    }
-    false
+    ch.is_ascii_alphanumeric()
 }
 
 impl SubscriberToken {
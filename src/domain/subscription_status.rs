@@ -0,0 +1,126 @@
+/// Where a subscription stands, backed by the `subscription_status` Postgres enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, serde::Serialize)]
+#[sqlx(type_name = "subscription_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+    PendingConfirmation,
+    Confirmed,
+    Unsubscribed,
+    Bounced,
+    Complained,
+}
+
+impl SubscriptionStatus {
+    pub const ALL: [SubscriptionStatus; 5] = [
+        SubscriptionStatus::PendingConfirmation,
+        SubscriptionStatus::Confirmed,
+        SubscriptionStatus::Unsubscribed,
+        SubscriptionStatus::Bounced,
+        SubscriptionStatus::Complained,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubscriptionStatus::PendingConfirmation => "pending_confirmation",
+            SubscriptionStatus::Confirmed => "confirmed",
+            SubscriptionStatus::Unsubscribed => "unsubscribed",
+            SubscriptionStatus::Bounced => "bounced",
+            SubscriptionStatus::Complained => "complained",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<SubscriptionStatus, String> {
+        Self::ALL
+            .into_iter()
+            .find(|status| status.as_str() == s)
+            .ok_or_else(|| format!("{} is not a valid status.", s))
+    }
+
+    /// The only changes a subscription can go through:
+    /// - pending subscriptions get confirmed, or drop out;
+    /// - subscribing again restarts the confirmation of unsubscribed and
+    ///   bounced addresses;
+    /// - bounces do not override an unsubscription;
+    /// - a spam complaint overrides everything and is final.
+    pub fn can_transition_to(self, next: SubscriptionStatus) -> bool {
+        use SubscriptionStatus::*;
+        matches!(
+            (self, next),
+            (PendingConfirmation, Confirmed | Unsubscribed | Bounced)
+                | (Confirmed, Unsubscribed | Bounced)
+                | (Unsubscribed | Bounced, PendingConfirmation)
+                | (
+                    PendingConfirmation | Confirmed | Unsubscribed | Bounced,
+                    Complained
+                )
+        )
+    }
+
+    /// The statuses a subscription can be in to move to `self`, to guard
+    /// `UPDATE`s with `status::TEXT = ANY($n)`.
+    pub fn allowed_sources(self) -> Vec<String> {
+        Self::ALL
+            .into_iter()
+            .filter(|source| source.can_transition_to(self))
+            .map(|source| source.as_str().to_owned())
+            .collect()
+    }
+}
+
+impl std::fmt::Display for SubscriptionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SubscriptionStatus::{self, *};
+    use claim::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn statuses_round_trip_through_their_names() {
+        for status in SubscriptionStatus::ALL {
+            assert_ok_eq!(SubscriptionStatus::parse(status.as_str()), status);
+        }
+    }
+
+    #[test]
+    fn unknown_statuses_are_rejected() {
+        assert_err!(SubscriptionStatus::parse("active"));
+    }
+
+    #[test]
+    fn only_pending_subscriptions_can_be_confirmed() {
+        assert_eq!(Confirmed.allowed_sources(), vec!["pending_confirmation"]);
+    }
+
+    #[test]
+    fn subscribing_again_restarts_unsubscribed_and_bounced_subscriptions() {
+        assert_eq!(
+            PendingConfirmation.allowed_sources(),
+            vec!["unsubscribed", "bounced"]
+        );
+    }
+
+    #[test]
+    fn bounces_do_not_override_unsubscriptions() {
+        assert!(!Unsubscribed.can_transition_to(Bounced));
+        assert!(Confirmed.can_transition_to(Bounced));
+    }
+
+    #[test]
+    fn complaints_are_final() {
+        for status in SubscriptionStatus::ALL {
+            assert!(!Complained.can_transition_to(status));
+        }
+        assert_eq!(Complained.allowed_sources().len(), 4);
+    }
+
+    #[test]
+    fn no_status_transitions_to_itself() {
+        for status in SubscriptionStatus::ALL {
+            assert!(!status.can_transition_to(status));
+        }
+    }
+}
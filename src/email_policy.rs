@@ -0,0 +1,346 @@
+use anyhow::Context;
+use std::collections::HashSet;
+
+use crate::configuration::{EmailPolicySettings, PolicyAction};
+use crate::domain::SubscriberEmail;
+
+/// Local parts of addresses that belong to a function rather than a person.
+const ROLE_LOCAL_PARTS: &[&str] = &[
+    "abuse",
+    "admin",
+    "administrator",
+    "billing",
+    "contact",
+    "donotreply",
+    "do-not-reply",
+    "help",
+    "hostmaster",
+    "info",
+    "mailer-daemon",
+    "marketing",
+    "no-reply",
+    "noreply",
+    "office",
+    "postmaster",
+    "root",
+    "sales",
+    "security",
+    "support",
+    "webmaster",
+];
+
+/// Domains subscribers commonly misspell. Each of them is also a valid
+/// domain on its own: `ymail.com` is not a typo of `gmail.com`.
+const COMMON_DOMAINS: &[&str] = &[
+    "aol.com",
+    "email.com",
+    "gmail.com",
+    "gmx.com",
+    "gmx.de",
+    "gmx.net",
+    "googlemail.com",
+    "hotmail.co.uk",
+    "hotmail.com",
+    "hotmail.fr",
+    "icloud.com",
+    "live.com",
+    "mac.com",
+    "mail.com",
+    "me.com",
+    "msn.com",
+    "outlook.com",
+    "proton.me",
+    "protonmail.com",
+    "yahoo.co.uk",
+    "yahoo.com",
+    "yahoo.fr",
+    "ymail.com",
+];
+
+/// Common domains shorter than this are a single edit away from too many
+/// real domains (`my.com` for `me.com`) to suggest them as corrections.
+const MIN_SUGGESTED_DOMAIN_LENGTH: usize = 8;
+
+/// Why an address was let through but deserves a second look.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmailFlag {
+    Disposable,
+    RoleAccount,
+    LikelyTypo { suggestion: String },
+}
+
+impl EmailFlag {
+    /// The tag flagged subscribers get, for admins to review them.
+    pub fn tag(&self) -> &'static str {
+        match self {
+            EmailFlag::Disposable => "disposable-address",
+            EmailFlag::RoleAccount => "role-address",
+            EmailFlag::LikelyTypo { .. } => "possible-typo",
+        }
+    }
+
+    fn rejection_message(&self, email: &SubscriberEmail) -> String {
+        match self {
+            EmailFlag::Disposable => format!(
+                "{} is a disposable address, please subscribe with a permanent one.",
+                email
+            ),
+            EmailFlag::RoleAccount => format!(
+                "{} is a role address, please subscribe with a personal one.",
+                email
+            ),
+            EmailFlag::LikelyTypo { suggestion } => {
+                format!("{} looks misspelled, did you mean {}?", email, suggestion)
+            }
+        }
+    }
+}
+
+pub struct EmailPolicy {
+    settings: EmailPolicySettings,
+    disposable_domains: HashSet<String>,
+}
+
+impl EmailPolicySettings {
+    pub fn policy(&self) -> Result<EmailPolicy, anyhow::Error> {
+        let domains =
+            std::fs::read_to_string(&self.disposable_domains_path).with_context(|| {
+                format!(
+                    "Failed to read the disposable domains from {}.",
+                    self.disposable_domains_path
+                )
+            })?;
+        Ok(EmailPolicy::new(self.clone(), &domains))
+    }
+}
+
+impl EmailPolicy {
+    /// `disposable_domains` holds one domain per line, `#` starts a comment.
+    pub fn new(settings: EmailPolicySettings, disposable_domains: &str) -> Self {
+        let disposable_domains = disposable_domains
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|domain| !domain.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        Self {
+            settings,
+            disposable_domains,
+        }
+    }
+
+    /// The flags of an accepted address, or why it was rejected.
+    pub fn check(&self, email: &SubscriberEmail) -> Result<Vec<EmailFlag>, String> {
+        let (local_part, domain) = split_address(email.as_ref());
+        let mut flags = vec![];
+        if self.settings.disposable != PolicyAction::Allow && self.is_disposable(&domain) {
+            flags.push((self.settings.disposable, EmailFlag::Disposable));
+        }
+        if self.settings.role_accounts != PolicyAction::Allow && is_role_account(&local_part) {
+            flags.push((self.settings.role_accounts, EmailFlag::RoleAccount));
+        }
+        if self.settings.typos != PolicyAction::Allow {
+            if let Some(suggested_domain) = suggest_domain(&domain) {
+                // The local part is kept as typed: tags and case can matter.
+                let (typed_local_part, _) = email.as_ref().rsplit_once('@').unwrap_or_default();
+                let suggestion = format!("{}@{}", typed_local_part, suggested_domain);
+                flags.push((self.settings.typos, EmailFlag::LikelyTypo { suggestion }));
+            }
+        }
+        if let Some((_, flag)) = flags
+            .iter()
+            .find(|(action, _)| *action == PolicyAction::Reject)
+        {
+            return Err(flag.rejection_message(email));
+        }
+        Ok(flags.into_iter().map(|(_, flag)| flag).collect())
+    }
+
+    /// Subdomains of disposable domains are disposable too.
+    fn is_disposable(&self, domain: &str) -> bool {
+        let mut domain = domain;
+        loop {
+            if self.disposable_domains.contains(domain) {
+                return true;
+            }
+            match domain.split_once('.') {
+                Some((_, parent)) if parent.contains('.') => domain = parent,
+                _ => return false,
+            }
+        }
+    }
+}
+
+/// The lowercased local part, without its `+tag`, and domain of an address.
+fn split_address(email: &str) -> (String, String) {
+    let (local_part, domain) = email.rsplit_once('@').unwrap_or((email, ""));
+    let local_part = local_part.split('+').next().unwrap_or_default();
+    (local_part.to_lowercase(), domain.to_lowercase())
+}
+
+fn is_role_account(local_part: &str) -> bool {
+    ROLE_LOCAL_PARTS.contains(&local_part)
+}
+
+/// The common domain `domain` is a single typo away from, if any.
+fn suggest_domain(domain: &str) -> Option<&'static str> {
+    if COMMON_DOMAINS.contains(&domain) {
+        return None;
+    }
+    COMMON_DOMAINS
+        .iter()
+        .copied()
+        .filter(|common| common.len() >= MIN_SUGGESTED_DOMAIN_LENGTH)
+        .find(|common| edit_distance(domain, common) == 1)
+}
+
+/// Optimal string alignment distance: insertions, deletions, substitutions
+/// and transpositions of adjacent characters each count as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edit_distance, suggest_domain, EmailFlag, EmailPolicy};
+    use crate::configuration::{EmailPolicySettings, PolicyAction};
+    use crate::domain::SubscriberEmail;
+    use claim::{assert_err, assert_none, assert_ok_eq, assert_some_eq};
+
+    fn policy(action: PolicyAction) -> EmailPolicy {
+        let settings = EmailPolicySettings {
+            disposable_domains_path: String::new(),
+            disposable: action,
+            role_accounts: action,
+            typos: action,
+        };
+        EmailPolicy::new(
+            settings,
+            "# A comment\nmailinator.com\n\nYOPMAIL.com # trailing\n",
+        )
+    }
+
+    fn email(s: &str) -> SubscriberEmail {
+        SubscriberEmail::parse(s.into()).unwrap()
+    }
+
+    #[test]
+    fn transpositions_count_as_a_single_edit() {
+        assert_eq!(edit_distance("gmial.com", "gmail.com"), 1);
+        assert_eq!(edit_distance("gmail.co", "gmail.com"), 1);
+        assert_eq!(edit_distance("hotmail.fr", "hotmail.com"), 3);
+    }
+
+    #[test]
+    fn misspelled_common_domains_get_a_suggestion() {
+        assert_some_eq!(suggest_domain("gmial.com"), "gmail.com");
+        assert_some_eq!(suggest_domain("yahooo.com"), "yahoo.com");
+        assert_some_eq!(suggest_domain("outlok.com"), "outlook.com");
+        assert_some_eq!(suggest_domain("gmal.com"), "gmail.com");
+    }
+
+    #[test]
+    fn real_domains_close_to_short_common_domains_get_no_suggestion() {
+        for domain in ["my.com", "me.org", "aol.de", "gmx.at", "msn.org"] {
+            assert_none!(suggest_domain(domain));
+        }
+    }
+
+    #[test]
+    fn common_and_unrelated_domains_get_no_suggestion() {
+        for domain in [
+            "gmail.com",
+            "ymail.com",
+            "mail.com",
+            "example.com",
+            "rust-lang.org",
+        ] {
+            assert_none!(suggest_domain(domain));
+        }
+    }
+
+    #[test]
+    fn disposable_domains_and_their_subdomains_are_rejected() {
+        let policy = policy(PolicyAction::Reject);
+        for address in [
+            "ursula@mailinator.com",
+            "ursula@yopmail.com",
+            "ursula@eu.mailinator.com",
+        ] {
+            assert_err!(policy.check(&email(address)));
+        }
+    }
+
+    #[test]
+    fn role_accounts_are_detected_ignoring_case_and_tags() {
+        let policy = policy(PolicyAction::Reject);
+        for address in [
+            "noreply@example.com",
+            "Postmaster@example.com",
+            "info+news@example.com",
+        ] {
+            assert_err!(policy.check(&email(address)));
+        }
+    }
+
+    #[test]
+    fn the_rejection_of_a_typo_suggests_a_correction() {
+        let policy = policy(PolicyAction::Reject);
+        let error = policy.check(&email("ursula@gmial.com")).unwrap_err();
+        assert!(error.contains("did you mean ursula@gmail.com?"));
+    }
+
+    #[test]
+    fn typo_suggestions_only_replace_the_domain() {
+        let policy = policy(PolicyAction::Reject);
+        let error = policy.check(&email("Ursula+news@gmial.com")).unwrap_err();
+        assert!(error.ends_with("did you mean Ursula+news@gmail.com?"));
+    }
+
+    #[test]
+    fn flagged_addresses_are_accepted_with_their_flags() {
+        let policy = policy(PolicyAction::Flag);
+        assert_ok_eq!(
+            policy.check(&email("noreply@gmial.com")),
+            vec![
+                EmailFlag::RoleAccount,
+                EmailFlag::LikelyTypo {
+                    suggestion: "noreply@gmail.com".into()
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn nothing_is_checked_when_everything_is_allowed() {
+        let policy = policy(PolicyAction::Allow);
+        assert_ok_eq!(policy.check(&email("noreply@mailinator.com")), vec![]);
+    }
+
+    #[test]
+    fn ordinary_addresses_pass() {
+        let policy = policy(PolicyAction::Reject);
+        assert_ok_eq!(policy.check(&email("ursula_le_guin@gmail.com")), vec![]);
+    }
+}
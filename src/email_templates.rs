@@ -0,0 +1,402 @@
+//! Transactional emails: the emails sent to subscribers on their own
+//! actions rather than as part of an issue.
+//!
+//! Their subject, HTML and plain text bodies come from `templates/email/`
+//! unless admins saved their own version for the subscriber's locale.
+//! Saved versions are Tera templates, kept in `email_templates` so that
+//! older ones can be restored.
+use anyhow::Context;
+use sqlx::{PgExecutor, PgPool};
+use std::collections::HashMap;
+use tera::Tera;
+
+use crate::domain::Locale;
+use crate::merge_tags::describe;
+use crate::routes::{localised_template_name, render_localised};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionalEmail {
+    Confirmation,
+    Welcome,
+    UnsubscribeConfirmation,
+}
+
+impl TransactionalEmail {
+    pub const ALL: [TransactionalEmail; 3] = [
+        TransactionalEmail::Confirmation,
+        TransactionalEmail::Welcome,
+        TransactionalEmail::UnsubscribeConfirmation,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionalEmail::Confirmation => "confirmation",
+            TransactionalEmail::Welcome => "welcome",
+            TransactionalEmail::UnsubscribeConfirmation => "unsubscribe_confirmation",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<TransactionalEmail, String> {
+        Self::ALL
+            .into_iter()
+            .find(|email| email.as_str() == s)
+            .ok_or_else(|| format!("{} is not a transactional email.", s))
+    }
+
+    /// What admins see in the list of emails.
+    pub fn description(&self) -> &'static str {
+        match self {
+            TransactionalEmail::Confirmation => {
+                "Sent to new subscribers of double opt-in lists, with their confirmation link."
+            }
+            TransactionalEmail::Welcome => {
+                "Sent to new subscribers of single opt-in lists, who are confirmed right away."
+            }
+            TransactionalEmail::UnsubscribeConfirmation => {
+                "Sent to subscribers after they follow an unsubscribe link."
+            }
+        }
+    }
+
+    /// The default subject, HTML and plain text templates.
+    fn files(&self) -> [&'static str; 3] {
+        match self {
+            TransactionalEmail::Confirmation => [
+                "email/confirmation_subject.txt",
+                "email/html_email.html",
+                "email/plain_email.txt",
+            ],
+            TransactionalEmail::Welcome => [
+                "email/welcome_subject.txt",
+                "email/welcome.html",
+                "email/welcome.txt",
+            ],
+            TransactionalEmail::UnsubscribeConfirmation => [
+                "email/unsubscribed_subject.txt",
+                "email/unsubscribed.html",
+                "email/unsubscribed.txt",
+            ],
+        }
+    }
+
+    /// The variables the email is rendered with and sample values for the
+    /// preview, the required ones first.
+    pub fn variables(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            TransactionalEmail::Confirmation => &[(
+                "link",
+                "https://example.com/lists/newsletter/subscriptions/confirm?subscription_token=sample",
+            )],
+            TransactionalEmail::Welcome => &[
+                (
+                    "unsubscribe_link",
+                    "https://example.com/lists/newsletter/subscriptions/unsubscribe?sample",
+                ),
+                ("preferences_link", "https://example.com/preferences?sample"),
+                ("list", "Newsletter"),
+            ],
+            TransactionalEmail::UnsubscribeConfirmation => &[("list", "Newsletter")],
+        }
+    }
+
+    /// The variables both bodies must use, e.g. the confirmation link.
+    pub fn required_variables(&self) -> &'static [&'static str] {
+        match self {
+            TransactionalEmail::Confirmation => &["link"],
+            TransactionalEmail::Welcome => &["unsubscribe_link", "preferences_link"],
+            TransactionalEmail::UnsubscribeConfirmation => &[],
+        }
+    }
+
+    fn sample_context(&self) -> tera::Context {
+        let mut context = tera::Context::new();
+        for (name, value) in self.variables() {
+            context.insert(*name, value);
+        }
+        context
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+/// The templates of a transactional email, as edited by admins.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct EmailTemplate {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+impl EmailTemplate {
+    fn render(
+        &self,
+        context: &tera::Context,
+    ) -> Result<RenderedEmail, (&'static str, tera::Error)> {
+        Ok(RenderedEmail {
+            subject: Tera::one_off(&self.subject, context, false)
+                .map_err(|e| ("subject", e))?
+                .trim()
+                .to_owned(),
+            html: Tera::one_off(&self.html, context, true).map_err(|e| ("HTML body", e))?,
+            text: Tera::one_off(&self.text, context, false).map_err(|e| ("plain text body", e))?,
+        })
+    }
+
+    /// Render the templates with sample data, checking that they can be
+    /// sent: the subject is not blank and both bodies use the required
+    /// variables.
+    pub fn preview(&self, email: TransactionalEmail) -> Result<RenderedEmail, String> {
+        let rendered = self.render(&email.sample_context()).map_err(|(part, e)| {
+            format!("The {} is not a valid template: {}", part, describe(&e))
+        })?;
+        if rendered.subject.is_empty() {
+            return Err("The subject cannot be empty.".into());
+        }
+        for name in email.required_variables() {
+            let (_, value) = email
+                .variables()
+                .iter()
+                .find(|(variable, _)| variable == name)
+                .expect("Required variables have a sample value.");
+            for (part, body) in [
+                ("HTML body", &rendered.html),
+                ("plain text body", &rendered.text),
+            ] {
+                if !(body.contains(value) || body.contains(&tera::escape_html(value))) {
+                    return Err(format!("The {} must use {{{{ {} }}}}.", part, name));
+                }
+            }
+        }
+        Ok(rendered)
+    }
+
+    /// The templates an admin starts from: the default ones for the locale.
+    pub fn from_files(email: TransactionalEmail, locale: &Locale) -> Result<Self, anyhow::Error> {
+        let [subject, html, text] = email.files().map(|template| {
+            let (name, _) = localised_template_name(template, locale);
+            std::fs::read_to_string(format!("templates/{}", name))
+                .with_context(|| format!("Failed to read the {} template.", name))
+        });
+        Ok(Self {
+            subject: subject?,
+            html: html?,
+            text: text?,
+        })
+    }
+}
+
+/// Render a transactional email in the subscriber's locale.
+/// Admin versions win over the template files of the same locale, the most
+/// specific locale wins overall.
+#[tracing::instrument(name = "Render a transactional email", skip(pool, context))]
+pub async fn render_email(
+    pool: &PgPool,
+    email: TransactionalEmail,
+    locale: &Locale,
+    context: &tera::Context,
+) -> Result<RenderedEmail, anyhow::Error> {
+    let [subject, html, text] = email.files();
+    let (_, files_locale) = localised_template_name(html, locale);
+    let mut candidates = locale.fallbacks();
+    let default_locale = Locale::default();
+    if !candidates.contains(&default_locale.as_ref()) {
+        candidates.push(default_locale.as_ref());
+    }
+    let mut saved = get_current_templates(pool, email, &candidates).await?;
+    for candidate in candidates {
+        if let Some(template) = saved.remove(candidate) {
+            return template.render(context).map_err(|(part, e)| {
+                anyhow::anyhow!(
+                    "The saved {} of the {} email cannot be rendered: {}",
+                    part,
+                    email.as_str(),
+                    describe(&e)
+                )
+            });
+        }
+        if candidate == files_locale {
+            break;
+        }
+    }
+    Ok(RenderedEmail {
+        subject: render_localised(subject, locale, context)?
+            .trim()
+            .to_owned(),
+        html: render_localised(html, locale, context)?,
+        text: render_localised(text, locale, context)?,
+    })
+}
+
+/// The latest saved version of an email for each of the locales.
+#[tracing::instrument(name = "Get the current email templates", skip(pool))]
+async fn get_current_templates(
+    pool: &PgPool,
+    email: TransactionalEmail,
+    locales: &[&str],
+) -> Result<HashMap<String, EmailTemplate>, anyhow::Error> {
+    let locales: Vec<String> = locales.iter().map(|locale| locale.to_string()).collect();
+    let templates = sqlx::query!(
+        r#"
+        SELECT DISTINCT ON (locale) locale, subject, html, text
+        FROM email_templates
+        WHERE email = $1 AND locale = ANY($2)
+        ORDER BY locale, version DESC
+        "#,
+        email.as_str(),
+        &locales
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the saved email templates.")?
+    .into_iter()
+    .map(|r| {
+        (
+            r.locale,
+            EmailTemplate {
+                subject: r.subject,
+                html: r.html,
+                text: r.text,
+            },
+        )
+    })
+    .collect();
+    Ok(templates)
+}
+
+#[derive(serde::Serialize)]
+pub struct EmailTemplateVersion {
+    pub version: i32,
+    pub created_by: String,
+    pub created_at: String,
+    #[serde(flatten)]
+    pub template: EmailTemplate,
+}
+
+/// The saved versions of an email for a locale, the current one first.
+#[tracing::instrument(name = "Get the versions of an email template", skip(pool))]
+pub async fn get_versions(
+    pool: &PgPool,
+    email: TransactionalEmail,
+    locale: &Locale,
+) -> Result<Vec<EmailTemplateVersion>, anyhow::Error> {
+    let versions = sqlx::query!(
+        r#"
+        SELECT version, created_by, created_at, subject, html, text
+        FROM email_templates
+        WHERE email = $1 AND locale = $2
+        ORDER BY version DESC
+        "#,
+        email.as_str(),
+        locale.as_ref()
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the versions of an email template.")?
+    .into_iter()
+    .map(|r| EmailTemplateVersion {
+        version: r.version,
+        created_by: r.created_by,
+        created_at: r.created_at.format("%Y-%m-%d %H:%M").to_string(),
+        template: EmailTemplate {
+            subject: r.subject,
+            html: r.html,
+            text: r.text,
+        },
+    })
+    .collect();
+    Ok(versions)
+}
+
+/// Store a new version of an email for a locale, which becomes the current
+/// one. Returns its number.
+#[tracing::instrument(name = "Save an email template", skip(executor, template))]
+pub async fn save_version(
+    executor: impl PgExecutor<'_>,
+    email: TransactionalEmail,
+    locale: &Locale,
+    template: &EmailTemplate,
+    created_by: &str,
+) -> Result<i32, anyhow::Error> {
+    let version = sqlx::query!(
+        r#"
+        INSERT INTO email_templates (email, locale, version, subject, html, text, created_by, created_at)
+        SELECT $1, $2, COALESCE(MAX(version), 0) + 1, $3, $4, $5, $6, now()
+        FROM email_templates
+        WHERE email = $1 AND locale = $2
+        RETURNING version
+        "#,
+        email.as_str(),
+        locale.as_ref(),
+        template.subject,
+        template.html,
+        template.text,
+        created_by
+    )
+    .fetch_one(executor)
+    .await
+    .context("Failed to save the email template.")?
+    .version;
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EmailTemplate, TransactionalEmail};
+    use claim::{assert_err, assert_ok};
+
+    fn template(subject: &str, html: &str, text: &str) -> EmailTemplate {
+        EmailTemplate {
+            subject: subject.into(),
+            html: html.into(),
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn emails_are_parsed_from_their_names() {
+        for email in TransactionalEmail::ALL {
+            assert_eq!(TransactionalEmail::parse(email.as_str()), Ok(email));
+        }
+    }
+
+    #[test]
+    fn templates_using_the_required_variables_are_valid() {
+        assert_ok!(template(
+            "Confirm",
+            r#"<a href="{{ link }}">Confirm</a>"#,
+            "Confirm: {{ link | safe }}"
+        )
+        .preview(TransactionalEmail::Confirmation));
+    }
+
+    #[test]
+    fn templates_missing_a_required_variable_are_rejected() {
+        let preview = template("Confirm", r#"<a href="{{ link }}">Confirm</a>"#, "Confirm!")
+            .preview(TransactionalEmail::Confirmation);
+        assert_eq!(
+            preview.err().as_deref(),
+            Some("The plain text body must use {{ link }}.")
+        );
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        assert_err!(
+            template("Confirm", "{{ link", "{{ link }}").preview(TransactionalEmail::Confirmation)
+        );
+        assert_err!(template("Bye", "{{ unknown }}", "")
+            .preview(TransactionalEmail::UnsubscribeConfirmation));
+    }
+
+    #[test]
+    fn blank_subjects_are_rejected() {
+        assert_err!(template("{{ '' }} ", "Bye", "Bye")
+            .preview(TransactionalEmail::UnsubscribeConfirmation));
+    }
+}
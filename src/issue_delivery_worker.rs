@@ -1,9 +1,19 @@
 use crate::{
-    configuration::Settings, domain::SubscriberEmail, email_client::EmailClient,
-    startup::get_connection_pool,
+    configuration::Settings,
+    confirmation_email_worker::try_send_confirmation_email,
+    domain::{ListSlug, Locale, SubscriberEmail},
+    email_client::EmailClient,
+    merge_tags::{render_issue, Recipient},
+    routes::{
+        is_suppressed, open_tracking_link, preferences_link, render_localised, track_links,
+        unsubscribe_link, view_in_browser_link,
+    },
+    sequences::{advance_sequence, is_stale_sequence_step},
+    startup::{get_connection_pool, HmacSecret},
 };
 use chrono::Utc;
 use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashMap;
 use std::time::Duration;
 use tracing::{field::display, Span};
 use uuid::Uuid;
@@ -24,38 +34,143 @@ pub enum ExecutionOutcome {
 pub async fn try_execute_task(
     pool: &PgPool,
     email_client: &EmailClient,
+    base_url: &str,
+    hmac_secret: &HmacSecret,
     max_retries: u64,
     execute_after_seconds: u64,
 ) -> Result<ExecutionOutcome, anyhow::Error> {
     let task = dequeue_task(pool, max_retries).await?;
-    dbg!(&task);
     if task.is_none() {
         return Ok(ExecutionOutcome::EmptyQueue);
     }
-    let (transaction, issue_id, email) = task.unwrap();
+    let (mut transaction, issue_id, email, subscriber_id, digest) = task.unwrap();
     Span::current()
         .record("newsletter_issue_id", &display(issue_id))
         .record("subscriber_email", &display(&email));
+    // Issues due in the same digest are sent together. A digest of a single
+    // issue is sent as the issue itself.
+    let digest_tasks = if digest {
+        dequeue_digest_tasks(&mut transaction, issue_id, &email, max_retries).await?
+    } else {
+        vec![]
+    };
     match SubscriberEmail::parse(email.clone()) {
+        Ok(email) if is_suppressed(pool, email.as_ref()).await? => {
+            // The address was suppressed after the issue was enqueued.
+            tracing::info!("Skipping a suppressed address.");
+            log_delivery_attempt(&mut transaction, issue_id, email.as_ref(), "suppressed").await?;
+        }
+        Ok(email) if is_stale_sequence_step(pool, issue_id, subscriber_id).await? => {
+            tracing::info!("Skipping a sequence email to a subscriber who left the list.");
+            log_delivery_attempt(&mut transaction, issue_id, email.as_ref(), "skipped").await?;
+        }
+        Ok(email) if !digest_tasks.is_empty() => {
+            let mut tasks = vec![(issue_id, subscriber_id)];
+            tasks.extend(digest_tasks);
+            let digest = build_digest(pool, &email, &tasks, base_url, hmac_secret).await?;
+            let sent = match &digest.email {
+                Some((subject, html_content, text_content)) => email_client
+                    .send_email(&email, subject, html_content, text_content)
+                    .await
+                    .map_err(|e| {
+                        tracing::error!(
+                            error.cause_chain = ?e,
+                            error.message = %e,
+                            "Failed to deliver a digest to a confirmed subscriber. \
+                             Retrying later.",
+                        );
+                    })
+                    .is_ok(),
+                None => true,
+            };
+            for (task_issue_id, _) in &tasks {
+                let outcome = if digest.skipped.contains(task_issue_id) {
+                    "skipped"
+                } else if sent {
+                    "delivered"
+                } else {
+                    "failed"
+                };
+                log_delivery_attempt(&mut transaction, *task_issue_id, email.as_ref(), outcome)
+                    .await?;
+            }
+            let issue_ids: Vec<Uuid> = tasks.iter().map(|(issue_id, _)| *issue_id).collect();
+            if sent {
+                delete_digest_tasks(transaction, &issue_ids, email.as_ref()).await?;
+            } else {
+                retry_later_digest_tasks(transaction, &issue_ids, &email, execute_after_seconds)
+                    .await?;
+            }
+            return Ok(ExecutionOutcome::TaskCompleted);
+        }
         Ok(email) => {
             let issue = get_issue(pool, issue_id).await?;
+            let (recipient_id, recipient, unsubscribe_url, preferences_url) =
+                get_recipient(pool, subscriber_id, &email, base_url, hmac_secret).await?;
+            let view_in_browser_url = view_in_browser_link(
+                base_url,
+                &issue.slug,
+                issue.visibility == "public",
+                hmac_secret,
+            );
+            let (track_opens, track_clicks) = (issue.track_opens, issue.track_clicks);
+            let locale = Locale::parse(&recipient.locale).unwrap_or_default();
+            let issue = match render_issue(
+                &issue.title,
+                &issue.html_content,
+                &issue.text_content,
+                &recipient,
+                &unsubscribe_url,
+            ) {
+                Ok(issue) => issue,
+                Err(e) => {
+                    // Rendering is deterministic: retrying would not help.
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "Skipping a confirmed subscriber. \
+                         The issue could not be rendered for them.",
+                    );
+                    log_delivery_attempt(&mut transaction, issue_id, email.as_ref(), "skipped")
+                        .await?;
+                    advance_sequence(&mut transaction, issue_id, subscriber_id).await?;
+                    delete_task(transaction, issue_id, email.as_ref()).await?;
+                    return Ok(ExecutionOutcome::TaskCompleted);
+                }
+            };
+            let (mut html_content, mut text_content) = with_view_in_browser_link(
+                &issue.html_content,
+                &issue.text_content,
+                &view_in_browser_url,
+                &locale,
+            )?;
+            if !preferences_url.is_empty() {
+                (html_content, text_content) =
+                    with_preferences_link(&html_content, &text_content, &preferences_url, &locale)?;
+            }
+            if let Some(recipient_id) = recipient_id.filter(|_| track_clicks) {
+                html_content =
+                    track_links(&html_content, base_url, issue_id, recipient_id, hmac_secret);
+            }
+            if let Some(recipient_id) = recipient_id.filter(|_| track_opens) {
+                let pixel_url = open_tracking_link(base_url, issue_id, recipient_id, hmac_secret);
+                html_content = with_open_tracking_pixel(&html_content, &pixel_url);
+            }
             if let Err(e) = email_client
-                .send_email(
-                    &email,
-                    &issue.title,
-                    &issue.html_content,
-                    &issue.text_content,
-                )
+                .send_email(&email, &issue.title, &html_content, &text_content)
                 .await
             {
                 tracing::error!(
                     error.cause_chain = ?e,
                     error.message = %e,
                     "Failed to deliver issue to a confirmed subscriber. \
-                     Skipping.",
+                     Retrying later.",
                 );
-                retry_later_task(pool, issue_id, &email, execute_after_seconds).await?;
+                log_delivery_attempt(&mut transaction, issue_id, email.as_ref(), "failed").await?;
+                retry_later_task(transaction, issue_id, &email, execute_after_seconds).await?;
+                return Ok(ExecutionOutcome::TaskCompleted);
             }
+            log_delivery_attempt(&mut transaction, issue_id, email.as_ref(), "delivered").await?;
         }
         Err(e) => {
             tracing::error!(
@@ -65,6 +180,7 @@ pub async fn try_execute_task(
         }
     }
 
+    advance_sequence(&mut transaction, issue_id, subscriber_id).await?;
     delete_task(transaction, issue_id, &email).await?;
 
     Ok(ExecutionOutcome::TaskCompleted)
@@ -72,18 +188,17 @@ pub async fn try_execute_task(
 
 type PgTransaction = Transaction<'static, Postgres>;
 
+type Task = (PgTransaction, Uuid, String, Option<Uuid>, bool);
+
 #[tracing::instrument(skip_all)]
-async fn dequeue_task(
-    pool: &PgPool,
-    max_retries: u64,
-) -> Result<Option<(PgTransaction, Uuid, String)>, anyhow::Error> {
+async fn dequeue_task(pool: &PgPool, max_retries: u64) -> Result<Option<Task>, anyhow::Error> {
     let mut transaction = pool.begin().await?;
     let r = sqlx::query!(
         r#"
-            SELECT newsletter_issue_id, subscriber_email
+            SELECT newsletter_issue_id, subscriber_email, subscriber_id, digest
               FROM issue_delivery_queue
-             WHERE n_retries     < $1
-               AND execute_after > CURRENT_TIMESTAMP
+             WHERE n_retries     <= $1
+               AND execute_after <= CURRENT_TIMESTAMP
             FOR UPDATE
             SKIP LOCKED
             LIMIT 1
@@ -98,14 +213,50 @@ async fn dequeue_task(
             transaction,
             r.newsletter_issue_id,
             r.subscriber_email,
+            r.subscriber_id,
+            r.digest,
         )))
     } else {
         Ok(None)
     }
 }
 
+/// The other issues due in the digest of an address, as
+/// `(newsletter_issue_id, subscriber_id)`, locked like the task itself.
+#[tracing::instrument(skip_all)]
+async fn dequeue_digest_tasks(
+    transaction: &mut PgTransaction,
+    issue_id: Uuid,
+    email: &str,
+    max_retries: u64,
+) -> Result<Vec<(Uuid, Option<Uuid>)>, anyhow::Error> {
+    let tasks = sqlx::query!(
+        r#"
+            SELECT newsletter_issue_id, subscriber_id
+              FROM issue_delivery_queue
+             WHERE subscriber_email = $1
+               AND newsletter_issue_id <> $2
+               AND digest
+               AND n_retries     <= $3
+               AND execute_after <= CURRENT_TIMESTAMP
+            FOR UPDATE
+            SKIP LOCKED
+        "#,
+        email,
+        issue_id,
+        max_retries as i64
+    )
+    .fetch_all(transaction)
+    .await?
+    .into_iter()
+    .map(|r| (r.newsletter_issue_id, r.subscriber_id))
+    .collect();
+    Ok(tasks)
+}
+
+#[tracing::instrument(skip_all)]
 async fn retry_later_task(
-    pool: &PgPool,
+    mut transaction: PgTransaction,
     issue_id: Uuid,
     email: &SubscriberEmail,
     seconds: u64,
@@ -114,10 +265,7 @@ async fn retry_later_task(
     sqlx::query!(
         r#"
             UPDATE issue_delivery_queue
-            SET n_retries = (SELECT n_retries
-                               FROM issue_delivery_queue
-                              WHERE newsletter_issue_id = $1
-                                AND subscriber_email = $2 ) + 1,
+            SET n_retries = n_retries + 1,
                 execute_after = $3
             WHERE newsletter_issue_id = $1
               AND subscriber_email = $2 
@@ -126,7 +274,60 @@ async fn retry_later_task(
         email.as_ref(),
         execute_after
     )
-    .execute(pool)
+    .execute(&mut transaction)
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn retry_later_digest_tasks(
+    mut transaction: PgTransaction,
+    issue_ids: &[Uuid],
+    email: &SubscriberEmail,
+    seconds: u64,
+) -> Result<(), anyhow::Error> {
+    let execute_after = Utc::now() + chrono::Duration::seconds(seconds as i64);
+    sqlx::query!(
+        r#"
+            UPDATE issue_delivery_queue
+            SET n_retries = n_retries + 1,
+                execute_after = $3
+            WHERE newsletter_issue_id = ANY($1)
+              AND subscriber_email = $2
+        "#,
+        issue_ids,
+        email.as_ref(),
+        execute_after
+    )
+    .execute(&mut transaction)
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn log_delivery_attempt(
+    transaction: &mut PgTransaction,
+    issue_id: Uuid,
+    email: &str,
+    outcome: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_log (
+            newsletter_issue_id,
+            subscriber_email,
+            outcome,
+            attempted_at
+        )
+        VALUES ($1, $2, $3, now())
+        "#,
+        issue_id,
+        email,
+        outcome
+    )
+    .execute(transaction)
     .await?;
     Ok(())
 }
@@ -153,10 +354,221 @@ async fn delete_task(
     Ok(())
 }
 
+#[tracing::instrument(skip_all)]
+async fn delete_digest_tasks(
+    mut transaction: PgTransaction,
+    issue_ids: &[Uuid],
+    email: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_queue
+        WHERE
+            newsletter_issue_id = ANY($1) AND
+            subscriber_email = $2
+        "#,
+        issue_ids,
+        email
+    )
+    .execute(&mut transaction)
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
 struct NewsletterIssue {
     title: String,
     text_content: String,
     html_content: String,
+    slug: String,
+    visibility: String,
+    track_opens: bool,
+    track_clicks: bool,
+    published_at: chrono::DateTime<Utc>,
+}
+
+/// The HTML and plain text versions of a link added to every issue, in the
+/// recipient's language.
+fn chrome_link(
+    template: &str,
+    url: &str,
+    locale: &Locale,
+) -> Result<(String, String), tera::Error> {
+    let mut context = tera::Context::new();
+    context.insert("link", url);
+    let html = render_localised(&format!("{}.html", template), locale, &context)?;
+    let text = render_localised(&format!("{}.txt", template), locale, &context)?;
+    Ok((html.trim().to_owned(), text.trim().to_owned()))
+}
+
+/// Put a link to the issue's archive page at the top of both bodies,
+/// right after the opening `<body>` tag of full HTML documents.
+fn with_view_in_browser_link(
+    html: &str,
+    text: &str,
+    url: &str,
+    locale: &Locale,
+) -> Result<(String, String), tera::Error> {
+    let (link, text_link) = chrome_link("email/view_in_browser", url, locale)?;
+    let html = match html
+        .find("<body")
+        .and_then(|start| html[start..].find('>').map(|end| start + end + 1))
+    {
+        Some(i) => format!("{}{}{}", &html[..i], link, &html[i..]),
+        None => format!("{}{}", link, html),
+    };
+    let text = format!("{}\n\n{}", text_link, text);
+    Ok((html, text))
+}
+
+/// Put a link to the subscriber's preferences page at the bottom of both
+/// bodies, right before the closing `</body>` tag of full HTML documents.
+fn with_preferences_link(
+    html: &str,
+    text: &str,
+    url: &str,
+    locale: &Locale,
+) -> Result<(String, String), tera::Error> {
+    let (link, text_link) = chrome_link("email/manage_preferences", url, locale)?;
+    let html = match html.rfind("</body>") {
+        Some(i) => format!("{}{}{}", &html[..i], link, &html[i..]),
+        None => format!("{}{}", html, link),
+    };
+    let text = format!("{}\n\n{}", text, text_link);
+    Ok((html, text))
+}
+
+/// The content of the `<body>` of full HTML documents, the whole HTML otherwise.
+fn body_content(html: &str) -> &str {
+    let start = html
+        .find("<body")
+        .and_then(|start| html[start..].find('>').map(|end| start + end + 1));
+    match (start, html.rfind("</body>")) {
+        (Some(start), Some(end)) if start <= end => &html[start..end],
+        _ => html,
+    }
+}
+
+#[derive(serde::Serialize)]
+struct DigestIssue {
+    title: String,
+    view_in_browser_url: String,
+    html_content: String,
+    text_content: String,
+}
+
+struct Digest {
+    /// The subject and bodies, `None` if no issue could be rendered.
+    email: Option<(String, String, String)>,
+    /// The issues left out because they could not be rendered.
+    skipped: Vec<Uuid>,
+}
+
+/// Render every issue for the recipient and put them together in a single
+/// email, in the order they were published. Each issue keeps its own
+/// tracking, and the digest gets a link to the recipient's preferences.
+#[tracing::instrument(skip_all)]
+async fn build_digest(
+    pool: &PgPool,
+    email: &SubscriberEmail,
+    tasks: &[(Uuid, Option<Uuid>)],
+    base_url: &str,
+    hmac_secret: &HmacSecret,
+) -> Result<Digest, anyhow::Error> {
+    let mut issues = Vec::new();
+    let mut skipped = Vec::new();
+    let mut locale = None;
+    let mut preferences_url = String::new();
+    for (issue_id, subscriber_id) in tasks {
+        let issue = get_issue(pool, *issue_id).await?;
+        let (recipient_id, recipient, unsubscribe_url, recipient_preferences_url) =
+            get_recipient(pool, *subscriber_id, email, base_url, hmac_secret).await?;
+        let rendered = match render_issue(
+            &issue.title,
+            &issue.html_content,
+            &issue.text_content,
+            &recipient,
+            &unsubscribe_url,
+        ) {
+            Ok(rendered) => rendered,
+            Err(e) => {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Leaving an issue out of a digest. \
+                     It could not be rendered for the subscriber.",
+                );
+                skipped.push(*issue_id);
+                continue;
+            }
+        };
+        locale.get_or_insert_with(|| Locale::parse(&recipient.locale).unwrap_or_default());
+        if preferences_url.is_empty() {
+            preferences_url = recipient_preferences_url;
+        }
+        let mut html_content = body_content(&rendered.html_content).to_owned();
+        if let Some(recipient_id) = recipient_id.filter(|_| issue.track_clicks) {
+            html_content = track_links(
+                &html_content,
+                base_url,
+                *issue_id,
+                recipient_id,
+                hmac_secret,
+            );
+        }
+        if let Some(recipient_id) = recipient_id.filter(|_| issue.track_opens) {
+            let pixel_url = open_tracking_link(base_url, *issue_id, recipient_id, hmac_secret);
+            html_content = with_open_tracking_pixel(&html_content, &pixel_url);
+        }
+        issues.push((
+            issue.published_at,
+            DigestIssue {
+                title: rendered.title,
+                view_in_browser_url: view_in_browser_link(
+                    base_url,
+                    &issue.slug,
+                    issue.visibility == "public",
+                    hmac_secret,
+                ),
+                html_content,
+                text_content: rendered.text_content,
+            },
+        ));
+    }
+    if issues.is_empty() {
+        return Ok(Digest {
+            email: None,
+            skipped,
+        });
+    }
+    issues.sort_by_key(|(published_at, _)| *published_at);
+    let issues: Vec<DigestIssue> = issues.into_iter().map(|(_, issue)| issue).collect();
+    let locale = locale.unwrap_or_default();
+    let mut context = tera::Context::new();
+    context.insert("issues", &issues);
+    let subject = render_localised("email/digest_subject.txt", &locale, &context)?;
+    let mut html_content = render_localised("email/digest.html", &locale, &context)?;
+    let mut text_content = render_localised("email/digest.txt", &locale, &context)?;
+    if !preferences_url.is_empty() {
+        (html_content, text_content) =
+            with_preferences_link(&html_content, &text_content, &preferences_url, &locale)?;
+    }
+    Ok(Digest {
+        email: Some((subject.trim().to_owned(), html_content, text_content)),
+        skipped,
+    })
+}
+
+/// Append an invisible image to the HTML body, inside `<body>` if there is one.
+fn with_open_tracking_pixel(html: &str, url: &str) -> String {
+    let pixel = format!(
+        r#"<img src="{}" width="1" height="1" alt="" style="border: 0;">"#,
+        tera::escape_html(url)
+    );
+    match html.rfind("</body>") {
+        Some(i) => format!("{}{}{}", &html[..i], pixel, &html[i..]),
+        None => format!("{}{}", html, pixel),
+    }
 }
 
 #[tracing::instrument(skip_all)]
@@ -164,7 +576,9 @@ async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, any
     let issue = sqlx::query_as!(
         NewsletterIssue,
         r#"
-        SELECT title, text_content, html_content
+        SELECT
+            title, text_content, html_content, slug, visibility, track_opens, track_clicks,
+            published_at
         FROM newsletter_issues
         WHERE
             newsletter_issue_id = $1 
@@ -176,21 +590,115 @@ async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, any
     Ok(issue)
 }
 
+/// The subscription a recipient is receiving the issue through, their
+/// personalisation data, the link to leave that list and the link to their
+/// preferences page.
+/// Queue rows created before issues were personalised have no subscriber:
+/// the address' oldest subscription is used instead.
+/// Everything is left blank if the subscription is gone.
+#[tracing::instrument(skip_all)]
+async fn get_recipient(
+    pool: &PgPool,
+    subscriber_id: Option<Uuid>,
+    email: &SubscriberEmail,
+    base_url: &str,
+    hmac_secret: &HmacSecret,
+) -> Result<(Option<Uuid>, Recipient, String, String), anyhow::Error> {
+    let r = sqlx::query!(
+        r#"
+        SELECT
+            subscriptions.id,
+            subscriptions.name,
+            subscriptions.locale,
+            lists.slug,
+            ARRAY(
+                SELECT key FROM subscriber_attributes
+                WHERE subscriber_id = subscriptions.id
+                ORDER BY key
+            ) AS "attribute_keys!",
+            ARRAY(
+                SELECT value FROM subscriber_attributes
+                WHERE subscriber_id = subscriptions.id
+                ORDER BY key
+            ) AS "attribute_values!"
+        FROM subscriptions
+        JOIN lists USING (list_id)
+        WHERE subscriptions.id = $1 OR ($1 IS NULL AND lower(subscriptions.email) = lower($2))
+        ORDER BY subscriptions.subscribed_at
+        LIMIT 1
+        "#,
+        subscriber_id,
+        email.as_ref()
+    )
+    .fetch_optional(pool)
+    .await?;
+    let recipient = Recipient {
+        name: r.as_ref().map(|r| r.name.clone()).unwrap_or_default(),
+        email: email.as_ref().to_owned(),
+        attributes: r
+            .as_ref()
+            .map(|r| {
+                r.attribute_keys
+                    .iter()
+                    .cloned()
+                    .zip(r.attribute_values.iter().cloned())
+                    .collect()
+            })
+            .unwrap_or_else(HashMap::new),
+        locale: r
+            .as_ref()
+            .map(|r| r.locale.clone())
+            .unwrap_or_else(|| Locale::default().to_string()),
+    };
+    let recipient_id = r.as_ref().map(|r| r.id);
+    let preferences_url = recipient_id
+        .map(|id| preferences_link(base_url, id, hmac_secret))
+        .unwrap_or_default();
+    let unsubscribe_url = r
+        .and_then(|r| {
+            let list_slug = ListSlug::parse(r.slug).ok()?;
+            Some(unsubscribe_link(base_url, &list_slug, r.id, hmac_secret))
+        })
+        .unwrap_or_default();
+    Ok((recipient_id, recipient, unsubscribe_url, preferences_url))
+}
+
 async fn worker_loop(
     pool: PgPool,
     email_client: EmailClient,
+    base_url: String,
+    hmac_secret: HmacSecret,
+    confirmation_token_ttl: chrono::Duration,
     max_retries: u64,
     execute_after_seconds: u64,
 ) -> Result<(), anyhow::Error> {
     loop {
-        match try_execute_task(&pool, &email_client, max_retries, execute_after_seconds).await {
-            Ok(ExecutionOutcome::EmptyQueue) => {
+        let confirmation = try_send_confirmation_email(
+            &pool,
+            &email_client,
+            &base_url,
+            confirmation_token_ttl,
+            max_retries,
+            execute_after_seconds,
+        )
+        .await;
+        let delivery = try_execute_task(
+            &pool,
+            &email_client,
+            &base_url,
+            &hmac_secret,
+            max_retries,
+            execute_after_seconds,
+        )
+        .await;
+        match (confirmation, delivery) {
+            (Ok(ExecutionOutcome::EmptyQueue), Ok(ExecutionOutcome::EmptyQueue)) => {
                 tokio::time::sleep(Duration::from_secs(10)).await;
             }
-            Err(_) => {
+            (Err(_), _) | (_, Err(_)) => {
                 tokio::time::sleep(Duration::from_secs(1)).await;
             }
-            Ok(ExecutionOutcome::TaskCompleted) => {}
+            _ => {}
         }
     }
 }
@@ -201,6 +709,9 @@ pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), any
     worker_loop(
         connection_pool,
         email_client,
+        configuration.application.base_url,
+        HmacSecret(configuration.application.hmac_secret),
+        configuration.subscriptions.confirmation_token_ttl(),
         configuration.worker.max_retries,
         configuration.worker.execute_after_seconds,
     )
@@ -1,10 +1,18 @@
+pub mod abuse_protection;
 pub mod authentication;
 pub mod configuration;
+pub mod confirmation_email_worker;
+pub mod consent;
 pub mod domain;
 pub mod email_client;
+pub mod email_policy;
+pub mod email_templates;
 pub mod idempotency;
 pub mod issue_delivery_worker;
+pub mod markdown;
+pub mod merge_tags;
 pub mod routes;
+pub mod sequences;
 pub mod session_state;
 pub mod startup;
 pub mod telemetry;
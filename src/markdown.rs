@@ -0,0 +1,218 @@
+//! Markdown authoring of newsletter issues.
+//!
+//! Merge tags (see `merge_tags`) are set aside before parsing and put back
+//! afterwards, so that Markdown rendering and HTML sanitization leave them
+//! untouched, e.g. `[Unsubscribe]({{ unsubscribe_url }})` still works.
+//! Only variables, optionally with a `default`, are set aside: anything else,
+//! e.g. a `safe` filter or a `{% ... %}` tag, would let unsanitized HTML
+//! through, and is left as text instead.
+use pulldown_cmark::{html, Event, Options, Parser, Tag};
+
+fn placeholder(index: usize) -> String {
+    format!("mergetag{}placeholder", index)
+}
+
+/// Whether a merge tag only outputs a variable, e.g. `{{ subscriber.name }}`
+/// or `{{ subscriber.attributes.city | default(value="there") }}`, which Tera
+/// escapes in HTML bodies.
+fn is_plain_merge_tag(merge_tag: &str) -> bool {
+    let expression = match merge_tag
+        .strip_prefix("{{")
+        .and_then(|tag| tag.strip_suffix("}}"))
+    {
+        Some(expression) => expression,
+        None => return false,
+    };
+    let (variable, filter) = match expression.split_once('|') {
+        Some((variable, filter)) => (variable, Some(filter.trim())),
+        None => (expression, None),
+    };
+    let is_identifier = |s: &str| {
+        matches!(s.chars().next(), Some(c) if !c.is_ascii_digit())
+            && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    let is_default = |filter: &str| {
+        let value = filter
+            .strip_prefix("default(value=\"")
+            .and_then(|filter| filter.strip_suffix("\")"));
+        matches!(value, Some(value) if !value.contains('"'))
+    };
+    variable.trim().split('.').all(is_identifier)
+        && match filter {
+            Some(filter) => is_default(filter),
+            None => true,
+        }
+}
+
+/// Replace every plain `{{ ... }}` with a placeholder.
+fn protect_merge_tags(source: &str) -> (String, Vec<String>) {
+    let mut protected = String::with_capacity(source.len());
+    let mut merge_tags = Vec::new();
+    let mut rest = source;
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start + 2..].find("}}") {
+            Some(length) => start + 2 + length + 2,
+            None => break,
+        };
+        let merge_tag = &rest[start..end];
+        if is_plain_merge_tag(merge_tag) {
+            protected.push_str(&rest[..start]);
+            protected.push_str(&placeholder(merge_tags.len()));
+            merge_tags.push(merge_tag.to_owned());
+        } else {
+            protected.push_str(&rest[..end]);
+        }
+        rest = &rest[end..];
+    }
+    protected.push_str(rest);
+    (protected, merge_tags)
+}
+
+fn restore_merge_tags(mut rendered: String, merge_tags: &[String]) -> String {
+    for (index, merge_tag) in merge_tags.iter().enumerate() {
+        rendered = rendered.replace(&placeholder(index), merge_tag);
+    }
+    rendered
+}
+
+fn parser(markdown: &str) -> Parser<'_, '_> {
+    Parser::new_ext(
+        markdown,
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH,
+    )
+}
+
+/// Render Markdown to HTML, dropping anything unsafe such as scripts or
+/// event handlers that raw HTML in the source may contain.
+/// Braces are escaped, so that only the merge tags set aside are templated.
+pub fn to_html(markdown: &str) -> String {
+    let (markdown, merge_tags) = protect_merge_tags(markdown);
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, parser(&markdown));
+    let html = ammonia::clean(&unsafe_html).replace('{', "&#123;");
+    restore_merge_tags(html, &merge_tags)
+}
+
+/// Derive a readable plain text version of a Markdown document:
+/// formatting is dropped, links are followed by their target and
+/// list items are prefixed by a dash or their number.
+pub fn to_text(markdown: &str) -> String {
+    let (markdown, merge_tags) = protect_merge_tags(markdown);
+    let mut text = String::new();
+    // The next number of each enclosing list, `None` for bullet lists.
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    for event in parser(&markdown) {
+        match event {
+            Event::Text(s) | Event::Code(s) => text.push_str(&s),
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::Rule => text.push_str("---\n\n"),
+            Event::Start(Tag::List(first_number)) => lists.push(first_number),
+            Event::End(Tag::List(_)) => {
+                lists.pop();
+                if lists.is_empty() {
+                    text.push('\n');
+                }
+            }
+            Event::Start(Tag::Item) => {
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                text.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        text.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => text.push_str("- "),
+                }
+            }
+            Event::End(Tag::Item) if !text.ends_with('\n') => text.push('\n'),
+            Event::End(Tag::Link(_, url, _)) if !text.ends_with(url.as_ref()) => {
+                text.push_str(&format!(" ({})", url));
+            }
+            Event::End(Tag::Paragraph)
+            | Event::End(Tag::Heading(..))
+            | Event::End(Tag::CodeBlock(_))
+            | Event::End(Tag::TableRow)
+            | Event::End(Tag::TableHead) => {
+                if lists.is_empty() {
+                    text.push_str("\n\n");
+                } else {
+                    text.push('\n');
+                }
+            }
+            Event::End(Tag::TableCell) => text.push('\t'),
+            _ => {}
+        }
+    }
+    while text.contains("\n\n\n") {
+        text = text.replace("\n\n\n", "\n\n");
+    }
+    restore_merge_tags(text.trim().to_owned(), &merge_tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_html, to_text};
+
+    #[test]
+    fn markdown_is_rendered_to_html() {
+        let html = to_html("# Title\n\nSome *emphasis* and a [link](https://example.com).");
+        assert!(html.contains("<h1>Title</h1>"));
+        assert!(html.contains("<em>emphasis</em>"));
+        assert!(html.contains("<a href=\"https://example.com\""));
+    }
+
+    #[test]
+    fn unsafe_html_is_removed() {
+        let html = to_html("Hi <script>alert(1)</script><img src=\"x.png\" onerror=\"alert(1)\">");
+        assert!(!html.contains("script"));
+        assert!(!html.contains("onerror"));
+    }
+
+    #[test]
+    fn merge_tags_survive_rendering() {
+        let markdown = "Hi {{ subscriber.attributes.city | default(value=\"there\") }}, \
+            [unsubscribe]({{ unsubscribe_url }}).";
+        let html = to_html(markdown);
+        assert!(html.contains("Hi {{ subscriber.attributes.city | default(value=\"there\") }},"));
+        assert!(html.contains("href=\"{{ unsubscribe_url }}\""));
+        let text = to_text(markdown);
+        assert_eq!(
+            text,
+            "Hi {{ subscriber.attributes.city | default(value=\"there\") }}, \
+            unsubscribe ({{ unsubscribe_url }})."
+        );
+    }
+
+    #[test]
+    fn merge_tags_cannot_smuggle_unsafe_html() {
+        for markdown in [
+            r#"{{ "<script>alert(1)</script>" | safe }}"#,
+            "{{ subscriber.name | safe }}",
+            r#"{% set x = "<script>" %}{{ x }}"#,
+            "&#123;&#123; subscriber.name | safe }}",
+        ] {
+            let html = to_html(markdown);
+            assert!(!html.contains("<script"), "{}", html);
+            assert!(!html.contains("{{"), "{}", html);
+            assert!(!html.contains("{%"), "{}", html);
+        }
+    }
+
+    #[test]
+    fn plain_text_keeps_the_document_structure() {
+        let text = to_text(
+            "# Title\n\nFirst **paragraph**.\n\n- one\n- two\n\n1. first\n2. second\n\nBye",
+        );
+        assert_eq!(
+            text,
+            "Title\n\nFirst paragraph.\n\n- one\n- two\n\n1. first\n2. second\n\nBye"
+        );
+    }
+
+    #[test]
+    fn links_whose_text_is_the_url_are_not_repeated() {
+        assert_eq!(to_text("<https://example.com>"), "https://example.com");
+    }
+}
@@ -0,0 +1,157 @@
+//! Per-recipient personalisation of newsletter issues.
+//!
+//! The title, HTML and plain text bodies of an issue are Tera templates
+//! rendered once per recipient with the following variables:
+//! - `subscriber.name`, `subscriber.email`, `subscriber.locale`;
+//! - `subscriber.attributes.<key>` for each custom attribute;
+//! - `unsubscribe_url`.
+//!
+//! Attributes are not set for every subscriber: use the `default` filter,
+//! e.g. `{{ subscriber.attributes.company | default(value="your company") }}`.
+use std::collections::HashMap;
+use tera::{Context, Tera};
+
+#[derive(serde::Serialize)]
+pub struct Recipient {
+    pub name: String,
+    pub email: String,
+    pub attributes: HashMap<String, String>,
+    /// The language the subscriber asked for, e.g. `fr`.
+    pub locale: String,
+}
+
+pub struct RenderedIssue {
+    pub title: String,
+    pub html_content: String,
+    pub text_content: String,
+}
+
+fn context(recipient: &Recipient, unsubscribe_url: &str) -> Context {
+    let mut context = Context::new();
+    context.insert("subscriber", recipient);
+    context.insert("unsubscribe_url", unsubscribe_url);
+    context
+}
+
+/// Render the issue for a single recipient.
+/// Only the HTML body is escaped.
+pub fn render_issue(
+    title: &str,
+    html_content: &str,
+    text_content: &str,
+    recipient: &Recipient,
+    unsubscribe_url: &str,
+) -> Result<RenderedIssue, tera::Error> {
+    let context = context(recipient, unsubscribe_url);
+    Ok(RenderedIssue {
+        title: Tera::one_off(title, &context, false)?,
+        html_content: Tera::one_off(html_content, &context, true)?,
+        text_content: Tera::one_off(text_content, &context, false)?,
+    })
+}
+
+/// Check that the issue renders for a subscriber without any attribute,
+/// so that it cannot fail half-way through a send.
+pub fn validate_issue(title: &str, html_content: &str, text_content: &str) -> Result<(), String> {
+    let recipient = Recipient {
+        name: "Subscriber".into(),
+        email: "subscriber@example.com".into(),
+        attributes: HashMap::new(),
+        locale: "en".into(),
+    };
+    let context = context(&recipient, "https://example.com/unsubscribe");
+    for (part, template, autoescape) in [
+        ("title", title, false),
+        ("HTML body", html_content, true),
+        ("plain text body", text_content, false),
+    ] {
+        if let Err(e) = Tera::one_off(template, &context, autoescape) {
+            return Err(format!(
+                "The {} is not a valid template: {}",
+                part,
+                describe(&e)
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Tera's top-level error only names the template, the details are in the source chain.
+pub(crate) fn describe(e: &tera::Error) -> String {
+    let mut description = e.to_string();
+    let mut current = std::error::Error::source(e);
+    while let Some(cause) = current {
+        description = format!("{} {}", description, cause);
+        current = cause.source();
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_issue, validate_issue, Recipient};
+    use claim::{assert_err, assert_ok};
+    use std::collections::HashMap;
+
+    fn recipient() -> Recipient {
+        Recipient {
+            name: "Ursula <Le Guin>".into(),
+            email: "ursula_le_guin@gmail.com".into(),
+            attributes: HashMap::from([("company".to_string(), "Earthsea".to_string())]),
+            locale: "fr".into(),
+        }
+    }
+
+    #[test]
+    fn merge_tags_are_replaced_per_recipient() {
+        let issue = render_issue(
+            "News for {{ subscriber.name }}",
+            "<p>{{ subscriber.attributes.company }}</p>",
+            "Leave: {{ unsubscribe_url }}",
+            &recipient(),
+            "https://example.com/u",
+        )
+        .unwrap();
+        assert_eq!(issue.title, "News for Ursula <Le Guin>");
+        assert_eq!(issue.html_content, "<p>Earthsea</p>");
+        assert_eq!(issue.text_content, "Leave: https://example.com/u");
+    }
+
+    #[test]
+    fn values_are_escaped_in_the_html_body_only() {
+        let issue = render_issue(
+            "{{ subscriber.name }}",
+            "{{ subscriber.name }}",
+            "{{ subscriber.name }}",
+            &recipient(),
+            "",
+        )
+        .unwrap();
+        assert_eq!(issue.html_content, "Ursula &lt;Le Guin&gt;");
+        assert_eq!(issue.text_content, "Ursula <Le Guin>");
+    }
+
+    #[test]
+    fn missing_attributes_with_a_default_are_valid() {
+        assert_ok!(validate_issue(
+            "Hi",
+            "<p>{{ subscriber.attributes.city | default(value=\"there\") }}</p>",
+            "{% if subscriber.attributes.city %}{{ subscriber.attributes.city }}{% endif %}",
+        ));
+    }
+
+    #[test]
+    fn missing_attributes_without_a_default_are_rejected() {
+        assert_err!(validate_issue(
+            "Hi",
+            "<p>{{ subscriber.attributes.city }}</p>",
+            "Hi"
+        ));
+    }
+
+    #[test]
+    fn templates_that_do_not_compile_are_rejected() {
+        let error = validate_issue("Hi {{ subscriber.name", "<p>Hi</p>", "Hi").unwrap_err();
+        assert!(error.starts_with("The title is not a valid template"));
+    }
+}
@@ -0,0 +1,146 @@
+use actix_web::error::ErrorNotFound;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::domain::Locale;
+use crate::email_templates::{get_versions, EmailTemplate, TransactionalEmail};
+use crate::routes::{e400, e500, TEMPLATES};
+
+#[derive(serde::Serialize)]
+struct EmailSummary {
+    name: &'static str,
+    description: &'static str,
+    /// The locales with a saved version, and the number of the current one.
+    saved: Vec<(String, i32)>,
+}
+
+pub async fn email_templates(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut error_message = String::new();
+    for m in flash_messages.iter() {
+        writeln!(error_message, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let saved = sqlx::query!(
+        r#"
+        SELECT email, locale, MAX(version) AS "version!"
+        FROM email_templates
+        GROUP BY email, locale
+        ORDER BY email, locale
+        "#
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to retrieve the saved email templates.")
+    .map_err(e500)?;
+    let emails: Vec<EmailSummary> = TransactionalEmail::ALL
+        .into_iter()
+        .map(|email| EmailSummary {
+            name: email.as_str(),
+            description: email.description(),
+            saved: saved
+                .iter()
+                .filter(|r| r.email == email.as_str())
+                .map(|r| (r.locale.clone(), r.version))
+                .collect(),
+        })
+        .collect();
+
+    let mut context = tera::Context::new();
+    context.insert("error_message", &error_message);
+    context.insert("emails", &emails);
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        TEMPLATES
+            .render("admin/emails/list.html", &context)
+            .unwrap(),
+    ))
+}
+
+/// The form submits itself here to preview the templates before saving
+/// them, so whatever was typed in so far is filled back in.
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    #[serde(default)]
+    locale: String,
+    subject: Option<String>,
+    html: Option<String>,
+    text: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct Variable {
+    name: &'static str,
+    required: bool,
+}
+
+/// Edit the templates of an email for a locale, starting from the current
+/// version, and preview them with sample data.
+pub async fn email_template_form(
+    email: web::Path<String>,
+    query: web::Query<QueryParams>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut error_message = String::new();
+    for m in flash_messages.iter() {
+        writeln!(error_message, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let email = TransactionalEmail::parse(&email).map_err(ErrorNotFound)?;
+    let QueryParams {
+        locale,
+        subject,
+        html,
+        text,
+    } = query.into_inner();
+    let locale = if locale.is_empty() {
+        Locale::default()
+    } else {
+        Locale::parse(&locale).map_err(e400)?
+    };
+    let versions = get_versions(&pool, email, &locale).await.map_err(e500)?;
+    let template = match (subject, html, text) {
+        (Some(subject), Some(html), Some(text)) => EmailTemplate {
+            subject,
+            html,
+            text,
+        },
+        _ => match versions.first() {
+            Some(current) => current.template.clone(),
+            None => EmailTemplate::from_files(email, &locale).map_err(e500)?,
+        },
+    };
+    let (preview, preview_error) = match template.preview(email) {
+        Ok(preview) => (Some(preview), String::new()),
+        Err(e) => (None, e),
+    };
+    let required = email.required_variables();
+    let variables: Vec<Variable> = email
+        .variables()
+        .iter()
+        .map(|(name, _)| Variable {
+            name,
+            required: required.contains(name),
+        })
+        .collect();
+
+    let mut context = tera::Context::new();
+    context.insert("error_message", &error_message);
+    context.insert("email", email.as_str());
+    context.insert("description", email.description());
+    context.insert("locale", locale.as_ref());
+    context.insert("variables", &variables);
+    context.insert("template", &template);
+    context.insert("preview", &preview);
+    context.insert("preview_error", &preview_error);
+    context.insert("versions", &versions);
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        TEMPLATES
+            .render("admin/emails/edit.html", &context)
+            .unwrap(),
+    ))
+}
@@ -0,0 +1,4 @@
+mod get;
+pub use get::{email_template_form, email_templates};
+mod post;
+pub use post::{restore_email_template, save_email_template};
@@ -0,0 +1,106 @@
+use actix_web::error::ErrorNotFound;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+
+use crate::authentication::UserId;
+use crate::domain::Locale;
+use crate::email_templates::{get_versions, save_version, EmailTemplate, TransactionalEmail};
+use crate::routes::{e400, e500, get_username, see_other};
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct TemplateForm {
+    locale: String,
+    subject: String,
+    html: String,
+    text: String,
+}
+
+/// Save the templates as the new current version of the email, if they are
+/// valid. Invalid ones are sent back to the form, as they were typed in.
+pub async fn save_email_template(
+    email: web::Path<String>,
+    form: web::Form<TemplateForm>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let email = TransactionalEmail::parse(&email).map_err(ErrorNotFound)?;
+    let locale = Locale::parse(&form.locale).map_err(e400)?;
+    let template = EmailTemplate {
+        subject: form.subject.clone(),
+        html: form.html.clone(),
+        text: form.text.clone(),
+    };
+    if let Err(e) = template.preview(email) {
+        FlashMessage::error(e).send();
+        let query = serde_qs::to_string(&form.0).map_err(e500)?;
+        return Ok(see_other(&format!(
+            "/admin/emails/{}?{}",
+            email.as_str(),
+            query
+        )));
+    }
+    let username = get_username(*user_id.into_inner(), &pool)
+        .await
+        .map_err(e500)?;
+    let version = save_version(pool.get_ref(), email, &locale, &template, &username)
+        .await
+        .map_err(e500)?;
+    FlashMessage::info(format!(
+        "Version {} of the {} email has been saved.",
+        version,
+        email.as_str()
+    ))
+    .send();
+    Ok(see_other(&format!(
+        "/admin/emails/{}?locale={}",
+        email.as_str(),
+        locale
+    )))
+}
+
+#[derive(serde::Deserialize)]
+pub struct RestoreForm {
+    locale: String,
+}
+
+/// Make an older version the current one again, by saving a copy of it.
+pub async fn restore_email_template(
+    path: web::Path<(String, i32)>,
+    form: web::Form<RestoreForm>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (email, version) = path.into_inner();
+    let email = TransactionalEmail::parse(&email).map_err(ErrorNotFound)?;
+    let locale = Locale::parse(&form.locale).map_err(e400)?;
+    let versions = get_versions(&pool, email, &locale).await.map_err(e500)?;
+    let restored = versions
+        .into_iter()
+        .find(|v| v.version == version)
+        .ok_or_else(|| ErrorNotFound("There is no such version of the email."))?;
+    let username = get_username(*user_id.into_inner(), &pool)
+        .await
+        .map_err(e500)?;
+    let new_version = save_version(
+        pool.get_ref(),
+        email,
+        &locale,
+        &restored.template,
+        &username,
+    )
+    .await
+    .map_err(e500)?;
+    FlashMessage::info(format!(
+        "Version {} of the {} email has been restored as version {}.",
+        version,
+        email.as_str(),
+        new_version
+    ))
+    .send();
+    Ok(see_other(&format!(
+        "/admin/emails/{}?locale={}",
+        email.as_str(),
+        locale
+    )))
+}
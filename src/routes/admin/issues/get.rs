@@ -0,0 +1,205 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::routes::{e500, TEMPLATES};
+
+#[derive(serde::Serialize)]
+pub struct IssueStatistics {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    pub slug: String,
+    pub visibility: String,
+    pub published_at: String,
+    pub track_opens: bool,
+    /// Number of distinct addresses the issue reached.
+    pub n_delivered: i64,
+    /// Number of distinct subscribers who opened the issue at least once.
+    pub n_opened: i64,
+    /// Share of delivered copies that were opened, e.g. `42.5%`.
+    pub open_rate: Option<String>,
+    pub last_opened_at: Option<String>,
+    pub track_clicks: bool,
+    /// Number of distinct subscribers who followed at least one link.
+    pub n_clicked: i64,
+    /// Share of delivered copies with at least one click, e.g. `12.5%`.
+    pub click_rate: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct LinkStatistics {
+    pub url: String,
+    pub n_clicks: i64,
+    pub n_unique_clicks: i64,
+    /// Share of delivered copies where this link was followed.
+    pub click_rate: Option<String>,
+}
+
+fn format_timestamp(timestamp: DateTime<Utc>) -> String {
+    timestamp.format("%Y-%m-%d %H:%M").to_string()
+}
+
+fn rate(n: i64, total: i64) -> Option<String> {
+    if total == 0 {
+        None
+    } else {
+        Some(format!("{:.1}%", 100. * n as f64 / total as f64))
+    }
+}
+
+pub async fn published_issues(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut error_message = String::new();
+    for m in flash_messages.iter() {
+        writeln!(error_message, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let issues = get_issue_statistics(&pool, None).await.map_err(e500)?;
+
+    let mut context = tera::Context::new();
+    context.insert("error_message", &error_message);
+    context.insert("issues", &issues);
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        TEMPLATES
+            .render("admin/issues/list.html", &context)
+            .unwrap(),
+    ))
+}
+
+pub async fn issue_details(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut error_message = String::new();
+    for m in flash_messages.iter() {
+        writeln!(error_message, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let issue = match get_issue_statistics(&pool, Some(*issue_id))
+        .await
+        .map_err(e500)?
+        .pop()
+    {
+        Some(issue) => issue,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+
+    let links = get_top_links(&pool, *issue_id, issue.n_delivered)
+        .await
+        .map_err(e500)?;
+
+    let mut context = tera::Context::new();
+    context.insert("error_message", &error_message);
+    context.insert("issue", &issue);
+    context.insert("links", &links);
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        TEMPLATES
+            .render("admin/issues/detail.html", &context)
+            .unwrap(),
+    ))
+}
+
+/// Statistics of every published issue, most recent first, or of a single one.
+#[tracing::instrument(name = "Get issue statistics", skip(pool))]
+async fn get_issue_statistics(
+    pool: &PgPool,
+    issue_id: Option<Uuid>,
+) -> Result<Vec<IssueStatistics>, anyhow::Error> {
+    let issues = sqlx::query!(
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            slug,
+            visibility,
+            published_at,
+            track_opens,
+            (
+                SELECT COUNT(DISTINCT subscriber_email) FROM issue_delivery_log
+                WHERE issue_delivery_log.newsletter_issue_id = newsletter_issues.newsletter_issue_id
+                  AND outcome = 'delivered'
+            ) AS "n_delivered!",
+            (
+                SELECT COUNT(*) FROM issue_opens
+                WHERE issue_opens.newsletter_issue_id = newsletter_issues.newsletter_issue_id
+            ) AS "n_opened!",
+            (
+                SELECT MAX(last_opened_at) FROM issue_opens
+                WHERE issue_opens.newsletter_issue_id = newsletter_issues.newsletter_issue_id
+            ) AS last_opened_at,
+            track_clicks,
+            (
+                SELECT COUNT(DISTINCT subscriber_id) FROM issue_clicks
+                WHERE issue_clicks.newsletter_issue_id = newsletter_issues.newsletter_issue_id
+            ) AS "n_clicked!"
+        FROM newsletter_issues
+        WHERE $1::uuid IS NULL OR newsletter_issue_id = $1
+        ORDER BY published_at DESC
+        "#,
+        issue_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve issue statistics.")?
+    .into_iter()
+    .map(|r| IssueStatistics {
+        newsletter_issue_id: r.newsletter_issue_id,
+        title: r.title,
+        slug: r.slug,
+        visibility: r.visibility,
+        published_at: format_timestamp(r.published_at),
+        track_opens: r.track_opens,
+        n_delivered: r.n_delivered,
+        n_opened: r.n_opened,
+        open_rate: rate(r.n_opened, r.n_delivered),
+        last_opened_at: r.last_opened_at.map(format_timestamp),
+        track_clicks: r.track_clicks,
+        n_clicked: r.n_clicked,
+        click_rate: rate(r.n_clicked, r.n_delivered),
+    })
+    .collect();
+    Ok(issues)
+}
+
+/// The most followed links of an issue.
+#[tracing::instrument(name = "Get top links", skip(pool))]
+async fn get_top_links(
+    pool: &PgPool,
+    issue_id: Uuid,
+    n_delivered: i64,
+) -> Result<Vec<LinkStatistics>, anyhow::Error> {
+    let links = sqlx::query!(
+        r#"
+        SELECT
+            url,
+            SUM(n_clicks) AS "n_clicks!",
+            COUNT(*) AS "n_unique_clicks!"
+        FROM issue_clicks
+        WHERE newsletter_issue_id = $1
+        GROUP BY url
+        ORDER BY 3 DESC, 2 DESC, url
+        LIMIT 10
+        "#,
+        issue_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the top links.")?
+    .into_iter()
+    .map(|r| LinkStatistics {
+        url: r.url,
+        n_clicks: r.n_clicks,
+        n_unique_clicks: r.n_unique_clicks,
+        click_rate: rate(r.n_unique_clicks, n_delivered),
+    })
+    .collect();
+    Ok(links)
+}
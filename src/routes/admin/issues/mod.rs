@@ -0,0 +1,4 @@
+mod get;
+pub use get::{issue_details, published_issues};
+mod post;
+pub use post::set_issue_tracking;
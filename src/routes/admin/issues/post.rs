@@ -0,0 +1,54 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::routes::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    track_opens: Option<bool>,
+    track_clicks: Option<bool>,
+}
+
+/// Copies that have not been sent yet follow the new settings;
+/// opens and clicks of the ones already delivered stop being recorded.
+/// Tracked links keep redirecting.
+pub async fn set_issue_tracking(
+    issue_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET track_opens = COALESCE($2, track_opens),
+            track_clicks = COALESCE($3, track_clicks)
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id,
+        form.track_opens,
+        form.track_clicks
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to update tracking.")
+    .map_err(e500)?
+    .rows_affected();
+    if n_updated_rows == 0 {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    match form.track_opens {
+        Some(true) => FlashMessage::info("Opens of this issue are now tracked.").send(),
+        Some(false) => FlashMessage::info("Opens of this issue are no longer tracked.").send(),
+        None => {}
+    }
+    match form.track_clicks {
+        Some(true) => FlashMessage::info("Clicks in this issue are now tracked.").send(),
+        Some(false) => FlashMessage::info("Clicks in this issue are no longer tracked.").send(),
+        None => {}
+    }
+    Ok(see_other(&format!("/admin/issues/{}", issue_id)))
+}
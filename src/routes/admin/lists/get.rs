@@ -0,0 +1,65 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::configuration::SubscriptionSettings;
+use crate::routes::{e500, TEMPLATES};
+
+#[derive(serde::Serialize)]
+pub struct MailingList {
+    pub list_id: Uuid,
+    pub slug: String,
+    pub name: String,
+    pub n_confirmed_subscribers: i64,
+    /// `None` if the list follows the configuration.
+    pub double_opt_in: Option<bool>,
+}
+
+pub async fn mailing_lists(
+    pool: web::Data<PgPool>,
+    settings: web::Data<SubscriptionSettings>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut error_message = String::new();
+    for m in flash_messages.iter() {
+        writeln!(error_message, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let lists = get_mailing_lists(&pool).await.map_err(e500)?;
+
+    let mut context = tera::Context::new();
+    context.insert("error_message", &error_message);
+    context.insert("lists", &lists);
+    context.insert("default_double_opt_in", &settings.double_opt_in);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(TEMPLATES.render("admin/lists.html", &context).unwrap()))
+}
+
+#[tracing::instrument(name = "Get mailing lists", skip(pool))]
+pub async fn get_mailing_lists(pool: &PgPool) -> Result<Vec<MailingList>, anyhow::Error> {
+    let lists = sqlx::query_as!(
+        MailingList,
+        r#"
+        SELECT
+            lists.list_id,
+            lists.slug,
+            lists.name,
+            COUNT(subscriptions.id) FILTER (WHERE subscriptions.status = 'confirmed')
+                AS "n_confirmed_subscribers!",
+            lists.double_opt_in
+        FROM lists
+        LEFT JOIN subscriptions ON subscriptions.list_id = lists.list_id
+        GROUP BY lists.list_id
+        ORDER BY lists.name
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve mailing lists.")?;
+    Ok(lists)
+}
@@ -0,0 +1,4 @@
+mod get;
+pub use get::{get_mailing_lists, mailing_lists, MailingList};
+mod post;
+pub use post::{create_mailing_list, set_list_double_opt_in};
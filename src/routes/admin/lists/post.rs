@@ -0,0 +1,127 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::ListSlug;
+use crate::routes::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    name: String,
+    slug: String,
+    /// `on`, `off`, or blank to follow the configuration.
+    #[serde(default)]
+    double_opt_in: String,
+}
+
+fn parse_double_opt_in(s: &str) -> Result<Option<bool>, String> {
+    match s {
+        "" => Ok(None),
+        "on" => Ok(Some(true)),
+        "off" => Ok(Some(false)),
+        _ => Err(format!("{} is not a valid double opt-in setting.", s)),
+    }
+}
+
+pub async fn create_mailing_list(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let FormData {
+        name,
+        slug,
+        double_opt_in,
+    } = form.0;
+    let name = name.trim();
+    if name.is_empty() {
+        FlashMessage::error("The list name cannot be empty.").send();
+        return Ok(see_other("/admin/lists"));
+    }
+    let slug = match ListSlug::parse(slug) {
+        Ok(s) => s,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/lists"));
+        }
+    };
+    let double_opt_in = match parse_double_opt_in(&double_opt_in) {
+        Ok(double_opt_in) => double_opt_in,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/lists"));
+        }
+    };
+    let n_inserted_rows = insert_mailing_list(&pool, name, &slug, double_opt_in)
+        .await
+        .context("Failed to store the new mailing list.")
+        .map_err(e500)?;
+    if n_inserted_rows == 0 {
+        FlashMessage::error(format!("A list named {} already exists.", slug)).send();
+    } else {
+        FlashMessage::info(format!("The list {} has been created.", slug)).send();
+    }
+    Ok(see_other("/admin/lists"))
+}
+
+#[tracing::instrument(name = "Saving new mailing list in the database", skip(pool))]
+async fn insert_mailing_list(
+    pool: &PgPool,
+    name: &str,
+    slug: &ListSlug,
+    double_opt_in: Option<bool>,
+) -> Result<u64, sqlx::Error> {
+    let n_inserted_rows = sqlx::query!(
+        r#"
+        INSERT INTO lists (list_id, slug, name, created_at, double_opt_in)
+        VALUES ($1, $2, $3, now(), $4)
+        ON CONFLICT DO NOTHING
+        "#,
+        Uuid::new_v4(),
+        slug.as_ref(),
+        name,
+        double_opt_in
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(n_inserted_rows)
+}
+
+#[derive(serde::Deserialize)]
+pub struct DoubleOptInForm {
+    /// `on`, `off`, or blank to follow the configuration.
+    double_opt_in: String,
+}
+
+/// Choose whether subscribing to a list takes a confirmation email.
+pub async fn set_list_double_opt_in(
+    list_id: web::Path<Uuid>,
+    form: web::Form<DoubleOptInForm>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let double_opt_in = match parse_double_opt_in(&form.double_opt_in) {
+        Ok(double_opt_in) => double_opt_in,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/lists"));
+        }
+    };
+    let n_updated_rows = sqlx::query!(
+        r#"UPDATE lists SET double_opt_in = $2 WHERE list_id = $1"#,
+        list_id.into_inner(),
+        double_opt_in
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to update the mailing list.")
+    .map_err(e500)?
+    .rows_affected();
+    if n_updated_rows == 0 {
+        FlashMessage::error("The list could not be found.").send();
+    } else {
+        FlashMessage::info("The double opt-in setting has been saved.").send();
+    }
+    Ok(see_other("/admin/lists"))
+}
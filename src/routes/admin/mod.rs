@@ -1,11 +1,25 @@
 mod dashboard;
-pub use dashboard::admin_dashboard;
+pub use dashboard::{admin_dashboard, get_username};
+mod emails;
+pub use emails::*;
 mod password;
 pub use password::*;
+mod issues;
+pub use issues::*;
+mod lists;
+pub use lists::*;
 mod logout;
 pub use logout::*;
 mod newsletter;
 pub use newsletter::*;
+mod segments;
+pub use segments::*;
+mod sequences;
+pub use sequences::*;
+mod subscribers;
+pub use subscribers::*;
+mod suppressions;
+pub use suppressions::*;
 
 use actix_web::http::header::LOCATION;
 use actix_web::HttpResponse;
@@ -25,7 +39,7 @@ pub fn see_other(location: &str) -> HttpResponse {
 }
 
 // Return a 400 with the user-representation of the validation error as body. // The error root cause is preserved for logging purposes.
-pub fn e400<T: std::fmt::Debug + std::fmt::Display>(e: T) -> actix_web::Error
+pub fn e400<T>(e: T) -> actix_web::Error
 where
     T: std::fmt::Debug + std::fmt::Display + 'static,
 {
@@ -0,0 +1,36 @@
+use crate::markdown;
+use crate::routes::TEMPLATES;
+
+/// The HTML and plain text bodies of an issue.
+/// Bodies typed in explicitly take precedence over the ones derived from Markdown,
+/// which are wrapped in the email layout.
+pub(crate) fn issue_bodies(
+    markdown: &str,
+    html: String,
+    text: String,
+) -> Result<(String, String), &'static str> {
+    let is_blank = |s: &str| s.trim().is_empty();
+    if is_blank(markdown) {
+        if is_blank(&html) || is_blank(&text) {
+            return Err(
+                "An issue needs either a Markdown body or both an HTML and a plain text body.",
+            );
+        }
+        return Ok((html, text));
+    }
+    let html = if is_blank(&html) {
+        let mut context = tera::Context::new();
+        context.insert("content", &markdown::to_html(markdown));
+        TEMPLATES.render("email/issue.html", &context).unwrap()
+    } else {
+        html
+    };
+    let text = if is_blank(&text) {
+        let mut context = tera::Context::new();
+        context.insert("content", &markdown::to_text(markdown));
+        TEMPLATES.render("email/issue.txt", &context).unwrap()
+    } else {
+        text
+    };
+    Ok((html, text))
+}
@@ -1,20 +1,70 @@
-use actix_web::{http::header::ContentType, HttpResponse};
+use actix_web::{http::header::ContentType, web, HttpRequest, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
 use std::fmt::Write;
+use uuid::Uuid;
 
-use crate::routes::TEMPLATES;
+use super::recipients::{count_recipients, parse_segment_id, selected_list_ids};
+use crate::routes::{e400, e500, get_mailing_lists, get_segments, TEMPLATES};
+
+/// The form submits itself here to preview the recipients before sending,
+/// so whatever was typed in so far is filled back in.
+#[derive(serde::Deserialize, Default)]
+pub struct QueryParams {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    markdown: String,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    html: String,
+    #[serde(default)]
+    list_ids: Vec<Uuid>,
+    segment_id: Option<String>,
+}
 
 pub async fn newsletter_form(
+    request: HttpRequest,
     flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let mut error_message = String::new();
     for m in flash_messages.iter() {
         writeln!(error_message, "<p><i>{}</i></p>", m.content()).unwrap();
     }
-    let idempotency_key = uuid::Uuid::new_v4();
+    // `web::Query` cannot deserialize the repeated `list_ids[]` checkboxes.
+    let QueryParams {
+        title,
+        markdown,
+        text,
+        html,
+        list_ids,
+        segment_id,
+    } = serde_qs::Config::new(2, false)
+        .deserialize_str(request.query_string())
+        .map_err(e400)?;
+    let segment_id = parse_segment_id(segment_id).map_err(e400)?;
+    let list_ids = selected_list_ids(&pool, list_ids).await.map_err(e500)?;
+    let n_recipients = count_recipients(&pool, &list_ids, segment_id)
+        .await
+        .map_err(e500)?;
+
+    let idempotency_key = Uuid::new_v4();
+    let lists = get_mailing_lists(&pool).await.map_err(e500)?;
+    let segments = get_segments(&pool).await.map_err(e500)?;
     let mut context = tera::Context::new();
     context.insert("error_message", &error_message);
     context.insert("idempotency_key", &idempotency_key);
+    context.insert("title", &title);
+    context.insert("markdown", &markdown);
+    context.insert("text", &text);
+    context.insert("html", &html);
+    context.insert("lists", &lists);
+    context.insert("selected_list_ids", &list_ids);
+    context.insert("segments", &segments);
+    context.insert("segment_id", &segment_id);
+    context.insert("n_recipients", &n_recipients);
 
     Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
         TEMPLATES
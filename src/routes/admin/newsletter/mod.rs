@@ -1,4 +1,8 @@
+mod content;
+pub(crate) use content::issue_bodies;
 mod get;
 pub use get::newsletter_form;
 mod post;
 pub use post::publish_newsletter;
+pub(crate) use post::{insert_newsletter_issue, NewIssue};
+mod recipients;
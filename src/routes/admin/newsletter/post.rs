@@ -8,20 +8,37 @@ use sqlx::Postgres;
 use sqlx::Transaction;
 use uuid::Uuid;
 
+use super::content::issue_bodies;
+use super::recipients::{parse_segment_id, segment_exists, selected_list_ids};
 use crate::authentication::UserId;
 use crate::idempotency::save_response;
 use crate::idempotency::try_processing;
 use crate::idempotency::IdempotencyKey;
 use crate::idempotency::NextAction;
+use crate::merge_tags::validate_issue;
 use crate::routes::e400;
 use crate::routes::{e500, see_other};
 
 #[derive(serde::Deserialize)]
 pub struct BodyData {
     title: String,
+    #[serde(default)]
+    markdown: String,
+    #[serde(default)]
     html: String,
+    #[serde(default)]
     text: String,
     idempotency_key: String,
+    #[serde(default)]
+    list_ids: Vec<Uuid>,
+    segment_id: Option<String>,
+    #[serde(default)]
+    visibility: Option<String>,
+    /// An unchecked checkbox is not submitted at all.
+    #[serde(default)]
+    track_opens: Option<String>,
+    #[serde(default)]
+    track_clicks: Option<String>,
 }
 
 fn success_message() -> FlashMessage {
@@ -32,17 +49,39 @@ fn success_message() -> FlashMessage {
 }
 
 pub async fn publish_newsletter(
-    form: web::Form<BodyData>,
+    body: web::Bytes,
     pool: web::Data<PgPool>,
     user_id: ReqData<UserId>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = user_id.into_inner();
+    // `web::Form` cannot deserialize the repeated `list_ids[]` checkboxes.
     let BodyData {
         title,
+        markdown,
         html,
         text,
         idempotency_key,
-    } = form.0;
+        list_ids,
+        segment_id,
+        visibility,
+        track_opens,
+        track_clicks,
+    } = serde_qs::Config::new(2, false)
+        .deserialize_bytes(&body)
+        .map_err(e400)?;
+    let list_ids = selected_list_ids(&pool, list_ids).await.map_err(e500)?;
+    if list_ids.is_empty() {
+        return Err(e400("No mailing list was selected."));
+    }
+    let segment_id = parse_segment_id(segment_id).map_err(e400)?;
+    if let Some(segment_id) = segment_id {
+        if !segment_exists(&pool, segment_id).await.map_err(e500)? {
+            return Err(e400("The selected segment does not exist."));
+        }
+    }
+    let visibility = parse_visibility(visibility).map_err(e400)?;
+    let (html, text) = issue_bodies(&markdown, html, text).map_err(e400)?;
+    validate_issue(&title, &html, &text).map_err(e400)?;
     let idempotency_key: IdempotencyKey = idempotency_key.try_into().map_err(e400)?;
     let mut transaction = match try_processing(&pool, &idempotency_key, *user_id)
         .await
@@ -54,11 +93,20 @@ pub async fn publish_newsletter(
             return Ok(saved_response);
         }
     };
-    let issue_id = insert_newsletter_issue(&mut transaction, &title, &text, &html)
+    let issue = NewIssue {
+        title: &title,
+        text_content: &text,
+        html_content: &html,
+        markdown_content: &markdown,
+        visibility,
+        track_opens: track_opens.is_some(),
+        track_clicks: track_clicks.is_some(),
+    };
+    let issue_id = insert_newsletter_issue(&mut transaction, &issue)
         .await
         .context("Failed to store newsletter issue details")
         .map_err(e500)?;
-    enqueue_delivery_tasks(&mut transaction, issue_id)
+    enqueue_delivery_tasks(&mut transaction, issue_id, &list_ids, segment_id)
         .await
         .context("Failed to enqueue delivery tasks")
         .map_err(e500)?;
@@ -71,14 +119,53 @@ pub async fn publish_newsletter(
     Ok(response)
 }
 
+/// Issues stay out of the public archive unless asked otherwise.
+fn parse_visibility(visibility: Option<String>) -> Result<&'static str, String> {
+    match visibility.as_deref() {
+        None | Some("private") => Ok("private"),
+        Some("public") => Ok("public"),
+        Some(other) => Err(format!("`{}` is not a valid visibility.", other)),
+    }
+}
+
+/// A readable, unique identifier for the issue's archive page,
+/// e.g. `our-first-issue-3f2a9c1b`.
+fn issue_slug(title: &str, issue_id: Uuid) -> String {
+    let mut slug = String::new();
+    for c in title.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.len() >= 60 {
+            break;
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    let slug = if slug.is_empty() { "issue" } else { slug };
+    format!("{}-{}", slug, &issue_id.to_string()[..8])
+}
+
+pub(crate) struct NewIssue<'a> {
+    pub title: &'a str,
+    pub text_content: &'a str,
+    pub html_content: &'a str,
+    pub markdown_content: &'a str,
+    pub visibility: &'a str,
+    pub track_opens: bool,
+    pub track_clicks: bool,
+}
+
 #[tracing::instrument(skip_all)]
-async fn insert_newsletter_issue(
+pub(crate) async fn insert_newsletter_issue(
     transaction: &mut Transaction<'_, Postgres>,
-    title: &str,
-    text_content: &str,
-    html_content: &str,
+    issue: &NewIssue<'_>,
 ) -> Result<Uuid, sqlx::Error> {
     let newsletter_issue_id = Uuid::new_v4();
+    let slug = issue_slug(issue.title, newsletter_issue_id);
+    // Only issues authored in Markdown keep a source.
+    let markdown_content = Some(issue.markdown_content).filter(|s| !s.trim().is_empty());
     sqlx::query!(
         r#"
         INSERT INTO newsletter_issues (
@@ -86,14 +173,24 @@ async fn insert_newsletter_issue(
             title,
             text_content,
             html_content,
+            markdown_content,
+            slug,
+            visibility,
+            track_opens,
+            track_clicks,
             published_at
         )
-        VALUES ($1, $2, $3, $4, now()) 
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, now()) 
         "#,
         newsletter_issue_id,
-        title,
-        text_content,
-        html_content
+        issue.title,
+        issue.text_content,
+        issue.html_content,
+        markdown_content,
+        slug,
+        issue.visibility,
+        issue.track_opens,
+        issue.track_clicks
     )
     .execute(transaction)
     .await?;
@@ -104,18 +201,49 @@ async fn insert_newsletter_issue(
 async fn enqueue_delivery_tasks(
     transaction: &mut Transaction<'_, Postgres>,
     newsletter_issue_id: Uuid,
+    list_ids: &[Uuid],
+    segment_id: Option<Uuid>,
 ) -> Result<(), sqlx::Error> {
+    // Someone subscribed to several of the selected lists gets a single copy,
+    // personalised with their oldest subscription, whatever the case of the
+    // address on each list.
+    // Subscribers who asked for a digest get it at the start of the next
+    // day or week, UTC, whatever the time zone of the session.
+    // Must select the same addresses as `count_recipients`.
     sqlx::query!(
         r#"
         INSERT INTO issue_delivery_queue (
             newsletter_issue_id,
-            subscriber_email
+            subscriber_email,
+            subscriber_id,
+            execute_after,
+            digest
         )
-        SELECT $1, email
+        SELECT DISTINCT ON (lower(email)) $1::uuid, email, id,
+            CASE delivery_frequency
+                WHEN 'daily' THEN
+                    date_trunc('day', now() AT TIME ZONE 'UTC') AT TIME ZONE 'UTC' + interval '1 day'
+                WHEN 'weekly' THEN
+                    date_trunc('week', now() AT TIME ZONE 'UTC') AT TIME ZONE 'UTC' + interval '1 week'
+                ELSE now()
+            END,
+            delivery_frequency <> 'immediate'
         FROM subscriptions
-        WHERE status = 'confirmed' 
+        WHERE status = 'confirmed'
+          AND list_id = ANY($2)
+          AND (paused_until IS NULL OR paused_until <= now())
+          AND NOT EXISTS (
+            SELECT 1 FROM suppressions WHERE suppressions.email_hash = email_hash(subscriptions.email)
+          )
+          AND ($3::uuid IS NULL OR EXISTS (
+            SELECT 1 FROM segments
+            WHERE segment_id = $3 AND subscription_in_segment(subscriptions, segments)
+          ))
+        ORDER BY lower(email), subscribed_at
         "#,
         newsletter_issue_id,
+        list_ids,
+        segment_id
     )
     .execute(transaction)
     .await?;
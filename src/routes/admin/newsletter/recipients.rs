@@ -0,0 +1,71 @@
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::ListSlug;
+use crate::routes::get_list_id;
+
+/// The lists an issue is sent to: the default list when none was picked.
+/// Empty if nothing was picked and the default list has been removed.
+pub(super) async fn selected_list_ids(
+    pool: &PgPool,
+    list_ids: Vec<Uuid>,
+) -> Result<Vec<Uuid>, anyhow::Error> {
+    if !list_ids.is_empty() {
+        return Ok(list_ids);
+    }
+    let default_list_id = get_list_id(pool, &ListSlug::default())
+        .await
+        .context("Failed to look up the default mailing list")?;
+    Ok(default_list_id.into_iter().collect())
+}
+
+/// `<select>` submits an empty string when no segment is picked.
+pub(super) fn parse_segment_id(segment_id: Option<String>) -> Result<Option<Uuid>, uuid::Error> {
+    segment_id
+        .filter(|s| !s.is_empty())
+        .map(|s| Uuid::parse_str(&s))
+        .transpose()
+}
+
+#[tracing::instrument(name = "Check segment exists", skip(pool))]
+pub(super) async fn segment_exists(pool: &PgPool, segment_id: Uuid) -> Result<bool, sqlx::Error> {
+    let segment = sqlx::query!(
+        r#"SELECT segment_id FROM segments WHERE segment_id = $1"#,
+        segment_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(segment.is_some())
+}
+
+/// Must select the same addresses as `enqueue_delivery_tasks`.
+#[tracing::instrument(name = "Count newsletter recipients", skip(pool))]
+pub(super) async fn count_recipients(
+    pool: &PgPool,
+    list_ids: &[Uuid],
+    segment_id: Option<Uuid>,
+) -> Result<i64, sqlx::Error> {
+    let n_recipients = sqlx::query!(
+        r#"
+        SELECT COUNT(DISTINCT lower(email)) AS "count!"
+        FROM subscriptions
+        WHERE status = 'confirmed'
+          AND list_id = ANY($1)
+          AND (paused_until IS NULL OR paused_until <= now())
+          AND NOT EXISTS (
+            SELECT 1 FROM suppressions WHERE suppressions.email_hash = email_hash(subscriptions.email)
+          )
+          AND ($2::uuid IS NULL OR EXISTS (
+            SELECT 1 FROM segments
+            WHERE segment_id = $2 AND subscription_in_segment(subscriptions, segments)
+          ))
+        "#,
+        list_ids,
+        segment_id
+    )
+    .fetch_one(pool)
+    .await?
+    .count;
+    Ok(n_recipients)
+}
@@ -0,0 +1,81 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::domain::SubscriptionStatus;
+use crate::routes::{e500, TEMPLATES};
+
+#[derive(serde::Serialize)]
+pub struct Segment {
+    pub segment_id: Uuid,
+    pub name: String,
+    pub tags: Vec<String>,
+    pub status: Option<String>,
+    pub subscribed_from: Option<String>,
+    pub subscribed_until: Option<String>,
+    pub subscribed_within_days: Option<i32>,
+    /// Number of distinct addresses currently matching the segment, on any list.
+    pub n_matching_subscribers: i64,
+}
+
+pub async fn segments(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut error_message = String::new();
+    for m in flash_messages.iter() {
+        writeln!(error_message, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let segments = get_segments(&pool).await.map_err(e500)?;
+
+    let mut context = tera::Context::new();
+    context.insert("error_message", &error_message);
+    context.insert("segments", &segments);
+    context.insert("statuses", &SubscriptionStatus::ALL);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(TEMPLATES.render("admin/segments.html", &context).unwrap()))
+}
+
+#[tracing::instrument(name = "Get segments", skip(pool))]
+pub async fn get_segments(pool: &PgPool) -> Result<Vec<Segment>, anyhow::Error> {
+    let segments = sqlx::query!(
+        r#"
+        SELECT
+            segment_id,
+            name,
+            tags,
+            status,
+            subscribed_from,
+            subscribed_until,
+            subscribed_within_days,
+            (
+                SELECT COUNT(DISTINCT email) FROM subscriptions
+                WHERE subscription_in_segment(subscriptions, segments)
+            ) AS "n_matching_subscribers!"
+        FROM segments
+        ORDER BY name
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve segments.")?
+    .into_iter()
+    .map(|r| Segment {
+        segment_id: r.segment_id,
+        name: r.name,
+        tags: r.tags,
+        status: r.status,
+        subscribed_from: r.subscribed_from.map(|d| d.to_string()),
+        subscribed_until: r.subscribed_until.map(|d| d.to_string()),
+        subscribed_within_days: r.subscribed_within_days,
+        n_matching_subscribers: r.n_matching_subscribers,
+    })
+    .collect();
+    Ok(segments)
+}
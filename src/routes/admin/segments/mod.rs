@@ -0,0 +1,4 @@
+mod get;
+pub use get::{get_segments, segments, Segment};
+mod post;
+pub use post::{create_segment, delete_segment};
@@ -0,0 +1,155 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::NaiveDate;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{SubscriberTag, SubscriptionStatus};
+use crate::routes::{e500, see_other};
+
+/// Every field is optional but the name: empty inputs are submitted as empty strings.
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    name: String,
+    #[serde(default)]
+    tags: String,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    subscribed_from: String,
+    #[serde(default)]
+    subscribed_until: String,
+    #[serde(default)]
+    subscribed_within_days: String,
+}
+
+struct NewSegment {
+    name: String,
+    tags: Vec<String>,
+    status: Option<String>,
+    subscribed_from: Option<NaiveDate>,
+    subscribed_until: Option<NaiveDate>,
+    subscribed_within_days: Option<i32>,
+}
+
+impl TryFrom<FormData> for NewSegment {
+    type Error = String;
+
+    fn try_from(form: FormData) -> Result<Self, Self::Error> {
+        let name = form.name.trim().to_owned();
+        if name.is_empty() {
+            return Err("The segment name cannot be empty.".into());
+        }
+        let mut tags = form
+            .tags
+            .split(',')
+            .filter(|t| !t.trim().is_empty())
+            .map(|t| SubscriberTag::parse(t.to_owned()).map(|t| t.as_ref().to_owned()))
+            .collect::<Result<Vec<_>, _>>()?;
+        tags.sort();
+        tags.dedup();
+        let status = match form.status.as_str() {
+            "" => None,
+            s => Some(SubscriptionStatus::parse(s)?.to_string()),
+        };
+        let parse_date = |s: &str| match s.trim() {
+            "" => Ok(None),
+            s => NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .map(Some)
+                .map_err(|_| format!("{} is not a valid date, expected YYYY-MM-DD.", s)),
+        };
+        let subscribed_from = parse_date(&form.subscribed_from)?;
+        let subscribed_until = parse_date(&form.subscribed_until)?;
+        let subscribed_within_days = match form.subscribed_within_days.trim() {
+            "" => None,
+            s => match s.parse::<i32>() {
+                Ok(days) if days > 0 => Some(days),
+                _ => return Err(format!("{} is not a valid number of days.", s)),
+            },
+        };
+        Ok(Self {
+            name,
+            tags,
+            status,
+            subscribed_from,
+            subscribed_until,
+            subscribed_within_days,
+        })
+    }
+}
+
+pub async fn create_segment(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let segment: NewSegment = match form.0.try_into() {
+        Ok(segment) => segment,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/segments"));
+        }
+    };
+    let n_inserted_rows = insert_segment(&pool, &segment)
+        .await
+        .context("Failed to store the new segment.")
+        .map_err(e500)?;
+    if n_inserted_rows == 0 {
+        FlashMessage::error(format!("A segment named {} already exists.", segment.name)).send();
+    } else {
+        FlashMessage::info(format!("The segment {} has been created.", segment.name)).send();
+    }
+    Ok(see_other("/admin/segments"))
+}
+
+pub async fn delete_segment(
+    segment_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let n_deleted_rows = sqlx::query!(
+        r#"DELETE FROM segments WHERE segment_id = $1"#,
+        segment_id.into_inner()
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to delete the segment.")
+    .map_err(e500)?
+    .rows_affected();
+    if n_deleted_rows == 0 {
+        FlashMessage::error("The segment could not be found.").send();
+    } else {
+        FlashMessage::info("The segment has been deleted.").send();
+    }
+    Ok(see_other("/admin/segments"))
+}
+
+#[tracing::instrument(name = "Saving new segment in the database", skip_all)]
+async fn insert_segment(pool: &PgPool, segment: &NewSegment) -> Result<u64, sqlx::Error> {
+    let n_inserted_rows = sqlx::query!(
+        r#"
+        INSERT INTO segments (
+            segment_id,
+            name,
+            tags,
+            status,
+            subscribed_from,
+            subscribed_until,
+            subscribed_within_days,
+            created_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, now())
+        ON CONFLICT DO NOTHING
+        "#,
+        Uuid::new_v4(),
+        segment.name,
+        &segment.tags,
+        segment.status,
+        segment.subscribed_from,
+        segment.subscribed_until,
+        segment.subscribed_within_days
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(n_inserted_rows)
+}
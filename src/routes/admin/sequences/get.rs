@@ -0,0 +1,115 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::routes::{e500, get_mailing_lists, TEMPLATES};
+
+#[derive(serde::Serialize)]
+pub struct Sequence {
+    pub sequence_id: Uuid,
+    pub name: String,
+    pub list: String,
+    pub steps: Vec<SequenceStep>,
+    /// Subscribers with steps left to receive.
+    pub n_in_progress: i64,
+    pub n_completed: i64,
+}
+
+#[derive(serde::Serialize)]
+pub struct SequenceStep {
+    pub position: i32,
+    pub delay_hours: i32,
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+}
+
+pub async fn sequences(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut error_message = String::new();
+    for m in flash_messages.iter() {
+        writeln!(error_message, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let sequences = get_sequences(&pool).await.map_err(e500)?;
+    let lists = get_mailing_lists(&pool).await.map_err(e500)?;
+
+    let mut context = tera::Context::new();
+    context.insert("error_message", &error_message);
+    context.insert("sequences", &sequences);
+    context.insert("lists", &lists);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(TEMPLATES.render("admin/sequences.html", &context).unwrap()))
+}
+
+#[tracing::instrument(name = "Get sequences", skip(pool))]
+async fn get_sequences(pool: &PgPool) -> Result<Vec<Sequence>, anyhow::Error> {
+    let mut sequences: Vec<Sequence> = sqlx::query!(
+        r#"
+        SELECT
+            sequences.sequence_id,
+            sequences.name,
+            lists.name AS list,
+            (
+                SELECT COUNT(*) FROM sequence_enrollments
+                WHERE sequence_id = sequences.sequence_id AND completed_at IS NULL
+            ) AS "n_in_progress!",
+            (
+                SELECT COUNT(*) FROM sequence_enrollments
+                WHERE sequence_id = sequences.sequence_id AND completed_at IS NOT NULL
+            ) AS "n_completed!"
+        FROM sequences
+        JOIN lists USING (list_id)
+        ORDER BY sequences.name
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve sequences.")?
+    .into_iter()
+    .map(|r| Sequence {
+        sequence_id: r.sequence_id,
+        name: r.name,
+        list: r.list,
+        steps: vec![],
+        n_in_progress: r.n_in_progress,
+        n_completed: r.n_completed,
+    })
+    .collect();
+    let steps = sqlx::query!(
+        r#"
+        SELECT
+            sequence_steps.sequence_id,
+            sequence_steps.position,
+            sequence_steps.delay_hours,
+            sequence_steps.newsletter_issue_id,
+            newsletter_issues.title
+        FROM sequence_steps
+        JOIN newsletter_issues USING (newsletter_issue_id)
+        ORDER BY sequence_steps.position
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve sequence steps.")?;
+    for step in steps {
+        if let Some(sequence) = sequences
+            .iter_mut()
+            .find(|sequence| sequence.sequence_id == step.sequence_id)
+        {
+            sequence.steps.push(SequenceStep {
+                position: step.position,
+                delay_hours: step.delay_hours,
+                newsletter_issue_id: step.newsletter_issue_id,
+                title: step.title,
+            });
+        }
+    }
+    Ok(sequences)
+}
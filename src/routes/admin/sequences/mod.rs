@@ -0,0 +1,4 @@
+mod get;
+pub use get::sequences;
+mod post;
+pub use post::{add_sequence_step, create_sequence};
@@ -0,0 +1,143 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::merge_tags::validate_issue;
+use crate::routes::{e500, insert_newsletter_issue, issue_bodies, see_other, NewIssue};
+
+#[derive(serde::Deserialize)]
+pub struct SequenceForm {
+    name: String,
+    list_id: Uuid,
+}
+
+pub async fn create_sequence(
+    form: web::Form<SequenceForm>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let name = form.name.trim();
+    if name.is_empty() {
+        FlashMessage::error("The sequence name cannot be empty.").send();
+        return Ok(see_other("/admin/sequences"));
+    }
+    let n_inserted_rows = sqlx::query!(
+        r#"
+        INSERT INTO sequences (sequence_id, list_id, name, created_at)
+        SELECT $1, list_id, $3, now() FROM lists WHERE list_id = $2
+        "#,
+        Uuid::new_v4(),
+        form.list_id,
+        name
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to store the new sequence.")
+    .map_err(e500)?
+    .rows_affected();
+    if n_inserted_rows == 0 {
+        FlashMessage::error("The selected list does not exist.").send();
+    } else {
+        FlashMessage::info(format!("The sequence {} has been created.", name)).send();
+    }
+    Ok(see_other("/admin/sequences"))
+}
+
+#[derive(serde::Deserialize)]
+pub struct StepForm {
+    title: String,
+    /// Hours after the previous step, or after the confirmation for the first one.
+    delay_hours: String,
+    #[serde(default)]
+    markdown: String,
+    #[serde(default)]
+    html: String,
+    #[serde(default)]
+    text: String,
+}
+
+/// Append an email to a sequence. Subscribers who are still going through
+/// it get it too.
+pub async fn add_sequence_step(
+    sequence_id: web::Path<Uuid>,
+    form: web::Form<StepForm>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let sequence_id = sequence_id.into_inner();
+    let StepForm {
+        title,
+        delay_hours,
+        markdown,
+        html,
+        text,
+    } = form.0;
+    let step = parse_delay_hours(&delay_hours).and_then(|delay_hours| {
+        let (html, text) = issue_bodies(&markdown, html, text)?;
+        validate_issue(&title, &html, &text)?;
+        Ok((delay_hours, html, text))
+    });
+    let (delay_hours, html, text) = match step {
+        Ok(step) => step,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/sequences"));
+        }
+    };
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let issue = NewIssue {
+        title: &title,
+        text_content: &text,
+        html_content: &html,
+        markdown_content: &markdown,
+        visibility: "private",
+        track_opens: false,
+        track_clicks: false,
+    };
+    let issue_id = insert_newsletter_issue(&mut transaction, &issue)
+        .await
+        .context("Failed to store the sequence email.")
+        .map_err(e500)?;
+    let n_inserted_rows = sqlx::query!(
+        r#"
+        INSERT INTO sequence_steps (sequence_id, position, delay_hours, newsletter_issue_id)
+        SELECT sequence_id, (
+            SELECT COALESCE(MAX(position), 0) + 1 FROM sequence_steps WHERE sequence_id = $1
+        ), $2, $3
+        FROM sequences WHERE sequence_id = $1
+        "#,
+        sequence_id,
+        delay_hours,
+        issue_id
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to store the sequence step.")
+    .map_err(e500)?
+    .rows_affected();
+    if n_inserted_rows == 0 {
+        FlashMessage::error("The sequence could not be found.").send();
+        return Ok(see_other("/admin/sequences"));
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to store a sequence step.")
+        .map_err(e500)?;
+    FlashMessage::info(format!("{} has been added to the sequence.", title)).send();
+    Ok(see_other("/admin/sequences"))
+}
+
+fn parse_delay_hours(delay_hours: &str) -> Result<i32, String> {
+    match delay_hours.trim().parse::<i32>() {
+        Ok(delay_hours) if delay_hours >= 0 => Ok(delay_hours),
+        _ => Err(format!(
+            "{} is not a valid delay, use a number of hours.",
+            delay_hours
+        )),
+    }
+}
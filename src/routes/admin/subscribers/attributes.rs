@@ -0,0 +1,102 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::AttributeKey;
+use crate::routes::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    key: String,
+    value: String,
+}
+
+fn details_page(subscriber_id: Uuid) -> HttpResponse {
+    see_other(&format!("/admin/subscribers/{}", subscriber_id))
+}
+
+pub async fn set_subscriber_attribute(
+    subscriber_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+    let FormData { key, value } = form.0;
+    let key = match AttributeKey::parse(key) {
+        Ok(key) => key,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(details_page(subscriber_id));
+        }
+    };
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let n_updated_rows = upsert_attribute(&mut transaction, subscriber_id, &key, &value)
+        .await
+        .context("Failed to set the subscriber attribute.")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to set a subscriber attribute.")
+        .map_err(e500)?;
+    if n_updated_rows == 0 {
+        FlashMessage::error("The subscriber could not be found.").send();
+    } else {
+        FlashMessage::info(format!("The attribute {} has been saved.", key)).send();
+    }
+    Ok(details_page(subscriber_id))
+}
+
+pub async fn remove_subscriber_attribute(
+    path: web::Path<(Uuid, String)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (subscriber_id, key) = path.into_inner();
+    let n_deleted_rows = sqlx::query!(
+        r#"DELETE FROM subscriber_attributes WHERE subscriber_id = $1 AND key = $2"#,
+        subscriber_id,
+        key
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to remove the subscriber attribute.")
+    .map_err(e500)?
+    .rows_affected();
+    if n_deleted_rows == 0 {
+        FlashMessage::error(format!("The subscriber has no attribute {}.", key)).send();
+    } else {
+        FlashMessage::info(format!("The attribute {} has been removed.", key)).send();
+    }
+    Ok(details_page(subscriber_id))
+}
+
+/// Set an attribute, overwriting its previous value.
+/// Returns the number of affected rows: 0 if the subscriber does not exist.
+#[tracing::instrument(name = "Saving subscriber attribute", skip(transaction, value))]
+pub async fn upsert_attribute(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    key: &AttributeKey,
+    value: &str,
+) -> Result<u64, sqlx::Error> {
+    let n_updated_rows = sqlx::query!(
+        r#"
+        INSERT INTO subscriber_attributes (subscriber_id, key, value)
+        SELECT id, $2, $3 FROM subscriptions WHERE id = $1
+        ON CONFLICT (subscriber_id, key) DO UPDATE SET value = EXCLUDED.value
+        "#,
+        subscriber_id,
+        key.as_ref(),
+        value
+    )
+    .execute(transaction)
+    .await?
+    .rows_affected();
+    Ok(n_updated_rows)
+}
@@ -0,0 +1,168 @@
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::web::Bytes;
+use actix_web::{web, HttpResponse};
+use futures::channel::mpsc::Sender;
+use futures::{SinkExt, TryStreamExt};
+use sqlx::PgPool;
+
+use crate::domain::SubscriptionStatus;
+use crate::routes::e500;
+
+/// Number of rows buffered before a chunk is sent to the client.
+const ROWS_PER_CHUNK: usize = 100;
+
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    status: Option<String>,
+    list: Option<String>,
+}
+
+pub async fn export_subscribers(
+    query: web::Query<QueryParams>,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let status = query.0.status.filter(|s| !s.is_empty());
+    let list = query.0.list.filter(|s| !s.is_empty());
+    let (mut sender, receiver) = futures::channel::mpsc::channel(16);
+    actix_web::rt::spawn(async move {
+        if let Err(e) = stream_subscribers_csv(&pool, status, list, &mut sender).await {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to export subscribers.",
+            );
+            let _ = sender.send(Err(e500(e))).await;
+        }
+    });
+    HttpResponse::Ok()
+        .content_type("text/csv")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename("subscribers.csv".into())],
+        })
+        .streaming(receiver)
+}
+
+#[tracing::instrument(name = "Export subscribers as CSV", skip(pool, sender))]
+async fn stream_subscribers_csv(
+    pool: &PgPool,
+    status: Option<String>,
+    list: Option<String>,
+    sender: &mut Sender<Result<Bytes, actix_web::Error>>,
+) -> Result<(), anyhow::Error> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record([
+        "email",
+        "name",
+        "status",
+        "subscribed_at",
+        "confirmed_at",
+        "consent_source",
+        "list",
+        "consent_events",
+    ])?;
+    let mut rows = sqlx::query!(
+        r#"
+        SELECT
+            subscriptions.email,
+            subscriptions.name,
+            subscriptions.status AS "status: SubscriptionStatus",
+            subscriptions.subscribed_at,
+            subscriptions.confirmed_at,
+            subscriptions.consent_source,
+            lists.slug AS list,
+            (
+                SELECT string_agg(
+                    action
+                        || ' ' || to_char(occurred_at AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS"Z"')
+                        || COALESCE(' source=' || source, '')
+                        || COALESCE(' ip=' || ip_address, '')
+                        || COALESCE(' by=' || actor, ''),
+                    '; ' ORDER BY occurred_at
+                )
+                FROM consent_events
+                WHERE consent_events.subscriber_id = subscriptions.id
+            ) AS consent_events
+        FROM subscriptions
+        JOIN lists USING (list_id)
+        WHERE ($1::TEXT IS NULL OR status::TEXT = $1)
+          AND ($2::TEXT IS NULL OR lists.slug = $2)
+        ORDER BY subscribed_at
+        "#,
+        status,
+        list
+    )
+    .fetch(pool);
+    let mut n_buffered_rows = 0;
+    while let Some(r) = rows.try_next().await? {
+        writer.write_record(
+            [
+                r.email,
+                r.name,
+                r.status.to_string(),
+                r.subscribed_at.to_rfc3339(),
+                r.confirmed_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                r.consent_source.unwrap_or_default(),
+                r.list,
+                r.consent_events.unwrap_or_default(),
+            ]
+            .map(defuse_formula),
+        )?;
+        n_buffered_rows += 1;
+        if n_buffered_rows == ROWS_PER_CHUNK {
+            let chunk = into_chunk(writer)?;
+            if sender.send(Ok(chunk)).await.is_err() {
+                // The client went away, there is nobody left to stream to.
+                return Ok(());
+            }
+            writer = csv::Writer::from_writer(vec![]);
+            n_buffered_rows = 0;
+        }
+    }
+    let _ = sender.send(Ok(into_chunk(writer)?)).await;
+    Ok(())
+}
+
+/// Spreadsheets run cells starting with one of these characters as formulas.
+const FORMULA_PREFIXES: &[char] = &['=', '+', '-', '@', '\t', '\r'];
+
+/// Subscribers choose their own name and address: keep spreadsheets from
+/// running them as formulas by making them start with a quote.
+fn defuse_formula(cell: String) -> String {
+    if cell.starts_with(FORMULA_PREFIXES) {
+        format!("'{}", cell)
+    } else {
+        cell
+    }
+}
+
+fn into_chunk(writer: csv::Writer<Vec<u8>>) -> Result<Bytes, anyhow::Error> {
+    let buffer = writer
+        .into_inner()
+        .map_err(|e| anyhow::anyhow!("Failed to flush CSV rows: {}", e))?;
+    Ok(buffer.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::defuse_formula;
+
+    #[test]
+    fn cells_that_look_like_formulas_are_quoted() {
+        for cell in ["=1+1", "+1", "-1", "@SUM(A1)", "\t=1", "\r=1"] {
+            assert_eq!(defuse_formula(cell.into()), format!("'{}", cell));
+        }
+    }
+
+    #[test]
+    fn other_cells_are_left_alone() {
+        for cell in [
+            "",
+            "ursula_le_guin@gmail.com",
+            "le guin",
+            "2026-10-19T00:00:00+00:00",
+        ] {
+            assert_eq!(defuse_formula(cell.into()), cell);
+        }
+    }
+}
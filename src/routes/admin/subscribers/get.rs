@@ -0,0 +1,362 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::consent::get_consent_events;
+use crate::domain::SubscriptionStatus;
+use crate::routes::{e500, get_mailing_lists, TEMPLATES};
+
+const PAGE_SIZE: i64 = 20;
+
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    search: Option<String>,
+    status: Option<String>,
+    list: Option<String>,
+    page: Option<i64>,
+}
+
+#[derive(serde::Serialize)]
+struct SubscriberSummary {
+    id: Uuid,
+    email: String,
+    name: String,
+    list_name: String,
+    status: SubscriptionStatus,
+    subscribed_at: String,
+}
+
+#[derive(serde::Serialize)]
+struct SubscriberDetails {
+    id: Uuid,
+    email: String,
+    name: String,
+    list_name: String,
+    status: SubscriptionStatus,
+    /// Actions offered on the page, following the allowed transitions.
+    can_confirm: bool,
+    can_unsubscribe: bool,
+    subscribed_at: String,
+    confirmed_at: Option<String>,
+    tags: Vec<String>,
+    attributes: Vec<(String, String)>,
+}
+
+#[derive(serde::Serialize)]
+struct ConsentEntry {
+    action: String,
+    source: Option<String>,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    actor: Option<String>,
+    occurred_at: String,
+}
+
+#[derive(serde::Serialize)]
+struct DeliveryAttempt {
+    title: String,
+    outcome: String,
+    attempted_at: String,
+}
+
+fn format_timestamp(timestamp: DateTime<Utc>) -> String {
+    timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+}
+
+fn page_url(search: Option<&str>, status: Option<&str>, list: Option<&str>, page: i64) -> String {
+    format!(
+        "/admin/subscribers?search={}&status={}&list={}&page={}",
+        urlencoding::encode(search.unwrap_or_default()),
+        urlencoding::encode(status.unwrap_or_default()),
+        urlencoding::encode(list.unwrap_or_default()),
+        page
+    )
+}
+
+pub async fn list_subscribers(
+    query: web::Query<QueryParams>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut error_message = String::new();
+    for m in flash_messages.iter() {
+        writeln!(error_message, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    // Empty form fields are submitted as empty strings.
+    let QueryParams {
+        search,
+        status,
+        list,
+        page,
+    } = query.0;
+    let search = search.filter(|s| !s.trim().is_empty());
+    let status = status.filter(|s| !s.is_empty());
+    let list = list.filter(|s| !s.is_empty());
+
+    let n_subscribers =
+        count_subscribers(&pool, search.as_deref(), status.as_deref(), list.as_deref())
+            .await
+            .map_err(e500)?;
+    let n_pages = ((n_subscribers + PAGE_SIZE - 1) / PAGE_SIZE).max(1);
+    // Pages past the last one show the last one.
+    let page = page.unwrap_or(1).clamp(1, n_pages);
+    let subscribers = search_subscribers(
+        &pool,
+        search.as_deref(),
+        status.as_deref(),
+        list.as_deref(),
+        page,
+    )
+    .await
+    .map_err(e500)?;
+    let lists = get_mailing_lists(&pool).await.map_err(e500)?;
+
+    let mut context = tera::Context::new();
+    context.insert("error_message", &error_message);
+    context.insert("subscribers", &subscribers);
+    context.insert("n_subscribers", &n_subscribers);
+    context.insert("search", &search.as_deref().unwrap_or_default());
+    context.insert("status", &status.as_deref().unwrap_or_default());
+    context.insert("statuses", &SubscriptionStatus::ALL);
+    context.insert("list", &list.as_deref().unwrap_or_default());
+    context.insert("lists", &lists);
+    context.insert("page", &page);
+    context.insert("n_pages", &n_pages);
+    if page > 1 {
+        context.insert(
+            "previous_page_url",
+            &page_url(
+                search.as_deref(),
+                status.as_deref(),
+                list.as_deref(),
+                page - 1,
+            ),
+        );
+    }
+    if page < n_pages {
+        context.insert(
+            "next_page_url",
+            &page_url(
+                search.as_deref(),
+                status.as_deref(),
+                list.as_deref(),
+                page + 1,
+            ),
+        );
+    }
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        TEMPLATES
+            .render("admin/subscribers/list.html", &context)
+            .unwrap(),
+    ))
+}
+
+pub async fn subscriber_details(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut error_message = String::new();
+    for m in flash_messages.iter() {
+        writeln!(error_message, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let subscriber = match get_subscriber(&pool, *subscriber_id).await.map_err(e500)? {
+        Some(s) => s,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let deliveries = get_delivery_history(&pool, &subscriber.email)
+        .await
+        .map_err(e500)?;
+    let consent_events: Vec<ConsentEntry> = get_consent_events(pool.get_ref(), subscriber.id)
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .map(|e| ConsentEntry {
+            action: e.action,
+            source: e.source,
+            ip_address: e.ip_address,
+            user_agent: e.user_agent,
+            actor: e.actor,
+            occurred_at: format_timestamp(e.occurred_at),
+        })
+        .collect();
+
+    let mut context = tera::Context::new();
+    context.insert("error_message", &error_message);
+    context.insert("subscriber", &subscriber);
+    context.insert("deliveries", &deliveries);
+    context.insert("consent_events", &consent_events);
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        TEMPLATES
+            .render("admin/subscribers/detail.html", &context)
+            .unwrap(),
+    ))
+}
+
+#[tracing::instrument(name = "Count subscribers", skip(pool))]
+async fn count_subscribers(
+    pool: &PgPool,
+    search: Option<&str>,
+    status: Option<&str>,
+    list: Option<&str>,
+) -> Result<i64, anyhow::Error> {
+    let n_subscribers = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM subscriptions
+        JOIN lists USING (list_id)
+        WHERE ($1::TEXT IS NULL OR email ILIKE '%' || $1 || '%' OR subscriptions.name ILIKE '%' || $1 || '%')
+          AND ($2::TEXT IS NULL OR status::TEXT = $2)
+          AND ($3::TEXT IS NULL OR lists.slug = $3)
+        "#,
+        search,
+        status,
+        list
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to count subscribers.")?
+    .count;
+    Ok(n_subscribers)
+}
+
+#[tracing::instrument(name = "Search subscribers", skip(pool))]
+async fn search_subscribers(
+    pool: &PgPool,
+    search: Option<&str>,
+    status: Option<&str>,
+    list: Option<&str>,
+    page: i64,
+) -> Result<Vec<SubscriberSummary>, anyhow::Error> {
+    let subscribers = sqlx::query!(
+        r#"
+        SELECT
+            subscriptions.id,
+            subscriptions.email,
+            subscriptions.name,
+            lists.name AS list_name,
+            subscriptions.status AS "status: SubscriptionStatus",
+            subscriptions.subscribed_at
+        FROM subscriptions
+        JOIN lists USING (list_id)
+        WHERE ($1::TEXT IS NULL OR email ILIKE '%' || $1 || '%' OR subscriptions.name ILIKE '%' || $1 || '%')
+          AND ($2::TEXT IS NULL OR status::TEXT = $2)
+          AND ($3::TEXT IS NULL OR lists.slug = $3)
+        ORDER BY subscribed_at DESC
+        LIMIT $4 OFFSET $5
+        "#,
+        search,
+        status,
+        list,
+        PAGE_SIZE,
+        (page - 1) * PAGE_SIZE
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve subscribers.")?
+    .into_iter()
+    .map(|r| SubscriberSummary {
+        id: r.id,
+        email: r.email,
+        name: r.name,
+        list_name: r.list_name,
+        status: r.status,
+        subscribed_at: format_timestamp(r.subscribed_at),
+    })
+    .collect();
+    Ok(subscribers)
+}
+
+#[tracing::instrument(name = "Get subscriber details", skip(pool))]
+async fn get_subscriber(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Option<SubscriberDetails>, anyhow::Error> {
+    let subscriber = sqlx::query!(
+        r#"
+        SELECT
+            subscriptions.id,
+            subscriptions.email,
+            subscriptions.name,
+            lists.name AS list_name,
+            subscriptions.status AS "status: SubscriptionStatus",
+            subscriptions.subscribed_at,
+            subscriptions.confirmed_at,
+            ARRAY(
+                SELECT tag FROM subscriber_tags
+                WHERE subscriber_id = subscriptions.id
+                ORDER BY tag
+            ) AS "tags!",
+            ARRAY(
+                SELECT key FROM subscriber_attributes
+                WHERE subscriber_id = subscriptions.id
+                ORDER BY key
+            ) AS "attribute_keys!",
+            ARRAY(
+                SELECT value FROM subscriber_attributes
+                WHERE subscriber_id = subscriptions.id
+                ORDER BY key
+            ) AS "attribute_values!"
+        FROM subscriptions
+        JOIN lists USING (list_id)
+        WHERE subscriptions.id = $1
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the subscriber.")?
+    .map(|r| SubscriberDetails {
+        id: r.id,
+        email: r.email,
+        name: r.name,
+        list_name: r.list_name,
+        status: r.status,
+        can_confirm: r.status.can_transition_to(SubscriptionStatus::Confirmed),
+        can_unsubscribe: r.status.can_transition_to(SubscriptionStatus::Unsubscribed),
+        subscribed_at: format_timestamp(r.subscribed_at),
+        confirmed_at: r.confirmed_at.map(format_timestamp),
+        tags: r.tags,
+        attributes: r
+            .attribute_keys
+            .into_iter()
+            .zip(r.attribute_values.into_iter())
+            .collect(),
+    });
+    Ok(subscriber)
+}
+
+#[tracing::instrument(name = "Get delivery history", skip(pool))]
+async fn get_delivery_history(
+    pool: &PgPool,
+    subscriber_email: &str,
+) -> Result<Vec<DeliveryAttempt>, anyhow::Error> {
+    let deliveries = sqlx::query!(
+        r#"
+        SELECT newsletter_issues.title, issue_delivery_log.outcome, issue_delivery_log.attempted_at
+        FROM issue_delivery_log
+        JOIN newsletter_issues USING (newsletter_issue_id)
+        WHERE issue_delivery_log.subscriber_email = $1
+        ORDER BY issue_delivery_log.attempted_at DESC
+        "#,
+        subscriber_email
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the delivery history.")?
+    .into_iter()
+    .map(|r| DeliveryAttempt {
+        title: r.title,
+        outcome: r.outcome,
+        attempted_at: format_timestamp(r.attempted_at),
+    })
+    .collect();
+    Ok(deliveries)
+}
@@ -0,0 +1,334 @@
+use actix_multipart::Multipart;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use futures::TryStreamExt;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashSet;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::authentication::UserId;
+use crate::confirmation_email_worker::enqueue_confirmation_email;
+use crate::consent::{record_consent_event, ConsentAction, ConsentContext};
+use crate::domain::{AttributeKey, ListSlug, NewSubscriber, SubscriberEmail, SubscriberName};
+use crate::email_policy::EmailPolicy;
+use crate::routes::{
+    e400, e500, flag_subscriber, get_list_id, get_mailing_lists, get_username, insert_subscriber,
+    see_other, select_subscriber_by_email, upsert_attribute, TEMPLATES,
+};
+use crate::sequences::start_sequences;
+
+const MAX_UPLOAD_SIZE: usize = 20 * 1024 * 1024;
+
+#[derive(serde::Deserialize)]
+struct CsvRecord {
+    email: String,
+    name: String,
+}
+
+impl TryFrom<CsvRecord> for NewSubscriber {
+    type Error = String;
+    fn try_from(value: CsvRecord) -> Result<Self, Self::Error> {
+        let name = SubscriberName::parse(value.name)?;
+        let email = SubscriberEmail::parse(value.email)?;
+        Ok(Self { email, name })
+    }
+}
+
+/// How imported subscribers should be added to the list.
+enum ImportMode {
+    /// Consent was collected elsewhere: insert the subscribers as confirmed.
+    Confirmed { consent_source: String },
+    /// Insert the subscribers as pending and have the background worker ask
+    /// them to confirm by email.
+    SendConfirmation,
+}
+
+struct ImportForm {
+    csv: Vec<u8>,
+    list_slug: ListSlug,
+    mode: ImportMode,
+}
+
+#[derive(serde::Serialize, Default)]
+struct ImportReport {
+    n_imported: usize,
+    n_duplicates: usize,
+    errors: Vec<RowError>,
+}
+
+#[derive(serde::Serialize)]
+struct RowError {
+    line: u64,
+    message: String,
+}
+
+pub async fn import_subscribers_form(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut error_message = String::new();
+    for m in flash_messages.iter() {
+        writeln!(error_message, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let lists = get_mailing_lists(&pool).await.map_err(e500)?;
+    let mut context = tera::Context::new();
+    context.insert("error_message", &error_message);
+    context.insert("lists", &lists);
+    context.insert("default_list", ListSlug::default().as_ref());
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        TEMPLATES
+            .render("admin/subscribers/import.html", &context)
+            .unwrap(),
+    ))
+}
+
+pub async fn import_subscribers(
+    payload: Multipart,
+    pool: web::Data<PgPool>,
+    email_policy: web::Data<EmailPolicy>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let username = get_username(*user_id.into_inner(), &pool)
+        .await
+        .map_err(e500)?;
+    let form = match read_import_form(payload).await {
+        Ok(form) => form,
+        Err(e) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other("/admin/subscribers/import"));
+        }
+    };
+    let list_id = match get_list_id(pool.get_ref(), &form.list_slug)
+        .await
+        .context("Failed to look up the mailing list to import into.")
+        .map_err(e500)?
+    {
+        Some(list_id) => list_id,
+        None => {
+            FlashMessage::error(format!("There is no list named {}.", form.list_slug)).send();
+            return Ok(see_other("/admin/subscribers/import"));
+        }
+    };
+
+    let mut report = ImportReport::default();
+    let mut seen_emails = HashSet::new();
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(form.csv.as_slice());
+    let headers = reader.headers().map_err(e400)?.clone();
+    // Any column besides `email` and `name` is stored as a custom attribute.
+    let mut attribute_columns = Vec::new();
+    for (index, header) in headers.iter().enumerate() {
+        if header == "email" || header == "name" {
+            continue;
+        }
+        match AttributeKey::parse(header.to_owned()) {
+            Ok(key) => attribute_columns.push((index, key)),
+            Err(e) => {
+                FlashMessage::error(e).send();
+                return Ok(see_other("/admin/subscribers/import"));
+            }
+        }
+    }
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                report.errors.push(RowError {
+                    line: e.position().map(|p| p.line()).unwrap_or_default(),
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        };
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+        let new_subscriber: Result<NewSubscriber, String> = record
+            .deserialize::<CsvRecord>(Some(&headers))
+            .map_err(|e| e.to_string())
+            .and_then(TryInto::try_into);
+        let new_subscriber = match new_subscriber {
+            Ok(s) => s,
+            Err(message) => {
+                report.errors.push(RowError { line, message });
+                continue;
+            }
+        };
+        // The policy applies to imported addresses as it does to the form.
+        let flags = match email_policy.check(&new_subscriber.email) {
+            Ok(flags) => flags,
+            Err(message) => {
+                report.errors.push(RowError { line, message });
+                continue;
+            }
+        };
+
+        if !seen_emails.insert(new_subscriber.email.as_ref().to_owned()) {
+            report.n_duplicates += 1;
+            continue;
+        }
+        match select_subscriber_by_email(&mut transaction, list_id, &new_subscriber.email).await {
+            Ok(_) => {
+                report.n_duplicates += 1;
+                continue;
+            }
+            Err(sqlx::Error::RowNotFound) => {}
+            Err(e) => return Err(e500(e)),
+        }
+
+        let consent = match &form.mode {
+            ImportMode::Confirmed { consent_source } => {
+                ConsentContext::admin(username.clone()).with_source(consent_source.as_str())
+            }
+            ImportMode::SendConfirmation => ConsentContext::admin(username.clone()),
+        };
+        let subscriber_id = match &form.mode {
+            ImportMode::Confirmed { consent_source } => {
+                let subscriber_id = insert_confirmed_subscriber(
+                    &mut transaction,
+                    list_id,
+                    &new_subscriber,
+                    consent_source,
+                )
+                .await
+                .context("Failed to insert imported subscriber in the database.")
+                .map_err(e500)?;
+                start_sequences(&mut transaction, subscriber_id)
+                    .await
+                    .map_err(e500)?;
+                subscriber_id
+            }
+            ImportMode::SendConfirmation => {
+                let subscriber_id = insert_subscriber(&mut transaction, list_id, &new_subscriber)
+                    .await
+                    .context("Failed to insert imported subscriber in the database.")
+                    .map_err(e500)?;
+                enqueue_confirmation_email(&mut transaction, subscriber_id)
+                    .await
+                    .context("Failed to enqueue the confirmation email of an imported subscriber.")
+                    .map_err(e500)?;
+                subscriber_id
+            }
+        };
+        report.n_imported += 1;
+        record_consent_event(
+            &mut transaction,
+            subscriber_id,
+            ConsentAction::Imported,
+            &consent,
+        )
+        .await
+        .map_err(e500)?;
+        flag_subscriber(&mut *transaction, subscriber_id, &flags)
+            .await
+            .map_err(e500)?;
+        for (index, key) in &attribute_columns {
+            let value = record.get(*index).unwrap_or_default();
+            if value.is_empty() {
+                continue;
+            }
+            upsert_attribute(&mut transaction, subscriber_id, key, value)
+                .await
+                .context("Failed to store the attributes of an imported subscriber.")
+                .map_err(e500)?;
+        }
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to import subscribers.")
+        .map_err(e500)?;
+
+    let lists = get_mailing_lists(&pool).await.map_err(e500)?;
+    let mut context = tera::Context::new();
+    context.insert("error_message", "");
+    context.insert("report", &report);
+    context.insert("lists", &lists);
+    context.insert("default_list", form.list_slug.as_ref());
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        TEMPLATES
+            .render("admin/subscribers/import.html", &context)
+            .unwrap(),
+    ))
+}
+
+async fn read_import_form(mut payload: Multipart) -> Result<ImportForm, anyhow::Error> {
+    let mut csv = None;
+    let mut mode = None;
+    let mut consent_source = String::new();
+    let mut list_slug = None;
+    while let Some(mut field) = payload.try_next().await? {
+        let mut data = Vec::new();
+        while let Some(chunk) = field.try_next().await? {
+            if data.len() + chunk.len() > MAX_UPLOAD_SIZE {
+                anyhow::bail!("The uploaded file must be smaller than {MAX_UPLOAD_SIZE} bytes.");
+            }
+            data.extend_from_slice(&chunk);
+        }
+        match field.name() {
+            "file" => csv = Some(data),
+            "mode" => mode = Some(String::from_utf8(data)?),
+            "consent_source" => consent_source = String::from_utf8(data)?.trim().to_owned(),
+            "list" => list_slug = Some(String::from_utf8(data)?),
+            _ => {}
+        }
+    }
+    let csv = csv
+        .filter(|csv| !csv.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("Please select a CSV file to import."))?;
+    let mode = match mode.as_deref() {
+        Some("confirmed") if consent_source.is_empty() => {
+            anyhow::bail!("A consent source is required to import subscribers as confirmed.")
+        }
+        Some("confirmed") => ImportMode::Confirmed { consent_source },
+        Some("send_confirmation") => ImportMode::SendConfirmation,
+        _ => anyhow::bail!("Please choose how the subscribers should be imported."),
+    };
+    let list_slug = match list_slug {
+        Some(slug) => ListSlug::parse(slug).map_err(anyhow::Error::msg)?,
+        None => ListSlug::default(),
+    };
+    Ok(ImportForm {
+        csv,
+        list_slug,
+        mode,
+    })
+}
+
+#[tracing::instrument(
+    name = "Saving imported subscriber details in the database",
+    skip(new_subscriber, transaction)
+)]
+async fn insert_confirmed_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    list_id: Uuid,
+    new_subscriber: &NewSubscriber,
+    consent_source: &str,
+) -> Result<Uuid, sqlx::Error> {
+    let subscriber_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+    INSERT INTO subscriptions (
+        id, list_id, email, name, subscribed_at, status, confirmed_at, consent_source
+    )
+    VALUES ($1, $2, $3, $4, now(), 'confirmed', now(), $5)
+    "#,
+        subscriber_id,
+        list_id,
+        new_subscriber.email.as_ref(),
+        new_subscriber.name.as_ref(),
+        consent_source
+    )
+    .execute(transaction)
+    .await?;
+    Ok(subscriber_id)
+}
@@ -0,0 +1,15 @@
+mod attributes;
+pub use attributes::{remove_subscriber_attribute, set_subscriber_attribute, upsert_attribute};
+mod export;
+pub use export::export_subscribers;
+mod get;
+pub use get::{list_subscribers, subscriber_details};
+mod import;
+pub use import::{import_subscribers, import_subscribers_form};
+mod post;
+pub use post::{
+    admin_confirm_subscriber, admin_delete_subscriber, admin_unsubscribe_subscriber,
+    delete_subscriber,
+};
+mod tags;
+pub use tags::{add_subscriber_tag, remove_subscriber_tag};
@@ -0,0 +1,223 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::authentication::UserId;
+use crate::consent::{record_consent_event, ConsentAction, ConsentContext};
+use crate::domain::SubscriptionStatus;
+use crate::routes::{delete_pending_deliveries, e500, get_username, mark_confirmed, see_other};
+
+fn details_page(subscriber_id: Uuid) -> HttpResponse {
+    see_other(&format!("/admin/subscribers/{}", subscriber_id))
+}
+
+async fn admin_consent(
+    user_id: web::ReqData<UserId>,
+    pool: &PgPool,
+) -> Result<ConsentContext, actix_web::Error> {
+    let username = get_username(*user_id.into_inner(), pool)
+        .await
+        .map_err(e500)?;
+    Ok(ConsentContext::admin(username))
+}
+
+pub async fn admin_confirm_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+    let consent = admin_consent(user_id, &pool).await?;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    if mark_confirmed(
+        &mut transaction,
+        subscriber_id,
+        ConsentAction::AdminConfirmed,
+        &consent,
+    )
+    .await
+    .map_err(e500)?
+    {
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit SQL transaction to confirm a subscriber.")
+            .map_err(e500)?;
+        FlashMessage::info("The subscriber has been confirmed.").send();
+    } else {
+        FlashMessage::error("The subscriber could not be confirmed.").send();
+    }
+    Ok(details_page(subscriber_id))
+}
+
+pub async fn admin_unsubscribe_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+    let consent = admin_consent(user_id, &pool).await?;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let email = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET status = $2
+        WHERE id = $1 AND status::TEXT = ANY($3)
+        RETURNING email
+        "#,
+        subscriber_id,
+        SubscriptionStatus::Unsubscribed as SubscriptionStatus,
+        &SubscriptionStatus::Unsubscribed.allowed_sources()
+    )
+    .fetch_optional(&mut transaction)
+    .await
+    .context("Failed to unsubscribe the subscriber.")
+    .map_err(e500)?
+    .map(|r| r.email);
+    match email {
+        Some(email) => {
+            delete_pending_deliveries(&mut transaction, &email)
+                .await
+                .context("Failed to remove pending deliveries.")
+                .map_err(e500)?;
+            record_consent_event(
+                &mut transaction,
+                subscriber_id,
+                ConsentAction::AdminUnsubscribed,
+                &consent,
+            )
+            .await
+            .map_err(e500)?;
+            transaction
+                .commit()
+                .await
+                .context("Failed to commit SQL transaction to unsubscribe a subscriber.")
+                .map_err(e500)?;
+            FlashMessage::info("The subscriber has been unsubscribed.").send();
+        }
+        None => FlashMessage::error("The subscriber could not be unsubscribed.").send(),
+    }
+    Ok(details_page(subscriber_id))
+}
+
+pub async fn admin_delete_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+    let consent = admin_consent(user_id, &pool).await?;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    if delete_subscriber(&mut transaction, subscriber_id)
+        .await
+        .context("Failed to delete the subscriber.")
+        .map_err(e500)?
+    {
+        record_consent_event(
+            &mut transaction,
+            subscriber_id,
+            ConsentAction::AdminDeleted,
+            &consent,
+        )
+        .await
+        .map_err(e500)?;
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit SQL transaction to delete a subscriber.")
+            .map_err(e500)?;
+        FlashMessage::info("The subscriber has been deleted.").send();
+    } else {
+        FlashMessage::error("The subscriber could not be found.").send();
+    }
+    Ok(see_other("/admin/subscribers"))
+}
+
+/// Delete a subscriber together with their tokens, tags, attributes, opens, clicks,
+/// queued confirmation email, sequence progress and, once the address is not on any other list, its delivery records.
+/// Consent events are kept as evidence; only erasing the address removes them.
+/// Returns `false` if the subscriber does not exist.
+#[tracing::instrument(name = "Delete subscriber", skip(transaction))]
+pub async fn delete_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query!(
+        r#"DELETE FROM subscription_tokens WHERE subscriber_id = $1"#,
+        subscriber_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM subscriber_tags WHERE subscriber_id = $1"#,
+        subscriber_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM subscriber_attributes WHERE subscriber_id = $1"#,
+        subscriber_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM issue_opens WHERE subscriber_id = $1"#,
+        subscriber_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM issue_clicks WHERE subscriber_id = $1"#,
+        subscriber_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM confirmation_email_queue WHERE subscriber_id = $1"#,
+        subscriber_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM sequence_enrollments WHERE subscriber_id = $1"#,
+        subscriber_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    let email = match sqlx::query!(
+        r#"DELETE FROM subscriptions WHERE id = $1 RETURNING email"#,
+        subscriber_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    {
+        Some(r) => r.email,
+        None => return Ok(false),
+    };
+    delete_pending_deliveries(transaction, &email).await?;
+    sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_log
+        WHERE lower(subscriber_email) = lower($1)
+          AND NOT EXISTS (SELECT 1 FROM subscriptions WHERE lower(email) = lower($1))
+        "#,
+        email
+    )
+    .execute(transaction)
+    .await?;
+    Ok(true)
+}
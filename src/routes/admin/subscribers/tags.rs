@@ -0,0 +1,75 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::SubscriberTag;
+use crate::routes::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    tag: String,
+}
+
+fn details_page(subscriber_id: Uuid) -> HttpResponse {
+    see_other(&format!("/admin/subscribers/{}", subscriber_id))
+}
+
+pub async fn add_subscriber_tag(
+    subscriber_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+    let tag = match SubscriberTag::parse(form.0.tag) {
+        Ok(tag) => tag,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(details_page(subscriber_id));
+        }
+    };
+    let n_inserted_rows = sqlx::query!(
+        r#"
+        INSERT INTO subscriber_tags (subscriber_id, tag)
+        SELECT id, $2 FROM subscriptions WHERE id = $1
+        ON CONFLICT DO NOTHING
+        "#,
+        subscriber_id,
+        tag.as_ref()
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to tag the subscriber.")
+    .map_err(e500)?
+    .rows_affected();
+    if n_inserted_rows == 0 {
+        FlashMessage::error(format!("The subscriber could not be tagged with {}.", tag)).send();
+    } else {
+        FlashMessage::info(format!("The tag {} has been added.", tag)).send();
+    }
+    Ok(details_page(subscriber_id))
+}
+
+pub async fn remove_subscriber_tag(
+    path: web::Path<(Uuid, String)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (subscriber_id, tag) = path.into_inner();
+    let n_deleted_rows = sqlx::query!(
+        r#"DELETE FROM subscriber_tags WHERE subscriber_id = $1 AND tag = $2"#,
+        subscriber_id,
+        tag
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to remove the tag from the subscriber.")
+    .map_err(e500)?
+    .rows_affected();
+    if n_deleted_rows == 0 {
+        FlashMessage::error(format!("The subscriber is not tagged with {}.", tag)).send();
+    } else {
+        FlashMessage::info(format!("The tag {} has been removed.", tag)).send();
+    }
+    Ok(details_page(subscriber_id))
+}
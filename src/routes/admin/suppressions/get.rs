@@ -0,0 +1,91 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use super::SUPPRESSION_REASONS;
+use crate::routes::{e500, TEMPLATES};
+
+const PAGE_SIZE: i64 = 50;
+
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    /// Look up a single address.
+    email: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct Suppression {
+    email_hash: String,
+    reason: String,
+    source: String,
+    suppressed_at: String,
+}
+
+pub async fn suppressions(
+    query: web::Query<QueryParams>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut error_message = String::new();
+    for m in flash_messages.iter() {
+        writeln!(error_message, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let email = query
+        .email
+        .as_deref()
+        .map(str::trim)
+        .filter(|e| !e.is_empty());
+    let (suppressions, n_suppressions) = get_suppressions(&pool, email).await.map_err(e500)?;
+
+    let mut context = tera::Context::new();
+    context.insert("error_message", &error_message);
+    context.insert("suppressions", &suppressions);
+    context.insert("n_suppressions", &n_suppressions);
+    context.insert("email", &email.unwrap_or_default());
+    context.insert("reasons", &SUPPRESSION_REASONS);
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        TEMPLATES
+            .render("admin/suppressions.html", &context)
+            .unwrap(),
+    ))
+}
+
+/// The most recent entries, or the one of `email`.
+#[tracing::instrument(name = "Get suppressions", skip(pool))]
+async fn get_suppressions(
+    pool: &PgPool,
+    email: Option<&str>,
+) -> Result<(Vec<Suppression>, i64), anyhow::Error> {
+    let n_suppressions = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM suppressions"#)
+        .fetch_one(pool)
+        .await
+        .context("Failed to count suppressions.")?
+        .count;
+    let suppressions = sqlx::query!(
+        r#"
+        SELECT email_hash, reason, source, suppressed_at
+        FROM suppressions
+        WHERE $1::TEXT IS NULL OR email_hash = email_hash($1)
+        ORDER BY suppressed_at DESC
+        LIMIT $2
+        "#,
+        email,
+        PAGE_SIZE
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve suppressions.")?
+    .into_iter()
+    .map(|r| Suppression {
+        email_hash: r.email_hash,
+        reason: r.reason,
+        source: r.source,
+        suppressed_at: r.suppressed_at.format("%Y-%m-%d %H:%M").to_string(),
+    })
+    .collect();
+    Ok((suppressions, n_suppressions))
+}
@@ -0,0 +1,50 @@
+mod get;
+pub use get::suppressions;
+mod post;
+pub use post::{create_suppression, delete_suppression};
+
+use anyhow::Context;
+use sqlx::PgExecutor;
+
+pub const SUPPRESSION_REASONS: [&str; 4] = ["bounced", "complained", "legal_request", "manual"];
+
+/// Whether an address must never be mailed again.
+#[tracing::instrument(name = "Check the suppression list", skip(executor))]
+pub async fn is_suppressed(
+    executor: impl PgExecutor<'_>,
+    email: &str,
+) -> Result<bool, anyhow::Error> {
+    let r = sqlx::query!(
+        r#"SELECT EXISTS (SELECT 1 FROM suppressions WHERE email_hash = email_hash($1)) AS "suppressed!""#,
+        email
+    )
+    .fetch_one(executor)
+    .await
+    .context("Failed to check the suppression list.")?;
+    Ok(r.suppressed)
+}
+
+/// Add an address to the suppression list, unless it is already there.
+/// Only a hash of the address is stored.
+#[tracing::instrument(name = "Suppress an address", skip(executor))]
+pub async fn suppress_address(
+    executor: impl PgExecutor<'_>,
+    email: &str,
+    reason: &str,
+    source: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO suppressions (email_hash, reason, source, suppressed_at)
+        VALUES (email_hash($1), $2, $3, now())
+        ON CONFLICT (email_hash) DO NOTHING
+        "#,
+        email,
+        reason,
+        source
+    )
+    .execute(executor)
+    .await
+    .context("Failed to add an address to the suppression list.")?;
+    Ok(())
+}
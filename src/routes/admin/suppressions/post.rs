@@ -0,0 +1,58 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+
+use super::{suppress_address, SUPPRESSION_REASONS};
+use crate::domain::SubscriberEmail;
+use crate::routes::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    email: String,
+    reason: String,
+}
+
+pub async fn create_suppression(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let FormData { email, reason } = form.0;
+    let email = match SubscriberEmail::parse(email.trim().to_owned()) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/suppressions"));
+        }
+    };
+    if !SUPPRESSION_REASONS.contains(&reason.as_str()) {
+        FlashMessage::error(format!("{} is not a valid reason.", reason)).send();
+        return Ok(see_other("/admin/suppressions"));
+    }
+    suppress_address(pool.get_ref(), email.as_ref(), &reason, "admin")
+        .await
+        .map_err(e500)?;
+    FlashMessage::info("The address will not be mailed anymore.").send();
+    Ok(see_other("/admin/suppressions"))
+}
+
+pub async fn delete_suppression(
+    email_hash: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let n_deleted_rows = sqlx::query!(
+        r#"DELETE FROM suppressions WHERE email_hash = $1"#,
+        email_hash.as_str()
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to delete the suppression.")
+    .map_err(e500)?
+    .rows_affected();
+    if n_deleted_rows == 0 {
+        FlashMessage::error("The suppression could not be found.").send();
+    } else {
+        FlashMessage::info("The address can be mailed again.").send();
+    }
+    Ok(see_other("/admin/suppressions"))
+}
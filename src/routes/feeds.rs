@@ -0,0 +1,152 @@
+use actix_web::http::header::{
+    ETag, EntityTag, Header, HttpDate, IfModifiedSince, IfNoneMatch, LastModified,
+};
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, TimeZone, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::time::SystemTime;
+use uuid::Uuid;
+
+use super::issues::render_for_archive;
+use super::{e500, TEMPLATES};
+use crate::startup::ApplicationBaseUrl;
+
+/// Feed readers only care about recent issues.
+const FEED_SIZE: i64 = 20;
+
+#[derive(serde::Serialize)]
+struct FeedEntry {
+    id: Uuid,
+    title: String,
+    html_content: String,
+    slug: String,
+    rss_date: String,
+    atom_date: String,
+}
+
+pub async fn rss_feed(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    feed(
+        &request,
+        &pool,
+        &base_url.0,
+        "feeds/rss.xml",
+        "application/rss+xml; charset=utf-8",
+    )
+    .await
+}
+
+pub async fn atom_feed(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    feed(
+        &request,
+        &pool,
+        &base_url.0,
+        "feeds/atom.xml",
+        "application/atom+xml; charset=utf-8",
+    )
+    .await
+}
+
+/// Render the feed of public issues, answering `304 Not Modified` when the
+/// reader's copy is still current.
+async fn feed(
+    request: &HttpRequest,
+    pool: &PgPool,
+    base_url: &str,
+    template: &str,
+    content_type: &str,
+) -> Result<HttpResponse, actix_web::Error> {
+    let entries = get_feed_entries(pool).await.map_err(e500)?;
+    // Issues cannot be edited: the feed only changes when one is published.
+    let last_modified = entries
+        .first()
+        .map(|e| e.published_at)
+        .unwrap_or_else(|| Utc.timestamp(0, 0));
+    let entries: Vec<FeedEntry> = entries
+        .into_iter()
+        .map(|e| {
+            let (title, html_content) = render_for_archive(e.title, e.html_content);
+            FeedEntry {
+                id: e.newsletter_issue_id,
+                title,
+                html_content,
+                slug: e.slug,
+                rss_date: e.published_at.to_rfc2822(),
+                atom_date: e.published_at.to_rfc3339(),
+            }
+        })
+        .collect();
+
+    let mut context = tera::Context::new();
+    context.insert("base_url", base_url);
+    context.insert("entries", &entries);
+    context.insert("updated", &last_modified.to_rfc3339());
+    let body = TEMPLATES.render(template, &context).unwrap();
+
+    let etag = EntityTag::new_strong(hex::encode(&Sha256::digest(body.as_bytes())[..16]));
+    // HTTP dates have a one second resolution.
+    let last_modified = Utc.timestamp(last_modified.timestamp(), 0);
+    let last_modified = HttpDate::from(SystemTime::from(last_modified));
+    if is_fresh(request, &etag, last_modified) {
+        return Ok(HttpResponse::NotModified()
+            .insert_header(ETag(etag))
+            .insert_header(LastModified(last_modified))
+            .finish());
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(ETag(etag))
+        .insert_header(LastModified(last_modified))
+        .body(body))
+}
+
+/// `If-None-Match` takes precedence over `If-Modified-Since` (RFC 7232).
+fn is_fresh(request: &HttpRequest, etag: &EntityTag, last_modified: HttpDate) -> bool {
+    if request.headers().contains_key(IfNoneMatch::name()) {
+        return match IfNoneMatch::parse(request) {
+            Ok(IfNoneMatch::Any) => true,
+            Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+            Err(_) => false,
+        };
+    }
+    match IfModifiedSince::parse(request) {
+        Ok(IfModifiedSince(since)) => last_modified <= since,
+        Err(_) => false,
+    }
+}
+
+struct PublicIssue {
+    newsletter_issue_id: Uuid,
+    title: String,
+    html_content: String,
+    slug: String,
+    published_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Get feed entries", skip(pool))]
+async fn get_feed_entries(pool: &PgPool) -> Result<Vec<PublicIssue>, anyhow::Error> {
+    let issues = sqlx::query_as!(
+        PublicIssue,
+        r#"
+        SELECT newsletter_issue_id, title, html_content, slug, published_at
+        FROM newsletter_issues
+        WHERE visibility = 'public'
+        ORDER BY published_at DESC
+        LIMIT $1
+        "#,
+        FEED_SIZE
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve feed entries.")?;
+    Ok(issues)
+}
@@ -1,10 +1,16 @@
-use actix_web::{http::header::ContentType, HttpResponse};
+use actix_web::{http::header::ContentType, web, HttpRequest, HttpResponse};
 use tera::Context;
 
-use super::TEMPLATES;
+use super::{render_localised, request_locale};
+use crate::abuse_protection::AbuseProtection;
 
-pub async fn home() -> HttpResponse {
+pub async fn home(request: HttpRequest, protection: web::Data<AbuseProtection>) -> HttpResponse {
+    let locale = request_locale(&request);
+    let mut context = Context::new();
+    context.insert("form_token", &protection.form_token());
+    context.insert("challenge", &protection.has_challenge());
+    context.insert("locale", locale.as_ref());
     HttpResponse::Ok()
         .content_type(ContentType::html())
-        .body(TEMPLATES.render("home.html", &Context::new()).unwrap())
+        .body(render_localised("home.html", &locale, &context).unwrap())
 }
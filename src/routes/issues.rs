@@ -0,0 +1,201 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use super::{e500, TEMPLATES};
+use crate::merge_tags::{render_issue, Recipient};
+use crate::startup::HmacSecret;
+
+const PAGE_SIZE: i64 = 10;
+
+#[derive(serde::Deserialize)]
+pub struct ListParameters {
+    page: Option<i64>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct IssueParameters {
+    signature: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct IssueSummary {
+    slug: String,
+    title: String,
+    published_at: String,
+}
+
+#[derive(serde::Serialize)]
+struct ArchivedIssue {
+    title: String,
+    html_content: String,
+    published_at: String,
+}
+
+/// Build the "view in browser" link of an issue.
+/// Private issues are not listed in the archive: their link is signed,
+/// so that only the people who received them can open it.
+pub fn view_in_browser_link(
+    base_url: &str,
+    slug: &str,
+    is_public: bool,
+    hmac_secret: &HmacSecret,
+) -> String {
+    if is_public {
+        return format!("{}/issues/{}", base_url, slug);
+    }
+    let signature = hmac_secret.sign("issue", slug.as_bytes());
+    format!(
+        "{}/issues/{}?signature={}",
+        base_url,
+        slug,
+        hex::encode(signature)
+    )
+}
+
+fn publication_date(published_at: &DateTime<Utc>) -> String {
+    published_at.format("%Y-%m-%d").to_string()
+}
+
+/// Render merge tags for an anonymous reader.
+/// Issues written before merge tags existed may not be valid templates:
+/// they are shown as they were sent.
+pub(super) fn render_for_archive(title: String, html_content: String) -> (String, String) {
+    let reader = Recipient {
+        name: String::new(),
+        email: String::new(),
+        attributes: Default::default(),
+        locale: Default::default(),
+    };
+    let (title, html_content) = match render_issue(&title, &html_content, "", &reader, "") {
+        Ok(issue) => (issue.title, issue.html_content),
+        Err(_) => (title, html_content),
+    };
+    // Emails are full documents with inline styles: keep the content only.
+    (title, ammonia::clean(&html_content))
+}
+
+pub async fn list_issues(
+    parameters: web::Query<ListParameters>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let n_issues = count_public_issues(&pool).await.map_err(e500)?;
+    let n_pages = ((n_issues + PAGE_SIZE - 1) / PAGE_SIZE).max(1);
+    // Pages past the last one show the last one.
+    let page = parameters.page.unwrap_or(1).clamp(1, n_pages);
+    let issues = get_public_issues(&pool, page).await.map_err(e500)?;
+
+    let mut context = tera::Context::new();
+    context.insert("issues", &issues);
+    context.insert("page", &page);
+    context.insert("n_pages", &n_pages);
+    if page > 1 {
+        context.insert("previous_page", &(page - 1));
+    }
+    if page < n_pages {
+        context.insert("next_page", &(page + 1));
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(TEMPLATES.render("issues/list.html", &context).unwrap()))
+}
+
+pub async fn issue_page(
+    slug: web::Path<String>,
+    parameters: web::Query<IssueParameters>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let slug = slug.into_inner();
+    let has_valid_signature = parameters
+        .signature
+        .as_ref()
+        .and_then(|s| hex::decode(s).ok())
+        .map(|s| hmac_secret.verify("issue", slug.as_bytes(), &s))
+        .unwrap_or(false);
+    let issue = match get_issue(&pool, &slug, has_valid_signature)
+        .await
+        .map_err(e500)?
+    {
+        Some(issue) => issue,
+        // Private issues are indistinguishable from missing ones.
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+
+    let mut context = tera::Context::new();
+    context.insert("issue", &issue);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(TEMPLATES.render("issues/issue.html", &context).unwrap()))
+}
+
+#[tracing::instrument(name = "Count public issues", skip(pool))]
+async fn count_public_issues(pool: &PgPool) -> Result<i64, anyhow::Error> {
+    let n_issues = sqlx::query!(
+        r#"SELECT COUNT(*) AS "count!" FROM newsletter_issues WHERE visibility = 'public'"#
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to count public issues.")?
+    .count;
+    Ok(n_issues)
+}
+
+#[tracing::instrument(name = "Get public issues", skip(pool))]
+async fn get_public_issues(pool: &PgPool, page: i64) -> Result<Vec<IssueSummary>, anyhow::Error> {
+    let issues = sqlx::query!(
+        r#"
+        SELECT slug, title, published_at
+        FROM newsletter_issues
+        WHERE visibility = 'public'
+        ORDER BY published_at DESC
+        LIMIT $1 OFFSET $2
+        "#,
+        PAGE_SIZE,
+        (page - 1) * PAGE_SIZE
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve public issues.")?
+    .into_iter()
+    .map(|r| IssueSummary {
+        slug: r.slug,
+        title: render_for_archive(r.title, String::new()).0,
+        published_at: publication_date(&r.published_at),
+    })
+    .collect();
+    Ok(issues)
+}
+
+#[tracing::instrument(name = "Get archived issue", skip(pool))]
+async fn get_issue(
+    pool: &PgPool,
+    slug: &str,
+    include_private: bool,
+) -> Result<Option<ArchivedIssue>, anyhow::Error> {
+    let issue = sqlx::query!(
+        r#"
+        SELECT title, html_content, published_at
+        FROM newsletter_issues
+        WHERE slug = $1 AND (visibility = 'public' OR $2)
+        "#,
+        slug,
+        include_private
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the issue.")?
+    .map(|r| {
+        let (title, html_content) = render_for_archive(r.title, r.html_content);
+        ArchivedIssue {
+            title,
+            html_content,
+            published_at: publication_date(&r.published_at),
+        }
+    });
+    Ok(issue)
+}
@@ -1,19 +1,37 @@
 use lazy_static::lazy_static;
-use tera::Tera;
+use tera::{Context, Tera};
+
+use crate::domain::Locale;
 
 mod admin;
+mod feeds;
 mod health_check;
 mod home;
+mod issues;
 mod login;
+mod preferences;
+mod privacy;
+mod subscriber_pages;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_unsubscribe;
+mod tracking;
+mod webhooks;
 
 pub use admin::*;
+pub use feeds::*;
 pub use health_check::*;
 pub use home::*;
+pub use issues::*;
 pub use login::*;
+pub use preferences::*;
+pub use privacy::*;
+pub use subscriber_pages::*;
 pub use subscriptions::*;
 pub use subscriptions_confirm::*;
+pub use subscriptions_unsubscribe::*;
+pub use tracking::*;
+pub use webhooks::*;
 
 lazy_static! {
     pub static ref TEMPLATES: Tera = {
@@ -27,6 +45,48 @@ lazy_static! {
     };
 }
 
+/// The variant of a template for a locale, e.g. `email/welcome.pt-BR.txt` or
+/// `email/welcome.pt.txt` for `email/welcome.txt`, falling back to the
+/// template itself, written in the default locale.
+/// Returns the name of the template and the locale it is written in.
+fn localised_template(
+    template: &str,
+    locale: &Locale,
+    exists: impl Fn(&str) -> bool,
+) -> (String, String) {
+    let (stem, extension) = template.rsplit_once('.').unwrap_or((template, ""));
+    locale
+        .fallbacks()
+        .into_iter()
+        .map(|locale| (format!("{}.{}.{}", stem, locale, extension), locale))
+        .find(|(name, _)| exists(name))
+        .map(|(name, locale)| (name, locale.to_owned()))
+        .unwrap_or_else(|| (template.to_owned(), Locale::default().to_string()))
+}
+
+/// The variant of a template `render_localised` picks for a locale, and the
+/// locale it is written in.
+pub fn localised_template_name(template: &str, locale: &Locale) -> (String, String) {
+    localised_template(template, locale, |name| {
+        TEMPLATES
+            .get_template_names()
+            .any(|existing| existing == name)
+    })
+}
+
+/// Render the variant of a template for a locale, see `localised_template`.
+/// Templates get the locale they are written in as `lang`.
+pub fn render_localised(
+    template: &str,
+    locale: &Locale,
+    context: &Context,
+) -> Result<String, tera::Error> {
+    let (template, lang) = localised_template_name(template, locale);
+    let mut context = context.clone();
+    context.insert("lang", &lang);
+    TEMPLATES.render(&template, &context)
+}
+
 pub fn error_chain_fmt(
     e: &impl std::error::Error,
     f: &mut std::fmt::Formatter<'_>,
@@ -39,3 +99,39 @@ pub fn error_chain_fmt(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::localised_template;
+    use crate::domain::Locale;
+
+    fn localised(template: &str, locale: &str, existing: &[&str]) -> (String, String) {
+        let locale = Locale::parse(locale).unwrap();
+        localised_template(template, &locale, |name| existing.contains(&name))
+    }
+
+    #[test]
+    fn the_most_specific_variant_is_picked() {
+        let existing = [
+            "email/welcome.txt",
+            "email/welcome.pt.txt",
+            "email/welcome.pt-BR.txt",
+        ];
+        assert_eq!(
+            localised("email/welcome.txt", "pt-BR", &existing),
+            ("email/welcome.pt-BR.txt".into(), "pt-BR".into())
+        );
+        assert_eq!(
+            localised("email/welcome.txt", "pt-PT", &existing),
+            ("email/welcome.pt.txt".into(), "pt".into())
+        );
+    }
+
+    #[test]
+    fn missing_variants_fall_back_to_the_default_locale() {
+        assert_eq!(
+            localised("home.html", "de", &["home.html", "home.fr.html"]),
+            ("home.html".into(), "en".into())
+        );
+    }
+}
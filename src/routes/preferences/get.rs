@@ -0,0 +1,84 @@
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::PreferencesParameters;
+use crate::domain::{DeliveryFrequency, SubscriptionStatus};
+use crate::routes::{e500, SubscriberPage, TEMPLATES};
+use crate::startup::HmacSecret;
+
+#[derive(serde::Serialize)]
+struct Preferences {
+    list: String,
+    name: String,
+    email: String,
+    status: SubscriptionStatus,
+    delivery_frequency: DeliveryFrequency,
+    paused_until: Option<DateTime<Utc>>,
+}
+
+#[tracing::instrument(name = "Show subscription preferences", skip_all)]
+pub async fn preferences(
+    request: HttpRequest,
+    parameters: web::Query<PreferencesParameters>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if let Err(page) = parameters.verify(&hmac_secret) {
+        return Ok(page.respond(&request));
+    }
+    let preferences = match get_preferences(&pool, parameters.subscriber_id)
+        .await
+        .map_err(e500)?
+    {
+        Some(preferences) => preferences,
+        None => {
+            let page = SubscriberPage::Error {
+                status: StatusCode::NOT_FOUND,
+                title: "Subscription not found",
+                message: "This subscription does not exist anymore.".into(),
+            };
+            return Ok(page.respond(&request));
+        }
+    };
+    let messages: Vec<&str> = flash_messages.iter().map(|m| m.content()).collect();
+    let mut context = tera::Context::new();
+    context.insert("preferences", &preferences);
+    context.insert("frequencies", &DeliveryFrequency::ALL);
+    context.insert("subscriber_id", &parameters.subscriber_id);
+    context.insert("signature", &parameters.signature);
+    context.insert("messages", &messages);
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(TEMPLATES.render("preferences/page.html", &context).unwrap()))
+}
+
+#[tracing::instrument(name = "Get subscription preferences", skip(pool))]
+async fn get_preferences(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Option<Preferences>, anyhow::Error> {
+    let preferences = sqlx::query_as!(
+        Preferences,
+        r#"
+        SELECT l.name AS list, s.name, s.email, s.status AS "status: SubscriptionStatus",
+               s.delivery_frequency AS "delivery_frequency: DeliveryFrequency",
+               -- Past pauses are over.
+               CASE WHEN s.paused_until > now() THEN s.paused_until END AS paused_until
+        FROM subscriptions s
+        JOIN lists l ON l.list_id = s.list_id
+        WHERE s.id = $1
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch the subscription preferences.")?;
+    Ok(preferences)
+}
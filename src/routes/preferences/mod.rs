@@ -0,0 +1,124 @@
+mod get;
+mod post;
+
+pub use get::preferences;
+pub use post::{change_email, confirm_email_change, pause_delivery, update_preferences};
+
+use actix_web::http::StatusCode;
+use chrono::{DateTime, TimeZone, Utc};
+use uuid::Uuid;
+
+use crate::routes::SubscriberPage;
+use crate::startup::HmacSecret;
+
+/// The query string of a preferences link, and the hidden fields of the
+/// forms of the preferences page.
+#[derive(serde::Deserialize)]
+pub struct PreferencesParameters {
+    subscriber_id: Uuid,
+    signature: String,
+}
+
+fn preferences_query(subscriber_id: Uuid, hmac_secret: &HmacSecret) -> String {
+    let signature = hmac_secret.sign("preferences", subscriber_id.to_string().as_bytes());
+    format!(
+        "subscriber_id={}&signature={}",
+        subscriber_id,
+        hex::encode(signature)
+    )
+}
+
+/// Build the link, put in every issue, to the page where a subscriber
+/// manages their subscription.
+/// Like unsubscribe links, it never expires.
+pub fn preferences_link(base_url: &str, subscriber_id: Uuid, hmac_secret: &HmacSecret) -> String {
+    format!(
+        "{}/preferences?{}",
+        base_url,
+        preferences_query(subscriber_id, hmac_secret)
+    )
+}
+
+/// The preferences page, to redirect to after a change.
+fn preferences_page(subscriber_id: Uuid, hmac_secret: &HmacSecret) -> String {
+    format!(
+        "/preferences?{}",
+        preferences_query(subscriber_id, hmac_secret)
+    )
+}
+
+impl PreferencesParameters {
+    fn verify(&self, hmac_secret: &HmacSecret) -> Result<(), SubscriberPage> {
+        let signature = hex::decode(&self.signature)
+            .map_err(|_| SubscriberPage::invalid_link(StatusCode::BAD_REQUEST))?;
+        if !hmac_secret.verify(
+            "preferences",
+            self.subscriber_id.to_string().as_bytes(),
+            &signature,
+        ) {
+            return Err(SubscriberPage::invalid_link(StatusCode::UNAUTHORIZED));
+        }
+        Ok(())
+    }
+}
+
+/// The query string of the link confirming a new address.
+#[derive(serde::Deserialize)]
+pub struct EmailChangeParameters {
+    subscriber_id: Uuid,
+    email: String,
+    expires: i64,
+    signature: String,
+}
+
+fn email_change_message(subscriber_id: Uuid, email: &str, expires: i64) -> Vec<u8> {
+    format!("{}/{}/{}", subscriber_id, email, expires).into_bytes()
+}
+
+/// Build the link we email to a new address, to check that it belongs to
+/// the subscriber before moving their subscription to it.
+pub fn email_change_link(
+    base_url: &str,
+    subscriber_id: Uuid,
+    email: &str,
+    expires_at: DateTime<Utc>,
+    hmac_secret: &HmacSecret,
+) -> String {
+    let expires = expires_at.timestamp();
+    let signature = hmac_secret.sign(
+        "change-email",
+        &email_change_message(subscriber_id, email, expires),
+    );
+    format!(
+        "{}/preferences/email/confirm?subscriber_id={}&email={}&expires={}&signature={}",
+        base_url,
+        subscriber_id,
+        urlencoding::encode(email),
+        expires,
+        hex::encode(signature)
+    )
+}
+
+impl EmailChangeParameters {
+    fn verify(&self, hmac_secret: &HmacSecret) -> Result<(), SubscriberPage> {
+        let signature = hex::decode(&self.signature)
+            .map_err(|_| SubscriberPage::invalid_link(StatusCode::BAD_REQUEST))?;
+        if !hmac_secret.verify(
+            "change-email",
+            &email_change_message(self.subscriber_id, &self.email, self.expires),
+            &signature,
+        ) {
+            return Err(SubscriberPage::invalid_link(StatusCode::UNAUTHORIZED));
+        }
+        if Utc.timestamp(self.expires, 0) < Utc::now() {
+            return Err(SubscriberPage::Error {
+                status: StatusCode::GONE,
+                title: "Link expired",
+                message: "This link has expired. \
+                          You can ask for a new one from your preferences page."
+                    .into(),
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,369 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::{email_change_link, preferences_page, EmailChangeParameters, PreferencesParameters};
+use crate::configuration::SubscriptionSettings;
+use crate::consent::{record_consent_event, ConsentAction, ConsentContext};
+use crate::domain::{DeliveryFrequency, SubscriberEmail, SubscriberName};
+use crate::email_client::EmailClient;
+use crate::email_policy::EmailPolicy;
+use crate::routes::{
+    e500, is_suppressed, render_localised, request_locale, see_other, SubscriberPage,
+};
+use crate::startup::{ApplicationBaseUrl, HmacSecret};
+
+/// How far ahead delivery can be paused.
+const MAX_PAUSE_DAYS: i64 = 365;
+
+#[derive(serde::Deserialize)]
+pub struct PreferencesForm {
+    #[serde(flatten)]
+    parameters: PreferencesParameters,
+    name: String,
+    delivery_frequency: String,
+}
+
+#[tracing::instrument(name = "Update subscription preferences", skip_all)]
+pub async fn update_preferences(
+    request: HttpRequest,
+    form: web::Form<PreferencesForm>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let PreferencesForm {
+        parameters,
+        name,
+        delivery_frequency,
+    } = form.0;
+    if let Err(page) = parameters.verify(&hmac_secret) {
+        return Ok(page.respond(&request));
+    }
+    let preferences_page = see_other(&preferences_page(parameters.subscriber_id, &hmac_secret));
+    let (name, delivery_frequency) = match SubscriberName::parse(name)
+        .and_then(|name| Ok((name, DeliveryFrequency::parse(&delivery_frequency)?)))
+    {
+        Ok(preferences) => preferences,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(preferences_page);
+        }
+    };
+    sqlx::query!(
+        r#"UPDATE subscriptions SET name = $2, delivery_frequency = $3 WHERE id = $1"#,
+        parameters.subscriber_id,
+        name.as_ref(),
+        delivery_frequency as DeliveryFrequency
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to update the subscription preferences.")
+    .map_err(e500)?;
+    FlashMessage::info("Your preferences have been saved.").send();
+    Ok(preferences_page)
+}
+
+#[derive(serde::Deserialize)]
+pub struct PauseForm {
+    #[serde(flatten)]
+    parameters: PreferencesParameters,
+    /// The day delivery resumes, `YYYY-MM-DD`. Blank to resume right away.
+    until: String,
+}
+
+#[tracing::instrument(name = "Pause delivery", skip_all)]
+pub async fn pause_delivery(
+    request: HttpRequest,
+    form: web::Form<PauseForm>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let PauseForm { parameters, until } = form.0;
+    if let Err(page) = parameters.verify(&hmac_secret) {
+        return Ok(page.respond(&request));
+    }
+    let preferences_page = see_other(&preferences_page(parameters.subscriber_id, &hmac_secret));
+    let paused_until = match parse_pause_end(until.trim(), Utc::now()) {
+        Ok(paused_until) => paused_until,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(preferences_page);
+        }
+    };
+    sqlx::query!(
+        r#"UPDATE subscriptions SET paused_until = $2 WHERE id = $1"#,
+        parameters.subscriber_id,
+        paused_until
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to pause delivery.")
+    .map_err(e500)?;
+    match paused_until {
+        Some(paused_until) => FlashMessage::info(format!(
+            "Delivery is paused until {}.",
+            paused_until.format("%Y-%m-%d")
+        )),
+        None => FlashMessage::info("Delivery has resumed."),
+    }
+    .send();
+    Ok(preferences_page)
+}
+
+/// The start of the day delivery resumes, in UTC.
+fn parse_pause_end(until: &str, now: DateTime<Utc>) -> Result<Option<DateTime<Utc>>, String> {
+    if until.is_empty() {
+        return Ok(None);
+    }
+    let day = NaiveDate::parse_from_str(until, "%Y-%m-%d")
+        .map_err(|_| format!("{} is not a valid date.", until))?;
+    let paused_until = DateTime::<Utc>::from_utc(day.and_hms(0, 0, 0), Utc);
+    if paused_until <= now {
+        return Err("Pick a day in the future to resume delivery.".into());
+    }
+    if paused_until > now + chrono::Duration::days(MAX_PAUSE_DAYS) {
+        return Err("Delivery can be paused for a year at most.".into());
+    }
+    Ok(Some(paused_until))
+}
+
+#[derive(serde::Deserialize)]
+pub struct EmailChangeForm {
+    #[serde(flatten)]
+    parameters: PreferencesParameters,
+    email: String,
+}
+
+/// Email a confirmation link to the new address: the subscription only
+/// moves once its owner follows it.
+#[tracing::instrument(
+    name = "Request an email address change",
+    skip(request, form, pool, email_client, base_url, hmac_secret, settings)
+)]
+pub async fn change_email(
+    request: HttpRequest,
+    form: web::Form<EmailChangeForm>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    hmac_secret: web::Data<HmacSecret>,
+    settings: web::Data<SubscriptionSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let EmailChangeForm { parameters, email } = form.0;
+    if let Err(page) = parameters.verify(&hmac_secret) {
+        return Ok(page.respond(&request));
+    }
+    let preferences_page = see_other(&preferences_page(parameters.subscriber_id, &hmac_secret));
+    let policy = request
+        .app_data::<web::Data<EmailPolicy>>()
+        .context("The email policy is not registered.")
+        .map_err(e500)?;
+    let email =
+        match SubscriberEmail::parse(email).and_then(|email| policy.check(&email).map(|_| email)) {
+            Ok(email) => email,
+            Err(e) => {
+                FlashMessage::error(e).send();
+                return Ok(preferences_page);
+            }
+        };
+    if is_address_taken(&pool, parameters.subscriber_id, &email)
+        .await
+        .map_err(e500)?
+    {
+        FlashMessage::error(format!("{} is already subscribed to this list.", email)).send();
+        return Ok(preferences_page);
+    }
+    // Suppressed addresses get no email, but the same answer.
+    if !is_suppressed(pool.get_ref(), email.as_ref())
+        .await
+        .map_err(e500)?
+    {
+        let expires_at = Utc::now() + settings.confirmation_token_ttl();
+        let mut context = tera::Context::new();
+        context.insert(
+            "link",
+            &email_change_link(
+                &base_url.0,
+                parameters.subscriber_id,
+                email.as_ref(),
+                expires_at,
+                &hmac_secret,
+            ),
+        );
+        let locale = request_locale(&request);
+        let subject =
+            render_localised("email/change_email_subject.txt", &locale, &context).map_err(e500)?;
+        let html_body =
+            render_localised("email/change_email.html", &locale, &context).map_err(e500)?;
+        let plain_body =
+            render_localised("email/change_email.txt", &locale, &context).map_err(e500)?;
+        email_client
+            .send_email(&email, subject.trim(), &html_body, &plain_body)
+            .await
+            .context("Failed to send the email change confirmation.")
+            .map_err(e500)?;
+    }
+    FlashMessage::info(format!(
+        "We sent a link to {}, follow it to confirm your new address.",
+        email
+    ))
+    .send();
+    Ok(preferences_page)
+}
+
+/// Whether another subscription to the same list uses the address.
+#[tracing::instrument(name = "Check whether an address is taken", skip(pool))]
+async fn is_address_taken(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    email: &SubscriberEmail,
+) -> Result<bool, anyhow::Error> {
+    let taken = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM subscriptions other
+            JOIN subscriptions s ON s.list_id = other.list_id
+            WHERE s.id = $1 AND other.id <> $1 AND lower(other.email) = lower($2)
+        ) AS "taken!"
+        "#,
+        subscriber_id,
+        email.as_ref()
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to look up the address.")?
+    .taken;
+    Ok(taken)
+}
+
+#[tracing::instrument(name = "Confirm an email address change", skip_all)]
+pub async fn confirm_email_change(
+    request: HttpRequest,
+    parameters: web::Query<EmailChangeParameters>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if let Err(page) = parameters.verify(&hmac_secret) {
+        return Ok(page.respond(&request));
+    }
+    let email = SubscriberEmail::parse(parameters.email.clone())
+        .map_err(anyhow::Error::msg)
+        .map_err(e500)?;
+    if is_address_taken(&pool, parameters.subscriber_id, &email)
+        .await
+        .map_err(e500)?
+    {
+        let page = SubscriberPage::Error {
+            status: actix_web::http::StatusCode::CONFLICT,
+            title: "Address already subscribed",
+            message: format!("{} is already subscribed to this list.", email),
+        };
+        return Ok(page.respond(&request));
+    }
+    let consent = ConsentContext::from_request(&request).with_source("preferences");
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    move_subscription(&mut transaction, parameters.subscriber_id, &email, &consent)
+        .await
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the email address change.")
+        .map_err(e500)?;
+    FlashMessage::info(format!("Your email address is now {}.", email)).send();
+    Ok(see_other(&preferences_page(
+        parameters.subscriber_id,
+        &hmac_secret,
+    )))
+}
+
+/// Point the subscription, and the issues still to be delivered through it,
+/// at the new address.
+#[tracing::instrument(name = "Move a subscription to a new address", skip(transaction))]
+async fn move_subscription(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    email: &SubscriberEmail,
+    consent: &ConsentContext,
+) -> Result<(), anyhow::Error> {
+    let n_updated_rows = sqlx::query!(
+        r#"UPDATE subscriptions SET email = $2 WHERE id = $1"#,
+        subscriber_id,
+        email.as_ref()
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to update the subscription address.")?
+    .rows_affected();
+    if n_updated_rows == 0 {
+        return Ok(());
+    }
+    // The new address may already be getting the same issues from another list.
+    sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_queue q
+        WHERE q.subscriber_id = $1 AND EXISTS (
+            SELECT 1 FROM issue_delivery_queue other
+            WHERE other.newsletter_issue_id = q.newsletter_issue_id
+              AND lower(other.subscriber_email) = lower($2)
+        )
+        "#,
+        subscriber_id,
+        email.as_ref()
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete duplicate deliveries.")?;
+    sqlx::query!(
+        r#"UPDATE issue_delivery_queue SET subscriber_email = $2 WHERE subscriber_id = $1"#,
+        subscriber_id,
+        email.as_ref()
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to update pending deliveries.")?;
+    record_consent_event(
+        &mut *transaction,
+        subscriber_id,
+        ConsentAction::EmailChanged,
+        consent,
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_pause_end;
+    use chrono::{TimeZone, Utc};
+    use claim::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn a_blank_day_resumes_delivery() {
+        assert_ok_eq!(parse_pause_end("", Utc::now()), None);
+    }
+
+    #[test]
+    fn delivery_resumes_at_the_start_of_the_day() {
+        let now = Utc.ymd(2026, 10, 19).and_hms(12, 0, 0);
+        assert_ok_eq!(
+            parse_pause_end("2026-11-02", now),
+            Some(Utc.ymd(2026, 11, 2).and_hms(0, 0, 0))
+        );
+    }
+
+    #[test]
+    fn past_far_away_and_invalid_days_are_rejected() {
+        let now = Utc.ymd(2026, 10, 19).and_hms(12, 0, 0);
+        for until in ["2026-10-19", "2028-01-01", "next week", "2026-13-01"] {
+            assert_err!(parse_pause_end(until, now));
+        }
+    }
+}
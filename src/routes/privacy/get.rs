@@ -0,0 +1,259 @@
+use actix_web::http::header::{ContentDisposition, ContentType, DispositionParam, DispositionType};
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use super::PrivacyParameters;
+use crate::consent::{get_consent_events, ConsentEvent};
+use crate::domain::SubscriptionStatus;
+use crate::routes::{e500, TEMPLATES};
+use crate::startup::HmacSecret;
+
+pub async fn privacy_form() -> HttpResponse {
+    HttpResponse::Ok().content_type(ContentType::html()).body(
+        TEMPLATES
+            .render("privacy/form.html", &tera::Context::new())
+            .unwrap(),
+    )
+}
+
+#[tracing::instrument(name = "Show the data held about an address", skip_all)]
+pub async fn personal_data(
+    request: HttpRequest,
+    parameters: web::Query<PrivacyParameters>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if let Err(page) = parameters.verify(&hmac_secret) {
+        return Ok(page.respond(&request));
+    }
+    let data = get_personal_data(&pool, &parameters.email)
+        .await
+        .map_err(e500)?;
+    let mut context = tera::Context::new();
+    context.insert("data", &data);
+    context.insert("query", &parameters.query(&hmac_secret));
+    context.insert("expires", &parameters.expires);
+    context.insert("signature", &parameters.signature);
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(TEMPLATES.render("privacy/data.html", &context).unwrap()))
+}
+
+#[tracing::instrument(name = "Export the data held about an address", skip_all)]
+pub async fn export_personal_data(
+    request: HttpRequest,
+    parameters: web::Query<PrivacyParameters>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if let Err(page) = parameters.verify(&hmac_secret) {
+        return Ok(page.respond(&request));
+    }
+    let data = get_personal_data(&pool, &parameters.email)
+        .await
+        .map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename("personal-data.json".into())],
+        })
+        .json(data))
+}
+
+/// Everything we hold about an email address.
+#[derive(serde::Serialize)]
+pub struct PersonalData {
+    email: String,
+    subscriptions: Vec<SubscriptionData>,
+    deliveries: Vec<DeliveryData>,
+    pending_deliveries: Vec<PendingDeliveryData>,
+    opens: Vec<OpenData>,
+    clicks: Vec<ClickData>,
+}
+
+#[derive(serde::Serialize)]
+struct SubscriptionData {
+    list: String,
+    name: String,
+    status: SubscriptionStatus,
+    subscribed_at: DateTime<Utc>,
+    confirmed_at: Option<DateTime<Utc>>,
+    consent_source: Option<String>,
+    tags: Vec<String>,
+    attributes: BTreeMap<String, String>,
+    tokens: Vec<TokenData>,
+    consent_events: Vec<ConsentEvent>,
+}
+
+#[derive(serde::Serialize)]
+struct TokenData {
+    subscription_token: String,
+    created_at: DateTime<Utc>,
+    consumed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(serde::Serialize)]
+struct DeliveryData {
+    issue: String,
+    outcome: String,
+    attempted_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct PendingDeliveryData {
+    issue: String,
+    n_retries: i16,
+    execute_after: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct OpenData {
+    issue: String,
+    first_opened_at: DateTime<Utc>,
+    last_opened_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct ClickData {
+    issue: String,
+    url: String,
+    n_clicks: i32,
+    first_clicked_at: DateTime<Utc>,
+    last_clicked_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Get the data held about an address", skip(pool))]
+async fn get_personal_data(pool: &PgPool, email: &str) -> Result<PersonalData, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT s.id, l.slug, s.name, s.status AS "status: SubscriptionStatus",
+               s.subscribed_at, s.confirmed_at, s.consent_source
+        FROM subscriptions s
+        JOIN lists l ON l.list_id = s.list_id
+        WHERE lower(s.email) = lower($1)
+        ORDER BY s.subscribed_at
+        "#,
+        email
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch subscriptions.")?;
+    let subscriber_ids: Vec<Uuid> = rows.iter().map(|r| r.id).collect();
+    let mut subscriptions = Vec::with_capacity(rows.len());
+    for r in rows {
+        let tags = sqlx::query!(
+            r#"SELECT tag FROM subscriber_tags WHERE subscriber_id = $1 ORDER BY tag"#,
+            r.id
+        )
+        .fetch_all(pool)
+        .await
+        .context("Failed to fetch tags.")?
+        .into_iter()
+        .map(|t| t.tag)
+        .collect();
+        let attributes = sqlx::query!(
+            r#"SELECT key, value FROM subscriber_attributes WHERE subscriber_id = $1"#,
+            r.id
+        )
+        .fetch_all(pool)
+        .await
+        .context("Failed to fetch attributes.")?
+        .into_iter()
+        .map(|a| (a.key, a.value))
+        .collect();
+        let tokens = sqlx::query_as!(
+            TokenData,
+            r#"
+            SELECT subscription_token, created_at, consumed_at
+            FROM subscription_tokens
+            WHERE subscriber_id = $1
+            ORDER BY created_at
+            "#,
+            r.id
+        )
+        .fetch_all(pool)
+        .await
+        .context("Failed to fetch subscription tokens.")?;
+        let consent_events = get_consent_events(pool, r.id).await?;
+        subscriptions.push(SubscriptionData {
+            list: r.slug,
+            name: r.name,
+            status: r.status,
+            subscribed_at: r.subscribed_at,
+            confirmed_at: r.confirmed_at,
+            consent_source: r.consent_source,
+            tags,
+            attributes,
+            tokens,
+            consent_events,
+        });
+    }
+    let deliveries = sqlx::query_as!(
+        DeliveryData,
+        r#"
+        SELECT i.title AS issue, d.outcome, d.attempted_at
+        FROM issue_delivery_log d
+        JOIN newsletter_issues i USING (newsletter_issue_id)
+        WHERE lower(d.subscriber_email) = lower($1)
+        ORDER BY d.attempted_at
+        "#,
+        email
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the delivery log.")?;
+    let pending_deliveries = sqlx::query_as!(
+        PendingDeliveryData,
+        r#"
+        SELECT i.title AS issue, q.n_retries, q.execute_after
+        FROM issue_delivery_queue q
+        JOIN newsletter_issues i USING (newsletter_issue_id)
+        WHERE lower(q.subscriber_email) = lower($1)
+        ORDER BY q.execute_after
+        "#,
+        email
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch pending deliveries.")?;
+    let opens = sqlx::query_as!(
+        OpenData,
+        r#"
+        SELECT i.title AS issue, o.first_opened_at, o.last_opened_at
+        FROM issue_opens o
+        JOIN newsletter_issues i USING (newsletter_issue_id)
+        WHERE o.subscriber_id = ANY($1)
+        ORDER BY o.first_opened_at
+        "#,
+        &subscriber_ids
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch opens.")?;
+    let clicks = sqlx::query_as!(
+        ClickData,
+        r#"
+        SELECT i.title AS issue, c.url, c.n_clicks, c.first_clicked_at, c.last_clicked_at
+        FROM issue_clicks c
+        JOIN newsletter_issues i USING (newsletter_issue_id)
+        WHERE c.subscriber_id = ANY($1)
+        ORDER BY c.first_clicked_at
+        "#,
+        &subscriber_ids
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch clicks.")?;
+    Ok(PersonalData {
+        email: email.to_owned(),
+        subscriptions,
+        deliveries,
+        pending_deliveries,
+        opens,
+        clicks,
+    })
+}
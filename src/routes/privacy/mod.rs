@@ -0,0 +1,82 @@
+mod get;
+mod post;
+
+pub use get::{export_personal_data, personal_data, privacy_form};
+pub use post::{erase_personal_data, request_privacy_link};
+
+use actix_web::http::StatusCode;
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::routes::SubscriberPage;
+use crate::startup::HmacSecret;
+
+/// How long a link to download or erase personal data remains valid.
+pub const PRIVACY_LINK_TTL_HOURS: i64 = 24;
+
+/// How many privacy links an address can get per hour.
+const MAX_PRIVACY_LINKS_PER_HOUR: i64 = 3;
+
+/// The query string of a privacy link, and the hidden fields of the erase form.
+#[derive(serde::Deserialize)]
+pub struct PrivacyParameters {
+    email: String,
+    expires: i64,
+    signature: String,
+}
+
+fn privacy_message(email: &str, expires: i64) -> Vec<u8> {
+    format!("{}/{}", email, expires).into_bytes()
+}
+
+fn privacy_query(email: &str, expires: i64, hmac_secret: &HmacSecret) -> String {
+    let signature = hmac_secret.sign("privacy", &privacy_message(email, expires));
+    format!(
+        "email={}&expires={}&signature={}",
+        urlencoding::encode(email),
+        expires,
+        hex::encode(signature)
+    )
+}
+
+/// Build the link we email to an address to let its owner see, download and
+/// erase the data we hold about it.
+/// The expiry is signed together with the address, so neither can be changed.
+pub fn privacy_link(
+    base_url: &str,
+    email: &str,
+    expires_at: DateTime<Utc>,
+    hmac_secret: &HmacSecret,
+) -> String {
+    format!(
+        "{}/privacy/data?{}",
+        base_url,
+        privacy_query(email, expires_at.timestamp(), hmac_secret)
+    )
+}
+
+impl PrivacyParameters {
+    fn query(&self, hmac_secret: &HmacSecret) -> String {
+        privacy_query(&self.email, self.expires, hmac_secret)
+    }
+
+    fn verify(&self, hmac_secret: &HmacSecret) -> Result<(), SubscriberPage> {
+        let signature = hex::decode(&self.signature)
+            .map_err(|_| SubscriberPage::invalid_link(StatusCode::BAD_REQUEST))?;
+        if !hmac_secret.verify(
+            "privacy",
+            &privacy_message(&self.email, self.expires),
+            &signature,
+        ) {
+            return Err(SubscriberPage::invalid_link(StatusCode::UNAUTHORIZED));
+        }
+        if Utc.timestamp(self.expires, 0) < Utc::now() {
+            return Err(SubscriberPage::Error {
+                status: StatusCode::GONE,
+                title: "Link expired",
+                message: "This link has expired. You can request a new one from the privacy page."
+                    .into(),
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{privacy_link, PrivacyParameters, MAX_PRIVACY_LINKS_PER_HOUR, PRIVACY_LINK_TTL_HOURS};
+use crate::domain::SubscriberEmail;
+use crate::email_client::EmailClient;
+use crate::routes::{
+    delete_subscriber, e500, is_suppressed, render_localised, request_locale, suppress_address,
+    SubscriberPage, TEMPLATES,
+};
+use crate::startup::{ApplicationBaseUrl, HmacSecret};
+
+#[derive(serde::Deserialize)]
+pub struct PrivacyRequestForm {
+    email: String,
+}
+
+/// Email a signed link to the owner of an address.
+/// The response is the same whether or not we hold data about the address,
+/// so the form cannot be used to find out who is subscribed.
+#[tracing::instrument(
+    name = "Request a link to personal data",
+    skip(request, form, pool, email_client, base_url, hmac_secret)
+)]
+pub async fn request_privacy_link(
+    request: HttpRequest,
+    form: web::Form<PrivacyRequestForm>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let email = match SubscriberEmail::parse(form.0.email) {
+        Ok(email) => email,
+        Err(message) => {
+            let page = SubscriberPage::Error {
+                status: StatusCode::BAD_REQUEST,
+                title: "Invalid email",
+                message,
+            };
+            return Ok(page.respond(&request));
+        }
+    };
+    let should_send = has_subscriptions(&pool, email.as_ref())
+        .await
+        .map_err(e500)?
+        && !is_suppressed(pool.get_ref(), email.as_ref())
+            .await
+            .map_err(e500)?
+        && may_send_privacy_link(&pool, email.as_ref())
+            .await
+            .map_err(e500)?;
+    if should_send {
+        let expires_at = chrono::Utc::now() + chrono::Duration::hours(PRIVACY_LINK_TTL_HOURS);
+        let locale = request_locale(&request);
+        let mut context = tera::Context::new();
+        context.insert(
+            "link",
+            &privacy_link(&base_url.0, email.as_ref(), expires_at, &hmac_secret),
+        );
+        let subject =
+            render_localised("email/privacy_subject.txt", &locale, &context).map_err(e500)?;
+        let html_body = render_localised("email/privacy.html", &locale, &context).map_err(e500)?;
+        let plain_body = render_localised("email/privacy.txt", &locale, &context).map_err(e500)?;
+        record_privacy_email(&pool, email.as_ref())
+            .await
+            .map_err(e500)?;
+        email_client
+            .send_email(&email, subject.trim(), &html_body, &plain_body)
+            .await
+            .context("Failed to send the privacy link.")
+            .map_err(e500)?;
+    }
+    let body = TEMPLATES
+        .render("privacy/requested.html", &tera::Context::new())
+        .map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(body))
+}
+
+#[tracing::instrument(name = "Erase the data held about an address", skip_all)]
+pub async fn erase_personal_data(
+    request: HttpRequest,
+    form: web::Form<PrivacyParameters>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if let Err(page) = form.verify(&hmac_secret) {
+        return Ok(page.respond(&request));
+    }
+    erase_address(&pool, &form.email).await.map_err(e500)?;
+    let body = TEMPLATES
+        .render("privacy/erased.html", &tera::Context::new())
+        .map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(body))
+}
+
+/// Whether an address may get another privacy link, so that the form cannot
+/// be used to flood an inbox.
+#[tracing::instrument(name = "Check the privacy link rate limit", skip(pool))]
+async fn may_send_privacy_link(pool: &PgPool, email: &str) -> Result<bool, anyhow::Error> {
+    let n_sent = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!" FROM privacy_emails
+        WHERE lower(email) = lower($1) AND sent_at > now() - interval '1 hour'
+        "#,
+        email
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to count recent privacy emails.")?
+    .count;
+    Ok(n_sent < MAX_PRIVACY_LINKS_PER_HOUR)
+}
+
+#[tracing::instrument(name = "Record a privacy email", skip(pool))]
+async fn record_privacy_email(pool: &PgPool, email: &str) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"INSERT INTO privacy_emails (email, sent_at) VALUES ($1, now())"#,
+        email
+    )
+    .execute(pool)
+    .await
+    .context("Failed to record the privacy email.")?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Check whether an address has subscriptions", skip(pool))]
+async fn has_subscriptions(pool: &PgPool, email: &str) -> Result<bool, anyhow::Error> {
+    let exists = sqlx::query!(
+        r#"SELECT EXISTS (SELECT 1 FROM subscriptions WHERE lower(email) = lower($1)) AS "exists!""#,
+        email
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to look up subscriptions.")?
+    .exists;
+    Ok(exists)
+}
+
+/// Delete every subscription of an address with everything attached to it.
+/// Delivery records are kept for the issue statistics, under a random
+/// placeholder instead of the address.
+/// Only the hash of the address survives, on the suppression list, so that
+/// we never email it again.
+#[tracing::instrument(name = "Erase an address", skip(pool))]
+async fn erase_address(pool: &PgPool, email: &str) -> Result<(), anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    sqlx::query!(
+        r#"UPDATE issue_delivery_log SET subscriber_email = $2 WHERE lower(subscriber_email) = lower($1)"#,
+        email,
+        format!("erased-{}", Uuid::new_v4())
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to anonymise the delivery log.")?;
+    let subscriber_ids = sqlx::query!(
+        r#"SELECT id FROM subscriptions WHERE lower(email) = lower($1)"#,
+        email
+    )
+    .fetch_all(&mut transaction)
+    .await
+    .context("Failed to fetch subscriptions.")?;
+    for r in subscriber_ids {
+        delete_subscriber(&mut transaction, r.id)
+            .await
+            .context("Failed to delete a subscription.")?;
+        sqlx::query!(
+            r#"DELETE FROM consent_events WHERE subscriber_id = $1"#,
+            r.id
+        )
+        .execute(&mut transaction)
+        .await
+        .context("Failed to delete consent events.")?;
+    }
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_queue WHERE lower(subscriber_email) = lower($1)"#,
+        email
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to delete pending deliveries.")?;
+    sqlx::query!(
+        r#"DELETE FROM confirmation_emails WHERE lower(email) = lower($1)"#,
+        email
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to delete confirmation email records.")?;
+    sqlx::query!(
+        r#"DELETE FROM privacy_emails WHERE lower(email) = lower($1)"#,
+        email
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to delete privacy email records.")?;
+    sqlx::query!(
+        r#"DELETE FROM email_soft_bounces WHERE lower(email) = lower($1)"#,
+        email
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to delete soft bounce records.")?;
+    sqlx::query!(
+        r#"DELETE FROM subscribe_attempts WHERE lower(email) = lower($1)"#,
+        email
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to delete subscription requests.")?;
+    suppress_address(&mut transaction, email, "legal_request", "erasure").await?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to erase an address.")?;
+    Ok(())
+}
@@ -0,0 +1,155 @@
+use actix_web::error::InternalError;
+use actix_web::http::header::{ContentType, ACCEPT, ACCEPT_LANGUAGE};
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse};
+
+use super::render_localised;
+use crate::domain::Locale;
+
+/// What subscribers see after following a link from their inbox or
+/// submitting the subscription form.
+pub enum SubscriberPage {
+    CheckYourInbox,
+    Confirmed,
+    AlreadyConfirmed,
+    Resent,
+    Unsubscribed {
+        list: String,
+    },
+    Expired {
+        subscription_token: String,
+    },
+    InvalidLink {
+        status: StatusCode,
+    },
+    SomethingWentWrong,
+    /// Errors with a message of their own, e.g. why an address is invalid.
+    Error {
+        status: StatusCode,
+        title: &'static str,
+        message: String,
+    },
+}
+
+impl SubscriberPage {
+    pub fn invalid_link(status: StatusCode) -> Self {
+        Self::InvalidLink { status }
+    }
+
+    pub fn something_went_wrong() -> Self {
+        Self::SomethingWentWrong
+    }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::Expired { .. } => StatusCode::GONE,
+            Self::InvalidLink { status } | Self::Error { status, .. } => *status,
+            Self::SomethingWentWrong => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::OK,
+        }
+    }
+
+    fn json(&self) -> serde_json::Value {
+        match self {
+            Self::CheckYourInbox | Self::Resent => {
+                serde_json::json!({ "status": "pending_confirmation" })
+            }
+            Self::Confirmed => serde_json::json!({ "status": "confirmed" }),
+            Self::AlreadyConfirmed => serde_json::json!({ "status": "already_confirmed" }),
+            Self::Unsubscribed { .. } => serde_json::json!({ "status": "unsubscribed" }),
+            Self::Expired { .. } => serde_json::json!({
+                "error": "This confirmation link has expired.",
+                "resend_url": "/subscriptions/resend",
+            }),
+            Self::InvalidLink { .. } => serde_json::json!({
+                "error": "This link is not valid. Check that you copied the whole link from the email.",
+            }),
+            Self::SomethingWentWrong => serde_json::json!({
+                "error": "We could not process your request. Please try again later.",
+            }),
+            Self::Error { message, .. } => serde_json::json!({ "error": message }),
+        }
+    }
+
+    fn html(&self, locale: &Locale) -> String {
+        let mut context = tera::Context::new();
+        let template = match self {
+            Self::CheckYourInbox => "subscriptions/check_inbox.html",
+            Self::Confirmed => "subscriptions/confirmed.html",
+            Self::AlreadyConfirmed => "subscriptions/already_confirmed.html",
+            Self::Resent => "subscriptions/resent.html",
+            Self::Unsubscribed { list } => {
+                context.insert("list", list);
+                "subscriptions/unsubscribed.html"
+            }
+            Self::Expired { subscription_token } => {
+                context.insert("subscription_token", subscription_token);
+                "subscriptions/expired.html"
+            }
+            Self::InvalidLink { .. } => "subscriptions/invalid_link.html",
+            Self::SomethingWentWrong => "subscriptions/something_went_wrong.html",
+            Self::Error { title, message, .. } => {
+                context.insert("title", title);
+                context.insert("message", message);
+                "subscriptions/error.html"
+            }
+        };
+        render_localised(template, locale, &context).unwrap()
+    }
+
+    /// An HTML page in the language of the browser, or a JSON body for
+    /// clients sending `Accept: application/json`.
+    pub fn respond(&self, request: &HttpRequest) -> HttpResponse {
+        self.respond_in(request, &request_locale(request))
+    }
+
+    /// `respond`, with the HTML page in the given language.
+    pub fn respond_in(&self, request: &HttpRequest, locale: &Locale) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status());
+        if wants_json(request) {
+            response.json(self.json())
+        } else {
+            response
+                .content_type(ContentType::html())
+                .body(self.html(locale))
+        }
+    }
+}
+
+/// Unexpected errors get the "something went wrong" page, their details are
+/// only logged.
+pub fn page_response(
+    request: &HttpRequest,
+    page: Result<SubscriberPage, anyhow::Error>,
+) -> Result<HttpResponse, actix_web::Error> {
+    match page {
+        Ok(page) => Ok(page.respond(request)),
+        Err(e) => {
+            let response = SubscriberPage::something_went_wrong().respond(request);
+            Err(InternalError::from_response(e, response).into())
+        }
+    }
+}
+
+pub fn wants_json(request: &HttpRequest) -> bool {
+    request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .map(|accept| accept.contains("application/json"))
+        .unwrap_or(false)
+}
+
+/// The language of the browser, from its `Accept-Language` header.
+pub fn request_locale(request: &HttpRequest) -> Locale {
+    request
+        .headers()
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|header| header.to_str().ok())
+        .and_then(Locale::from_accept_language)
+        .unwrap_or_default()
+}
+
+pub async fn check_your_inbox(request: HttpRequest) -> HttpResponse {
+    SubscriberPage::CheckYourInbox.respond(&request)
+}
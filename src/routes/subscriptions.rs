@@ -1,19 +1,37 @@
-use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName, SubscriberToken};
+use crate::abuse_protection::{AbuseProtection, AntiBotFields, Verdict};
+use crate::configuration::SubscriptionSettings;
+use crate::consent::{record_consent_event, ConsentAction, ConsentContext};
+use crate::domain::{
+    ListSlug, Locale, NewSubscriber, SubscriberEmail, SubscriberName, SubscriberToken,
+    SubscriptionStatus,
+};
 use crate::email_client::EmailClient;
-use crate::startup::ApplicationBaseUrl;
+use crate::email_policy::{EmailFlag, EmailPolicy};
+use crate::email_templates::{render_email, TransactionalEmail};
+use crate::startup::{ApplicationBaseUrl, HmacSecret};
+use actix_web::error::InternalError;
 use actix_web::http::StatusCode;
-use actix_web::{web, HttpResponse, ResponseError};
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
 use anyhow::Context;
-use chrono::Utc;
-use sqlx::{PgPool, Postgres, Transaction};
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
-use super::{error_chain_fmt, TEMPLATES};
+use super::{
+    error_chain_fmt, is_suppressed, mark_confirmed, preferences_link, request_locale, see_other,
+    unsubscribe_link, wants_json, SubscriberPage,
+};
 
 #[derive(thiserror::Error)]
 pub enum SubscribeError {
     #[error("{0}")]
     ValidationError(String),
+    #[error("There is no mailing list named {0}.")]
+    UnknownList(String),
+    #[error("Too many subscription requests, please try again later.")]
+    RateLimited,
+    #[error("We could not verify that you are not a robot.")]
+    ChallengeFailed,
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -27,6 +45,9 @@ impl ResponseError for SubscribeError {
     fn status_code(&self) -> StatusCode {
         match self {
             SubscribeError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            SubscribeError::UnknownList(_) => StatusCode::NOT_FOUND,
+            SubscribeError::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            SubscribeError::ChallengeFailed => StatusCode::BAD_REQUEST,
             SubscribeError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -35,6 +56,12 @@ impl ResponseError for SubscribeError {
 pub struct FormData {
     email: String,
     name: String,
+    /// Which form the subscriber used, kept as evidence of their consent.
+    source: Option<String>,
+    /// The language to email the subscriber in, instead of the browser's.
+    locale: Option<String>,
+    #[serde(flatten)]
+    anti_bot: AntiBotFields,
 }
 
 impl TryFrom<FormData> for NewSubscriber {
@@ -48,77 +75,454 @@ impl TryFrom<FormData> for NewSubscriber {
 
 #[tracing::instrument(
     name = "Send a confirmation email to a new subscriber",
-    skip(email_client, new_subscriber, base_url)
+    skip(pool, email_client, email, base_url)
 )]
 pub async fn send_confirmation_email(
+    pool: &PgPool,
     email_client: &EmailClient,
-    new_subscriber: NewSubscriber,
+    email: &SubscriberEmail,
     base_url: &str,
+    list_slug: &ListSlug,
     subscription_token: &SubscriberToken,
-) -> Result<(), reqwest::Error> {
+    locale: &Locale,
+) -> Result<(), anyhow::Error> {
+    // Do not tell the subscriber apart: the address may not be theirs.
+    if is_suppressed(pool, email.as_ref()).await? {
+        tracing::info!("Not sending a confirmation email to a suppressed address.");
+        return Ok(());
+    }
     let mut context = tera::Context::new();
     context.insert(
         "link",
         &format!(
-            "{}/subscriptions/confirm?subscription_token={}",
+            "{}/lists/{}/subscriptions/confirm?subscription_token={}",
             base_url,
+            list_slug,
             subscription_token.as_ref()
         ),
     );
-    let html_body = TEMPLATES.render("email/html_email.html", &context).unwrap();
-
-    let plain_body = TEMPLATES.render("email/plain_email.txt", &context).unwrap();
+    let rendered = render_email(pool, TransactionalEmail::Confirmation, locale, &context).await?;
 
     email_client
-        .send_email(&new_subscriber.email, "Welcome!", &html_body, &plain_body)
-        .await
+        .send_email(email, &rendered.subject, &rendered.html, &rendered.text)
+        .await?;
+    record_confirmation_email(pool, email).await?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Record a confirmation email", skip(pool))]
+async fn record_confirmation_email(
+    pool: &PgPool,
+    email: &SubscriberEmail,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"INSERT INTO confirmation_emails (email, sent_at) VALUES ($1, now())"#,
+        email.as_ref()
+    )
+    .execute(pool)
+    .await
+    .context("Failed to record a confirmation email.")?;
+    Ok(())
+}
+
+/// Whether the address got fewer confirmation emails than allowed in the last hour.
+#[tracing::instrument(name = "Check the confirmation email rate limit", skip(pool, settings))]
+pub async fn may_send_confirmation_email(
+    pool: &PgPool,
+    email: &SubscriberEmail,
+    settings: &SubscriptionSettings,
+) -> Result<bool, anyhow::Error> {
+    let n_sent = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!" FROM confirmation_emails
+        WHERE lower(email) = lower($1) AND sent_at > now() - interval '1 hour'
+        "#,
+        email.as_ref()
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to count recent confirmation emails.")?
+    .count;
+    Ok(n_sent < settings.max_confirmation_emails_per_hour)
 }
 
+/// Send browsers to the "check your inbox" page, and API clients the status
+/// of their subscription.
+fn subscribe_response(
+    request: &HttpRequest,
+    result: Result<SubscriberPage, SubscribeError>,
+) -> Result<HttpResponse, actix_web::Error> {
+    match result {
+        Ok(SubscriberPage::CheckYourInbox) if !wants_json(request) => {
+            Ok(see_other("/subscriptions/check-your-inbox"))
+        }
+        Ok(page) => Ok(page.respond(request)),
+        Err(e) => {
+            let page = match &e {
+                SubscribeError::UnexpectedError(_) => SubscriberPage::something_went_wrong(),
+                _ => SubscriberPage::Error {
+                    status: e.status_code(),
+                    title: "We could not subscribe you",
+                    message: e.to_string(),
+                },
+            };
+            let response = page.respond(request);
+            Err(InternalError::from_response(e, response).into())
+        }
+    }
+}
+
+/// Whether to go on with a subscription request, and what to flag the
+/// subscriber for. Bots get the same response as everybody else, but no
+/// email is sent.
+async fn screen_request(
+    request: &HttpRequest,
+    pool: &PgPool,
+    form: &FormData,
+    consent: &ConsentContext,
+) -> Result<Option<Vec<EmailFlag>>, SubscribeError> {
+    // Invalid addresses are reported by `add_subscriber`.
+    let flags = match SubscriberEmail::parse(form.email.clone()) {
+        Ok(email) => request
+            .app_data::<web::Data<EmailPolicy>>()
+            .context("The email policy is not registered.")?
+            .check(&email)
+            .map_err(SubscribeError::ValidationError)?,
+        Err(_) => vec![],
+    };
+    let protection = request
+        .app_data::<web::Data<AbuseProtection>>()
+        .context("The abuse protection is not registered.")?;
+    let verdict = protection
+        .check(
+            pool,
+            &form.email,
+            &form.anti_bot,
+            consent.ip_address.as_deref(),
+        )
+        .await?;
+    match verdict {
+        Verdict::Accept => Ok(Some(flags)),
+        Verdict::Discard => Ok(None),
+        Verdict::RateLimited => Err(SubscribeError::RateLimited),
+        Verdict::ChallengeFailed => Err(SubscribeError::ChallengeFailed),
+    }
+}
+
+/// Tag the subscriber so that admins can review the addresses the email
+/// policy let through.
+#[tracing::instrument(name = "Flag a subscriber", skip(executor))]
+pub async fn flag_subscriber(
+    executor: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+    flags: &[EmailFlag],
+) -> Result<(), SubscribeError> {
+    if flags.is_empty() {
+        return Ok(());
+    }
+    let tags: Vec<String> = flags.iter().map(|flag| flag.tag().to_owned()).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriber_tags (subscriber_id, tag)
+        SELECT $1, tag FROM UNNEST($2::TEXT[]) AS tag
+        ON CONFLICT DO NOTHING
+        "#,
+        subscriber_id,
+        &tags
+    )
+    .execute(executor)
+    .await
+    .context("Failed to flag the subscriber.")?;
+    Ok(())
+}
+
+/// Subscribe to the default list.
+pub async fn subscribe(
+    request: HttpRequest,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    settings: web::Data<SubscriptionSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let consent = ConsentContext::from_request(&request);
+    let result = match screen_request(&request, &pool, &form, &consent).await {
+        Ok(Some(flags)) => {
+            let subscription = add_subscriber(
+                &request,
+                ListSlug::default(),
+                form.0,
+                &pool,
+                &settings,
+                consent,
+            )
+            .await;
+            match subscription {
+                Ok((subscriber_id, page)) => flag_subscriber(pool.get_ref(), subscriber_id, &flags)
+                    .await
+                    .map(|()| page),
+                Err(e) => Err(e),
+            }
+        }
+        Ok(None) => Ok(SubscriberPage::CheckYourInbox),
+        Err(e) => Err(e),
+    };
+    subscribe_response(&request, result)
+}
+
+pub async fn subscribe_to_list(
+    request: HttpRequest,
+    list_slug: web::Path<String>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    settings: web::Data<SubscriptionSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let list_slug = list_slug.into_inner();
+    let consent = ConsentContext::from_request(&request);
+    let result = match ListSlug::parse(list_slug.clone()) {
+        Ok(list_slug) => match screen_request(&request, &pool, &form, &consent).await {
+            Ok(Some(flags)) => {
+                let subscription =
+                    add_subscriber(&request, list_slug, form.0, &pool, &settings, consent).await;
+                match subscription {
+                    Ok((subscriber_id, page)) => {
+                        flag_subscriber(pool.get_ref(), subscriber_id, &flags)
+                            .await
+                            .map(|()| page)
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            Ok(None) => Ok(SubscriberPage::CheckYourInbox),
+            Err(e) => Err(e),
+        },
+        Err(_) => Err(SubscribeError::UnknownList(list_slug)),
+    };
+    subscribe_response(&request, result)
+}
+
+/// Add a subscriber and email them, returning the page to show them.
+/// Subscribers of double opt-in lists are sent a confirmation link, the
+/// others are confirmed right away and welcomed.
 #[tracing::instrument(
     name = "Adding a new subscriber",
-    skip(form, pool, email_client, base_url),
+    skip(request, form, pool, settings, consent),
     fields(
         subscriber_email = %form.email,
         subscriber_name= %form.name
     )
 )]
-pub async fn subscribe(
-    form: web::Form<FormData>,
-    pool: web::Data<PgPool>,
-    email_client: web::Data<EmailClient>,
-    base_url: web::Data<ApplicationBaseUrl>,
-) -> Result<HttpResponse, SubscribeError> {
-    let new_subscriber: NewSubscriber =
-        form.0.try_into().map_err(SubscribeError::ValidationError)?;
+async fn add_subscriber(
+    request: &HttpRequest,
+    list_slug: ListSlug,
+    form: FormData,
+    pool: &PgPool,
+    settings: &SubscriptionSettings,
+    consent: ConsentContext,
+) -> Result<(Uuid, SubscriberPage), SubscribeError> {
+    let consent = consent.with_source(
+        form.source
+            .clone()
+            .filter(|source| !source.trim().is_empty())
+            .unwrap_or_else(|| "subscription_form".into()),
+    );
+    let locale = form
+        .locale
+        .as_deref()
+        .and_then(|locale| Locale::parse(locale).ok())
+        .unwrap_or_else(|| request_locale(request));
+    let new_subscriber: NewSubscriber = form.try_into().map_err(SubscribeError::ValidationError)?;
 
     let mut transaction = pool
         .begin()
         .await
         .context("Failed to acquire a Postgres connection from the pool")?;
-    let sub_id = select_or_insert_subscriber_by_email(&mut transaction, &new_subscriber)
+    let list = get_list(&mut transaction, &list_slug)
         .await
-        .context("Failed to insert new subscriber in the database.")?;
+        .context("Failed to look up the mailing list.")?
+        .ok_or_else(|| SubscribeError::UnknownList(list_slug.to_string()))?;
+    let double_opt_in = list.double_opt_in.unwrap_or(settings.double_opt_in);
+    // Everybody gets the same page, whatever the state of their subscription.
+    let page = if double_opt_in {
+        SubscriberPage::CheckYourInbox
+    } else {
+        SubscriberPage::Confirmed
+    };
+    let sub_id =
+        select_or_insert_subscriber_by_email(&mut transaction, list.list_id, &new_subscriber)
+            .await
+            .context("Failed to insert new subscriber in the database.")?;
+    let status = restart_confirmation(&mut transaction, sub_id).await?;
+    if status != SubscriptionStatus::PendingConfirmation {
+        // Confirmed subscribers have nothing to do, complaints are final.
+        tracing::info!(
+            "Not sending a confirmation email to a {} subscriber.",
+            status
+        );
+        return Ok((sub_id, page));
+    }
+    set_locale(&mut transaction, sub_id, &locale).await?;
+    record_consent_event(
+        &mut transaction,
+        sub_id,
+        ConsentAction::SubscribeRequested,
+        &consent,
+    )
+    .await?;
 
-    let subscription_token = SubscriberToken::new();
-    store_token(&mut transaction, sub_id, &subscription_token)
+    if !double_opt_in {
+        let consent = ConsentContext {
+            source: Some("single_opt_in".into()),
+            ..consent
+        };
+        mark_confirmed(&mut transaction, sub_id, ConsentAction::Confirmed, &consent).await?;
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit SQL transaction to store a new subscriber.")?;
+        send_welcome_email(
+            request,
+            pool,
+            &new_subscriber.email,
+            &list_slug,
+            &list.name,
+            sub_id,
+            &locale,
+        )
         .await
-        .context("Failed to store the confirmation token for a new subscriber.")?;
+        .context("Failed to send a welcome email.")?;
+        return Ok((sub_id, page));
+    }
+
+    let subscription_token =
+        reuse_or_rotate_token(&mut transaction, sub_id, settings.confirmation_token_ttl())
+            .await
+            .context("Failed to store the confirmation token for a new subscriber.")?;
 
     transaction
         .commit()
         .await
         .context("Failed to commit SQL transaction to store a new subscriber.")?;
 
+    if !may_send_confirmation_email(pool, &new_subscriber.email, settings).await? {
+        // The token was reused: the emails already sent still work.
+        tracing::warn!("Too many confirmation emails were sent to this address.");
+        return Ok((sub_id, page));
+    }
     send_confirmation_email(
-        &email_client,
-        new_subscriber,
-        &base_url.0,
+        pool,
+        app_data::<EmailClient>(request)?,
+        &new_subscriber.email,
+        &app_data::<ApplicationBaseUrl>(request)?.0,
+        &list_slug,
         &subscription_token,
+        &locale,
     )
     .await
     .context("Failed to send a confirmation email.")?;
 
-    Ok(HttpResponse::Ok().finish())
+    Ok((sub_id, page))
+}
+
+/// A dependency registered with `App::app_data`.
+fn app_data<T: 'static>(request: &HttpRequest) -> Result<&T, anyhow::Error> {
+    request
+        .app_data::<web::Data<T>>()
+        .map(|data| data.get_ref())
+        .with_context(|| format!("{} is not registered.", std::any::type_name::<T>()))
+}
+
+/// Greet a subscriber who did not have to confirm their address, with the
+/// links every issue will carry.
+#[tracing::instrument(
+    name = "Send a welcome email to a new subscriber",
+    skip(request, pool, email)
+)]
+async fn send_welcome_email(
+    request: &HttpRequest,
+    pool: &PgPool,
+    email: &SubscriberEmail,
+    list_slug: &ListSlug,
+    list_name: &str,
+    subscriber_id: Uuid,
+    locale: &Locale,
+) -> Result<(), anyhow::Error> {
+    if is_suppressed(pool, email.as_ref()).await? {
+        tracing::info!("Not sending a welcome email to a suppressed address.");
+        return Ok(());
+    }
+    let base_url = &app_data::<ApplicationBaseUrl>(request)?.0;
+    let hmac_secret = app_data::<HmacSecret>(request)?;
+    let mut context = tera::Context::new();
+    context.insert("list", list_name);
+    context.insert(
+        "unsubscribe_link",
+        &unsubscribe_link(base_url, list_slug, subscriber_id, hmac_secret),
+    );
+    context.insert(
+        "preferences_link",
+        &preferences_link(base_url, subscriber_id, hmac_secret),
+    );
+    let rendered = render_email(pool, TransactionalEmail::Welcome, locale, &context).await?;
+    app_data::<EmailClient>(request)?
+        .send_email(email, &rendered.subject, &rendered.html, &rendered.text)
+        .await?;
+    Ok(())
+}
+
+/// Remember the language a subscriber asked for, the last one wins.
+#[tracing::instrument(name = "Set the locale of a subscriber", skip(transaction))]
+async fn set_locale(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    locale: &Locale,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"UPDATE subscriptions SET locale = $2 WHERE id = $1"#,
+        subscriber_id,
+        locale.as_ref()
+    )
+    .execute(transaction)
+    .await
+    .context("Failed to store the locale of the subscriber.")?;
+    Ok(())
+}
+
+/// What subscribing to a list takes.
+struct ListSettings {
+    list_id: Uuid,
+    name: String,
+    double_opt_in: Option<bool>,
+}
+
+#[tracing::instrument(name = "Get list settings from slug", skip(executor))]
+async fn get_list(
+    executor: impl PgExecutor<'_>,
+    list_slug: &ListSlug,
+) -> Result<Option<ListSettings>, sqlx::Error> {
+    sqlx::query_as!(
+        ListSettings,
+        r#"SELECT list_id, name, double_opt_in FROM lists WHERE slug = $1"#,
+        list_slug.as_ref(),
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+#[tracing::instrument(name = "Get list_id from slug", skip(executor))]
+pub async fn get_list_id(
+    executor: impl PgExecutor<'_>,
+    list_slug: &ListSlug,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"SELECT list_id FROM lists WHERE slug = $1"#,
+        list_slug.as_ref(),
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+    Ok(result.map(|r| r.list_id))
 }
 
 pub struct StoreTokenError(sqlx::Error);
@@ -167,21 +571,96 @@ pub async fn store_token(
     Ok(())
 }
 
+/// Subscribing again restarts the confirmation of subscriptions that ended,
+/// when their status allows it. Returns the resulting status.
+#[tracing::instrument(name = "Restart the confirmation of a subscription", skip(transaction))]
+async fn restart_confirmation(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<SubscriptionStatus, anyhow::Error> {
+    let status = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET status = CASE WHEN status::TEXT = ANY($3) THEN $2 ELSE status END
+        WHERE id = $1
+        RETURNING status AS "status: SubscriptionStatus"
+        "#,
+        subscriber_id,
+        SubscriptionStatus::PendingConfirmation as SubscriptionStatus,
+        &SubscriptionStatus::PendingConfirmation.allowed_sources()
+    )
+    .fetch_one(transaction)
+    .await
+    .context("Failed to restart the confirmation of the subscription.")?
+    .status;
+    Ok(status)
+}
+
+/// The subscriber's pending token if it has not expired yet, a new one otherwise.
+/// Repeated subscriptions then send the same confirmation link.
+#[tracing::instrument(name = "Reuse or rotate the subscription token", skip(transaction))]
+pub async fn reuse_or_rotate_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    ttl: chrono::Duration,
+) -> Result<SubscriberToken, anyhow::Error> {
+    let valid_since: DateTime<Utc> = Utc::now() - ttl;
+    let pending_token = sqlx::query!(
+        r#"
+        SELECT subscription_token FROM subscription_tokens
+        WHERE subscriber_id = $1 AND created_at > $2 AND consumed_at IS NULL
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+        subscriber_id,
+        valid_since
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to look up a pending subscription token.")?;
+    if let Some(r) = pending_token {
+        if let Ok(token) = SubscriberToken::parse(r.subscription_token) {
+            return Ok(token);
+        }
+    }
+    rotate_token(transaction, subscriber_id).await
+}
+
+/// Replace all the tokens of a subscriber with a new one.
+#[tracing::instrument(name = "Rotate the subscription token", skip(transaction))]
+pub async fn rotate_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<SubscriberToken, anyhow::Error> {
+    sqlx::query!(
+        r#"DELETE FROM subscription_tokens WHERE subscriber_id = $1"#,
+        subscriber_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete old subscription tokens.")?;
+    let subscription_token = SubscriberToken::new();
+    store_token(transaction, subscriber_id, &subscription_token).await?;
+    Ok(subscription_token)
+}
+
 #[tracing::instrument(
     name = "Saving new subscriber details in the database",
     skip(new_subscriber, transaction)
 )]
 pub async fn insert_subscriber(
     transaction: &mut Transaction<'_, Postgres>,
+    list_id: Uuid,
     new_subscriber: &NewSubscriber,
 ) -> Result<Uuid, sqlx::Error> {
     let sub_id = Uuid::new_v4();
     sqlx::query!(
         r#"
-    INSERT INTO subscriptions (id, email, name, subscribed_at, status)
-    VALUES ($1, $2, $3, $4, 'pending_confirmation')
+    INSERT INTO subscriptions (id, list_id, email, name, subscribed_at, status)
+    VALUES ($1, $2, $3, $4, $5, 'pending_confirmation')
     "#,
         sub_id,
+        list_id,
         new_subscriber.email.as_ref(),
         // Using `inner_ref`!
         new_subscriber.name.as_ref(),
@@ -202,12 +681,14 @@ pub async fn insert_subscriber(
 )]
 pub async fn select_subscriber_by_email(
     transaction: &mut Transaction<'_, Postgres>,
+    list_id: Uuid,
     subscriber_email: &SubscriberEmail,
 ) -> Result<Uuid, sqlx::Error> {
     let sub_record = sqlx::query!(
         r#"
-    SELECT id FROM subscriptions WHERE email = $1
+    SELECT id FROM subscriptions WHERE list_id = $1 AND lower(email) = lower($2)
     "#,
+        list_id,
         subscriber_email.as_ref(),
     )
     .fetch_one(transaction)
@@ -225,11 +706,14 @@ pub async fn select_subscriber_by_email(
 )]
 pub async fn select_or_insert_subscriber_by_email(
     transaction: &mut Transaction<'_, Postgres>,
+    list_id: Uuid,
     new_subscriber: &NewSubscriber,
 ) -> Result<Uuid, sqlx::Error> {
-    match select_subscriber_by_email(transaction, &new_subscriber.email).await {
+    match select_subscriber_by_email(transaction, list_id, &new_subscriber.email).await {
         Ok(sub_id) => Ok(sub_id),
-        Err(sqlx::Error::RowNotFound) => insert_subscriber(transaction, new_subscriber).await,
+        Err(sqlx::Error::RowNotFound) => {
+            insert_subscriber(transaction, list_id, new_subscriber).await
+        }
         Err(e) => Err(e),
     }
 }
@@ -1,8 +1,20 @@
-use actix_web::{web, HttpResponse};
-use sqlx::PgPool;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
-use crate::domain::SubscriberToken;
+use super::{
+    may_send_confirmation_email, page_response, reuse_or_rotate_token, send_confirmation_email,
+    SubscriberPage,
+};
+use crate::configuration::SubscriptionSettings;
+use crate::consent::{record_consent_event, ConsentAction, ConsentContext};
+use crate::domain::{ListSlug, Locale, SubscriberEmail, SubscriberToken, SubscriptionStatus};
+use crate::email_client::EmailClient;
+use crate::sequences::start_sequences;
+use crate::startup::ApplicationBaseUrl;
 
 #[derive(serde::Deserialize)]
 pub struct Parameters {
@@ -17,52 +29,272 @@ impl TryFrom<Parameters> for SubscriberToken {
     }
 }
 
-#[tracing::instrument(name = "Confirm a pending subscriber", skip(parameters, pool))]
-pub async fn confirm(parameters: web::Query<Parameters>, pool: web::Data<PgPool>) -> HttpResponse {
-    let token = match parameters.0.try_into() {
-        Ok(t) => t,
-        Err(_) => return HttpResponse::BadRequest().finish(),
+/// Kept for confirmation links sent before lists were introduced.
+#[tracing::instrument(
+    name = "Confirm a pending subscriber",
+    skip(request, parameters, pool, settings)
+)]
+pub async fn confirm(
+    request: HttpRequest,
+    parameters: web::Query<Parameters>,
+    pool: web::Data<PgPool>,
+    settings: web::Data<SubscriptionSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let consent = ConsentContext::from_request(&request);
+    let page = confirm_token(parameters.0, None, &pool, &settings, &consent).await;
+    page_response(&request, page)
+}
+
+#[tracing::instrument(
+    name = "Confirm a pending list subscriber",
+    skip(request, parameters, pool, settings)
+)]
+pub async fn confirm_list_subscription(
+    request: HttpRequest,
+    list_slug: web::Path<String>,
+    parameters: web::Query<Parameters>,
+    pool: web::Data<PgPool>,
+    settings: web::Data<SubscriptionSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let list_slug = match ListSlug::parse(list_slug.into_inner()) {
+        Ok(s) => s,
+        Err(_) => return Ok(SubscriberPage::invalid_link(StatusCode::NOT_FOUND).respond(&request)),
     };
-    let id = match get_subscriber_id_from_token(&pool, &token).await {
-        Ok(id) => id,
-        Err(_) => return HttpResponse::InternalServerError().finish(),
+    let consent = ConsentContext::from_request(&request);
+    let page = confirm_token(parameters.0, Some(&list_slug), &pool, &settings, &consent).await;
+    page_response(&request, page)
+}
+
+async fn confirm_token(
+    parameters: Parameters,
+    list_slug: Option<&ListSlug>,
+    pool: &PgPool,
+    settings: &SubscriptionSettings,
+    consent: &ConsentContext,
+) -> Result<SubscriberPage, anyhow::Error> {
+    let token: SubscriberToken = match parameters.try_into() {
+        Ok(t) => t,
+        Err(_) => return Ok(SubscriberPage::invalid_link(StatusCode::BAD_REQUEST)),
     };
-    match id {
+    let issued_token = get_issued_token(pool, &token, list_slug)
+        .await
+        .context("Failed to look up the subscription token.")?;
+    let page = match issued_token {
         // Non-existing token!
-        None => HttpResponse::Unauthorized().finish(),
-        Some(subscriber_id) => {
-            if confirm_subscriber(&pool, subscriber_id).await.is_err() {
-                return HttpResponse::InternalServerError().finish();
+        None => SubscriberPage::invalid_link(StatusCode::UNAUTHORIZED),
+        Some(issued_token) if issued_token.status == SubscriptionStatus::Confirmed => {
+            SubscriberPage::AlreadyConfirmed
+        }
+        // Used tokens cannot bring back a subscription that ended since.
+        Some(issued_token) if issued_token.consumed_at.is_some() => {
+            SubscriberPage::invalid_link(StatusCode::UNAUTHORIZED)
+        }
+        Some(issued_token) if issued_token.is_expired(settings) => SubscriberPage::Expired {
+            subscription_token: token.as_ref().to_owned(),
+        },
+        Some(issued_token) => {
+            if confirm_subscriber(pool, issued_token.subscriber_id, consent).await? {
+                SubscriberPage::Confirmed
+            } else {
+                SubscriberPage::invalid_link(StatusCode::CONFLICT)
             }
-            HttpResponse::Ok().finish()
         }
+    };
+    Ok(page)
+}
+
+/// Send a new confirmation link in exchange for an expired one.
+#[tracing::instrument(
+    name = "Resend a confirmation email",
+    skip(request, form, pool, email_client, base_url, settings)
+)]
+pub async fn resend_confirmation_email(
+    request: HttpRequest,
+    form: web::Form<Parameters>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    settings: web::Data<SubscriptionSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let page = resend(form.0, &pool, &email_client, &base_url.0, &settings).await;
+    page_response(&request, page)
+}
+
+async fn resend(
+    parameters: Parameters,
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &str,
+    settings: &SubscriptionSettings,
+) -> Result<SubscriberPage, anyhow::Error> {
+    let token: SubscriberToken = match parameters.try_into() {
+        Ok(t) => t,
+        Err(_) => return Ok(SubscriberPage::invalid_link(StatusCode::BAD_REQUEST)),
+    };
+    let issued_token = match get_issued_token(pool, &token, None)
+        .await
+        .context("Failed to look up the subscription token.")?
+    {
+        Some(issued_token) => issued_token,
+        None => return Ok(SubscriberPage::invalid_link(StatusCode::UNAUTHORIZED)),
+    };
+    match issued_token.status {
+        SubscriptionStatus::Confirmed => return Ok(SubscriberPage::AlreadyConfirmed),
+        SubscriptionStatus::PendingConfirmation if issued_token.consumed_at.is_none() => {}
+        _ => return Ok(SubscriberPage::invalid_link(StatusCode::UNAUTHORIZED)),
+    }
+    let email = SubscriberEmail::parse(issued_token.email).map_err(anyhow::Error::msg)?;
+    let list_slug = ListSlug::parse(issued_token.list_slug).map_err(anyhow::Error::msg)?;
+    let locale = Locale::parse(&issued_token.locale).unwrap_or_default();
+    // The tokens are left alone when no email can be sent, or the link the
+    // subscriber already has would stop working.
+    if !may_send_confirmation_email(pool, &email, settings).await? {
+        tracing::warn!("Too many confirmation emails were sent to this address.");
+        return Ok(SubscriberPage::Resent);
     }
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let new_token = reuse_or_rotate_token(
+        &mut transaction,
+        issued_token.subscriber_id,
+        settings.confirmation_token_ttl(),
+    )
+    .await?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the new subscription token.")?;
+    send_confirmation_email(
+        pool,
+        email_client,
+        &email,
+        base_url,
+        &list_slug,
+        &new_token,
+        &locale,
+    )
+    .await
+    .context("Failed to send a confirmation email.")?;
+    Ok(SubscriberPage::Resent)
 }
 
+/// Confirm a pending subscription and consume its tokens.
+/// Returns `false` if the subscription is not pending anymore.
 #[tracing::instrument(name = "Mark subscriber as confirmed", skip(subscriber_id, pool))]
-pub async fn confirm_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        r#"UPDATE subscriptions SET status = 'confirmed' WHERE id = $1"#,
+pub async fn confirm_subscriber(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    consent: &ConsentContext,
+) -> Result<bool, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    if !mark_confirmed(
+        &mut transaction,
+        subscriber_id,
+        ConsentAction::Confirmed,
+        consent,
+    )
+    .await?
+    {
+        return Ok(false);
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the confirmation.")?;
+    Ok(true)
+}
+
+/// `confirm_subscriber`, as part of a larger transaction, recording `action`
+/// as the consent event.
+pub async fn mark_confirmed(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    action: ConsentAction,
+    consent: &ConsentContext,
+) -> Result<bool, anyhow::Error> {
+    let n_confirmed = sqlx::query!(
+        r#"
+        UPDATE subscriptions SET status = $2, confirmed_at = now()
+        WHERE id = $1 AND status::TEXT = ANY($3)
+        "#,
         subscriber_id,
+        SubscriptionStatus::Confirmed as SubscriptionStatus,
+        &SubscriptionStatus::Confirmed.allowed_sources()
     )
-    .execute(pool)
+    .execute(&mut *transaction)
     .await
-    .map_err(|e| {
-        tracing::error!("Failed to execute query: {:?}", e);
-        e
-    })?;
+    .context("Failed to confirm the subscriber.")?
+    .rows_affected();
+    if n_confirmed == 0 {
+        return Ok(false);
+    }
+    sqlx::query!(
+        r#"
+        UPDATE subscription_tokens SET consumed_at = now()
+        WHERE subscriber_id = $1 AND consumed_at IS NULL
+        "#,
+        subscriber_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to consume the subscription tokens.")?;
+    record_consent_event(&mut *transaction, subscriber_id, action, consent).await?;
+    start_sequences(transaction, subscriber_id).await?;
+    Ok(true)
+}
 
-    Ok(())
+/// A subscription token and the subscription it was issued for.
+pub struct IssuedToken {
+    pub subscriber_id: Uuid,
+    pub email: String,
+    pub status: SubscriptionStatus,
+    pub list_slug: String,
+    pub locale: String,
+    pub created_at: DateTime<Utc>,
+    pub consumed_at: Option<DateTime<Utc>>,
+}
+
+impl IssuedToken {
+    fn is_expired(&self, settings: &SubscriptionSettings) -> bool {
+        self.created_at + settings.confirmation_token_ttl() < Utc::now()
+    }
 }
 
-#[tracing::instrument(name = "Get subscriber_id from token", skip(subscription_token, pool))]
-pub async fn get_subscriber_id_from_token(
+/// Look up the subscription a token was issued for.
+/// When a list is given, tokens issued for other lists are ignored.
+#[tracing::instrument(
+    name = "Get the subscription of a token",
+    skip(subscription_token, pool)
+)]
+pub async fn get_issued_token(
     pool: &PgPool,
     subscription_token: &SubscriberToken,
-) -> Result<Option<Uuid>, sqlx::Error> {
-    let result = sqlx::query!(
-        r#"SELECT subscriber_id FROM subscription_tokens WHERE subscription_token = $1"#,
+    list_slug: Option<&ListSlug>,
+) -> Result<Option<IssuedToken>, sqlx::Error> {
+    let result = sqlx::query_as!(
+        IssuedToken,
+        r#"
+        SELECT
+            subscription_tokens.subscriber_id,
+            subscriptions.email,
+            subscriptions.status AS "status: SubscriptionStatus",
+            lists.slug AS list_slug,
+            subscriptions.locale,
+            subscription_tokens.created_at,
+            subscription_tokens.consumed_at
+        FROM subscription_tokens
+        JOIN subscriptions ON subscriptions.id = subscription_tokens.subscriber_id
+        JOIN lists ON lists.list_id = subscriptions.list_id
+        WHERE subscription_tokens.subscription_token = $1
+          AND ($2::TEXT IS NULL OR lists.slug = $2)
+        "#,
         subscription_token.as_ref(),
+        list_slug.map(AsRef::as_ref),
     )
     .fetch_optional(pool)
     .await
@@ -71,5 +303,5 @@ pub async fn get_subscriber_id_from_token(
         e
     })?;
 
-    Ok(result.map(|r| r.subscriber_id))
+    Ok(result)
 }
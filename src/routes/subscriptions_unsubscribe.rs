@@ -0,0 +1,221 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::{is_suppressed, request_locale, SubscriberPage};
+use crate::consent::{record_consent_event, ConsentAction, ConsentContext};
+use crate::domain::{ListSlug, Locale, SubscriberEmail, SubscriptionStatus};
+use crate::email_client::EmailClient;
+use crate::email_templates::{render_email, TransactionalEmail};
+use crate::startup::HmacSecret;
+
+#[derive(serde::Deserialize)]
+pub struct UnsubscribeParameters {
+    subscriber_id: Uuid,
+    signature: String,
+}
+
+fn unsubscribe_message(list_slug: &ListSlug, subscriber_id: Uuid) -> Vec<u8> {
+    format!("{}/{}", list_slug, subscriber_id).into_bytes()
+}
+
+/// Build the link a subscriber can follow to leave a list.
+/// The link is signed, so it cannot be forged to unsubscribe somebody else.
+pub fn unsubscribe_link(
+    base_url: &str,
+    list_slug: &ListSlug,
+    subscriber_id: Uuid,
+    hmac_secret: &HmacSecret,
+) -> String {
+    let signature = hmac_secret.sign(
+        "unsubscribe",
+        &unsubscribe_message(list_slug, subscriber_id),
+    );
+    format!(
+        "{}/lists/{}/subscriptions/unsubscribe?subscriber_id={}&signature={}",
+        base_url,
+        list_slug,
+        subscriber_id,
+        hex::encode(signature)
+    )
+}
+
+#[tracing::instrument(
+    name = "Unsubscribe from a list",
+    skip(request, parameters, pool, email_client, hmac_secret)
+)]
+pub async fn unsubscribe(
+    request: HttpRequest,
+    list_slug: web::Path<String>,
+    parameters: web::Query<UnsubscribeParameters>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> HttpResponse {
+    let list_slug = match ListSlug::parse(list_slug.into_inner()) {
+        Ok(s) => s,
+        Err(_) => return HttpResponse::NotFound().finish(),
+    };
+    let signature = match hex::decode(&parameters.signature) {
+        Ok(s) => s,
+        Err(_) => return HttpResponse::BadRequest().finish(),
+    };
+    if !hmac_secret.verify(
+        "unsubscribe",
+        &unsubscribe_message(&list_slug, parameters.subscriber_id),
+        &signature,
+    ) {
+        return HttpResponse::Unauthorized().finish();
+    }
+    let consent = ConsentContext::from_request(&request);
+    let unsubscribed_email =
+        match unsubscribe_subscriber(&pool, &list_slug, parameters.subscriber_id, &consent).await {
+            Ok(email) => email,
+            Err(_) => return HttpResponse::InternalServerError().finish(),
+        };
+    // The page is in the subscriber's language, not the browser's: the link
+    // comes from one of their emails.
+    let (list, locale) =
+        match get_list_name_and_locale(&pool, &list_slug, parameters.subscriber_id).await {
+            Ok(Some((list, locale))) => (list, locale),
+            Ok(None) => (list_slug.to_string(), request_locale(&request)),
+            Err(_) => return HttpResponse::InternalServerError().finish(),
+        };
+    if let Some(email) = unsubscribed_email {
+        // The subscriber is gone either way, the email is only a courtesy.
+        if let Err(e) =
+            send_unsubscribe_confirmation(&pool, &email_client, email, &list, &locale).await
+        {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to send an unsubscribe confirmation email.",
+            );
+        }
+    }
+    SubscriberPage::Unsubscribed { list }.respond_in(&request, &locale)
+}
+
+/// Let subscribers know that they left the list, in case somebody else
+/// followed the link.
+#[tracing::instrument(
+    name = "Send an unsubscribe confirmation email",
+    skip(pool, email_client, email)
+)]
+async fn send_unsubscribe_confirmation(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    email: String,
+    list: &str,
+    locale: &Locale,
+) -> Result<(), anyhow::Error> {
+    let email = SubscriberEmail::parse(email).map_err(anyhow::Error::msg)?;
+    if is_suppressed(pool, email.as_ref()).await? {
+        tracing::info!("Not sending an unsubscribe confirmation to a suppressed address.");
+        return Ok(());
+    }
+    let mut context = tera::Context::new();
+    context.insert("list", list);
+    let rendered = render_email(
+        pool,
+        TransactionalEmail::UnsubscribeConfirmation,
+        locale,
+        &context,
+    )
+    .await?;
+    email_client
+        .send_email(&email, &rendered.subject, &rendered.html, &rendered.text)
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Get the list name and locale of a subscriber", skip(pool))]
+async fn get_list_name_and_locale(
+    pool: &PgPool,
+    list_slug: &ListSlug,
+    subscriber_id: Uuid,
+) -> Result<Option<(String, Locale)>, sqlx::Error> {
+    let r = sqlx::query!(
+        r#"
+        SELECT lists.name, subscriptions.locale
+        FROM subscriptions
+        JOIN lists USING (list_id)
+        WHERE subscriptions.id = $1 AND lists.slug = $2
+        "#,
+        subscriber_id,
+        list_slug.as_ref()
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+    Ok(r.map(|r| (r.name, Locale::parse(&r.locale).unwrap_or_default())))
+}
+
+/// Returns the address of the subscriber, unless they had already left.
+#[tracing::instrument(name = "Mark subscriber as unsubscribed", skip(pool))]
+async fn unsubscribe_subscriber(
+    pool: &PgPool,
+    list_slug: &ListSlug,
+    subscriber_id: Uuid,
+    consent: &ConsentContext,
+) -> Result<Option<String>, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let email = sqlx::query!(
+        r#"
+        UPDATE subscriptions SET status = $3
+        FROM lists
+        WHERE subscriptions.id = $1
+          AND lists.list_id = subscriptions.list_id
+          AND lists.slug = $2
+          AND subscriptions.status::TEXT = ANY($4)
+        RETURNING subscriptions.email
+        "#,
+        subscriber_id,
+        list_slug.as_ref(),
+        SubscriptionStatus::Unsubscribed as SubscriptionStatus,
+        &SubscriptionStatus::Unsubscribed.allowed_sources()
+    )
+    .fetch_optional(&mut transaction)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+    if let Some(r) = &email {
+        delete_pending_deliveries(&mut transaction, &r.email).await?;
+        record_consent_event(
+            &mut transaction,
+            subscriber_id,
+            ConsentAction::Unsubscribed,
+            consent,
+        )
+        .await?;
+    }
+    transaction.commit().await?;
+    Ok(email.map(|r| r.email))
+}
+
+/// Drop the issues still queued for an address,
+/// unless it remains a confirmed member of another list.
+#[tracing::instrument(name = "Remove pending deliveries for a subscriber", skip(transaction))]
+pub async fn delete_pending_deliveries(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_email: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_queue
+        WHERE lower(subscriber_email) = lower($1)
+          AND NOT EXISTS (
+            SELECT 1 FROM subscriptions WHERE lower(email) = lower($1) AND status = 'confirmed'
+          )
+        "#,
+        subscriber_email
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,234 @@
+use actix_web::http::header::LOCATION;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::routes::e500;
+use crate::startup::HmacSecret;
+
+/// What a redirect token stands for.
+#[derive(Debug, PartialEq)]
+struct TrackedLink {
+    issue_id: Uuid,
+    subscriber_id: Uuid,
+    url: String,
+}
+
+/// `<payload>.<signature>`, both base64url encoded, where the payload is the
+/// issue id, the subscriber id and the target URL.
+fn encode_token(link: &TrackedLink, hmac_secret: &HmacSecret) -> String {
+    let mut payload = Vec::with_capacity(32 + link.url.len());
+    payload.extend_from_slice(link.issue_id.as_bytes());
+    payload.extend_from_slice(link.subscriber_id.as_bytes());
+    payload.extend_from_slice(link.url.as_bytes());
+    let signature = hmac_secret.sign("click", &payload);
+    format!(
+        "{}.{}",
+        base64::encode_config(&payload, base64::URL_SAFE_NO_PAD),
+        base64::encode_config(signature, base64::URL_SAFE_NO_PAD)
+    )
+}
+
+/// `None` unless the token was issued by us: redirects cannot be pointed
+/// anywhere else.
+fn decode_token(token: &str, hmac_secret: &HmacSecret) -> Option<TrackedLink> {
+    let (payload, signature) = token.split_once('.')?;
+    let payload = base64::decode_config(payload, base64::URL_SAFE_NO_PAD).ok()?;
+    let signature = base64::decode_config(signature, base64::URL_SAFE_NO_PAD).ok()?;
+    if !hmac_secret.verify("click", &payload, &signature) {
+        return None;
+    }
+    if payload.len() < 32 {
+        return None;
+    }
+    Some(TrackedLink {
+        issue_id: Uuid::from_slice(&payload[..16]).ok()?,
+        subscriber_id: Uuid::from_slice(&payload[16..32]).ok()?,
+        url: String::from_utf8(payload[32..].to_vec()).ok()?,
+    })
+}
+
+/// Attribute values in the HTML body are escaped.
+fn unescape_attribute(value: &str) -> String {
+    value
+        .replace("&#x2F;", "/")
+        .replace("&#47;", "/")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Point every external link of an issue at a signed `/r/{token}` redirect.
+/// Links to this application, such as the unsubscribe link, are left alone.
+pub fn track_links(
+    html: &str,
+    base_url: &str,
+    issue_id: Uuid,
+    subscriber_id: Uuid,
+    hmac_secret: &HmacSecret,
+) -> String {
+    let mut tracked = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("<a ") {
+        let end = match rest[start..].find('>') {
+            Some(length) => start + length,
+            None => break,
+        };
+        let tag = &rest[start..end];
+        tracked.push_str(&rest[..start]);
+        let href = tag.find("href=\"").and_then(|i| {
+            let value_start = i + "href=\"".len();
+            let value_end = value_start + tag[value_start..].find('"')?;
+            Some((value_start, value_end))
+        });
+        match href {
+            Some((value_start, value_end)) => {
+                let url = unescape_attribute(&tag[value_start..value_end]);
+                let is_internal = url == base_url || url.starts_with(&format!("{}/", base_url));
+                let is_external =
+                    (url.starts_with("http://") || url.starts_with("https://")) && !is_internal;
+                if is_external {
+                    let link = TrackedLink {
+                        issue_id,
+                        subscriber_id,
+                        url,
+                    };
+                    tracked.push_str(&tag[..value_start]);
+                    tracked.push_str(&format!(
+                        "{}/r/{}",
+                        base_url,
+                        encode_token(&link, hmac_secret)
+                    ));
+                    tracked.push_str(&tag[value_end..]);
+                } else {
+                    tracked.push_str(tag);
+                }
+            }
+            None => tracked.push_str(tag),
+        }
+        rest = &rest[end..];
+    }
+    tracked.push_str(rest);
+    tracked
+}
+
+/// Redirect to the target of a tracked link, recording the click.
+#[tracing::instrument(name = "Follow a tracked link", skip(token, pool, hmac_secret))]
+pub async fn follow_link(
+    token: web::Path<String>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let link = match decode_token(&token, &hmac_secret) {
+        Some(link) => link,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    record_click(&pool, &link).await.map_err(e500)?;
+    Ok(HttpResponse::Found()
+        .insert_header((LOCATION, link.url))
+        .finish())
+}
+
+/// Clicks are only recorded while the issue is tracked,
+/// links keep working afterwards.
+#[tracing::instrument(skip(pool))]
+async fn record_click(pool: &PgPool, link: &TrackedLink) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_clicks (
+            newsletter_issue_id,
+            subscriber_id,
+            url,
+            n_clicks,
+            first_clicked_at,
+            last_clicked_at
+        )
+        SELECT newsletter_issue_id, $2, $3, 1, now(), now()
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1 AND track_clicks
+        ON CONFLICT (newsletter_issue_id, subscriber_id, url)
+        DO UPDATE SET n_clicks = issue_clicks.n_clicks + 1, last_clicked_at = now()
+        "#,
+        link.issue_id,
+        link.subscriber_id,
+        link.url
+    )
+    .execute(pool)
+    .await
+    .context("Failed to record a click.")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_token, encode_token, track_links, TrackedLink};
+    use crate::startup::HmacSecret;
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    fn secret() -> HmacSecret {
+        HmacSecret(Secret::new("long-and-very-secret-random-key".into()))
+    }
+
+    fn link() -> TrackedLink {
+        TrackedLink {
+            issue_id: Uuid::new_v4(),
+            subscriber_id: Uuid::new_v4(),
+            url: "https://example.com/?a=1&b=2".into(),
+        }
+    }
+
+    #[test]
+    fn tokens_round_trip() {
+        let link = link();
+        let token = encode_token(&link, &secret());
+        assert_eq!(decode_token(&token, &secret()), Some(link));
+    }
+
+    #[test]
+    fn tampered_tokens_are_rejected() {
+        let token = encode_token(&link(), &secret());
+        let (_, signature) = token.split_once('.').unwrap();
+        let forged = TrackedLink {
+            url: "https://evil.example.com".into(),
+            ..link()
+        };
+        let forged_token = encode_token(&forged, &secret());
+        let (forged_payload, _) = forged_token.split_once('.').unwrap();
+        assert_eq!(
+            decode_token(&format!("{}.{}", forged_payload, signature), &secret()),
+            None
+        );
+        let other_secret = HmacSecret(Secret::new("another-secret".into()));
+        assert_eq!(decode_token(&token, &other_secret), None);
+    }
+
+    #[test]
+    fn only_external_links_are_rewritten() {
+        let html = r#"<p><a href="https://example.com/?a=1&amp;b=2">Read</a> <a href="http://127.0.0.1/lists/newsletter/subscriptions/unsubscribe">Leave</a> <a href="mailto:us@example.com">Write</a></p>"#;
+        let tracked = track_links(
+            html,
+            "http://127.0.0.1",
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            &secret(),
+        );
+        let token = tracked
+            .split("href=\"http://127.0.0.1/r/")
+            .nth(1)
+            .unwrap()
+            .split('"')
+            .next()
+            .unwrap();
+        assert_eq!(
+            decode_token(token, &secret()).unwrap().url,
+            "https://example.com/?a=1&b=2"
+        );
+        assert!(tracked.contains(">Read</a>"));
+        assert!(tracked.contains(
+            r#"<a href="http://127.0.0.1/lists/newsletter/subscriptions/unsubscribe">Leave</a>"#
+        ));
+        assert!(tracked.contains(r#"<a href="mailto:us@example.com">Write</a>"#));
+    }
+}
@@ -0,0 +1,4 @@
+mod clicks;
+pub use clicks::{follow_link, track_links};
+mod opens;
+pub use opens::{open_tracking_link, track_open};
@@ -0,0 +1,108 @@
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::routes::e500;
+use crate::startup::HmacSecret;
+
+/// A transparent 1x1 GIF.
+const PIXEL: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+#[derive(serde::Deserialize)]
+pub struct OpenParameters {
+    issue_id: Uuid,
+    subscriber_id: Uuid,
+    signature: String,
+}
+
+fn open_message(issue_id: Uuid, subscriber_id: Uuid) -> Vec<u8> {
+    format!("{}/{}", issue_id, subscriber_id).into_bytes()
+}
+
+/// Build the URL of the tracking pixel a recipient's copy of an issue embeds.
+/// The URL is signed, so that opens cannot be recorded for somebody else.
+pub fn open_tracking_link(
+    base_url: &str,
+    issue_id: Uuid,
+    subscriber_id: Uuid,
+    hmac_secret: &HmacSecret,
+) -> String {
+    let signature = hmac_secret.sign("open", &open_message(issue_id, subscriber_id));
+    format!(
+        "{}/track/open?issue_id={}&subscriber_id={}&signature={}",
+        base_url,
+        issue_id,
+        subscriber_id,
+        hex::encode(signature)
+    )
+}
+
+/// Serve the tracking pixel, recording the open when the link is genuine.
+/// Email clients show a broken image for anything but a picture:
+/// the pixel is returned whatever happens.
+#[tracing::instrument(
+    name = "Track an open",
+    skip(parameters, pool, hmac_secret),
+    fields(issue_id=%parameters.issue_id, subscriber_id=%parameters.subscriber_id)
+)]
+pub async fn track_open(
+    parameters: web::Query<OpenParameters>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let is_genuine = hex::decode(&parameters.signature)
+        .map(|signature| {
+            hmac_secret.verify(
+                "open",
+                &open_message(parameters.issue_id, parameters.subscriber_id),
+                &signature,
+            )
+        })
+        .unwrap_or(false);
+    if is_genuine {
+        record_open(&pool, parameters.issue_id, parameters.subscriber_id)
+            .await
+            .map_err(e500)?;
+    }
+    Ok(HttpResponse::Ok()
+        .content_type("image/gif")
+        // Every open must reach us.
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .body(PIXEL))
+}
+
+/// Opens are only recorded while the issue is tracked.
+#[tracing::instrument(skip(pool))]
+async fn record_open(
+    pool: &PgPool,
+    issue_id: Uuid,
+    subscriber_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_opens (
+            newsletter_issue_id,
+            subscriber_id,
+            first_opened_at,
+            last_opened_at
+        )
+        SELECT newsletter_issue_id, $2, now(), now()
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1 AND track_opens
+        ON CONFLICT (newsletter_issue_id, subscriber_id)
+        DO UPDATE SET last_opened_at = now()
+        "#,
+        issue_id,
+        subscriber_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to record an open.")?;
+    Ok(())
+}
@@ -0,0 +1,203 @@
+mod postmark;
+
+use actix_web::http::header::{HeaderMap, HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE};
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use secrecy::ExposeSecret;
+use sqlx::{PgPool, Postgres, Transaction};
+use subtle::ConstantTimeEq;
+
+use super::{delete_pending_deliveries, e400, e500, suppress_address};
+use crate::configuration::WebhookSettings;
+use crate::consent::{record_consent_event, ConsentAction, ConsentContext};
+use crate::domain::SubscriptionStatus;
+
+#[derive(Debug, PartialEq)]
+pub enum EmailEventKind {
+    HardBounce,
+    SoftBounce,
+    Complaint,
+}
+
+/// A delivery problem reported by an email provider.
+#[derive(Debug)]
+pub struct EmailEvent {
+    /// The provider's identifier of the event, used to process it only once.
+    pub event_id: String,
+    pub email: String,
+    pub kind: EmailEventKind,
+}
+
+/// `(username, password)` from an `Authorization: Basic` header.
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let encoded = headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(base64::decode(encoded).ok()?).ok()?;
+    let (username, password) = decoded.split_once(':')?;
+    Some((username.to_owned(), password.to_owned()))
+}
+
+fn is_authenticated(request: &HttpRequest, settings: &WebhookSettings) -> bool {
+    match basic_credentials(request.headers()) {
+        // Compared in constant time, not to leak how much of them is right.
+        Some((username, password)) => {
+            let username_matches = username.as_bytes().ct_eq(settings.username.as_bytes());
+            let password_matches = password
+                .as_bytes()
+                .ct_eq(settings.password.expose_secret().as_bytes());
+            (username_matches & password_matches).into()
+        }
+        None => false,
+    }
+}
+
+#[tracing::instrument(
+    name = "Receive an email webhook",
+    skip(request, body, pool, settings),
+    fields(provider=%provider)
+)]
+pub async fn receive_email_webhook(
+    request: HttpRequest,
+    provider: web::Path<String>,
+    body: web::Bytes,
+    pool: web::Data<PgPool>,
+    settings: web::Data<WebhookSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if !is_authenticated(&request, &settings) {
+        let mut response = HttpResponse::Unauthorized().finish();
+        response.headers_mut().insert(
+            WWW_AUTHENTICATE,
+            HeaderValue::from_static(r#"Basic realm="webhooks""#),
+        );
+        return Ok(response);
+    }
+    let event = match provider.as_str() {
+        "postmark" => postmark::parse(&body).map_err(e400)?,
+        _ => return Ok(HttpResponse::NotFound().finish()),
+    };
+    if let Some(event) = event {
+        process_event(&pool, &provider, &event, settings.soft_bounce_threshold)
+            .await
+            .map_err(e500)?;
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Stop mailing addresses that bounced or complained: every subscription of the
+/// address gets the matching status, the address is suppressed and its queued
+/// issues are dropped.
+/// Soft bounces only count until the threshold is reached.
+#[tracing::instrument(skip(pool))]
+async fn process_event(
+    pool: &PgPool,
+    provider: &str,
+    event: &EmailEvent,
+    soft_bounce_threshold: i32,
+) -> Result<(), anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let is_new = sqlx::query!(
+        r#"
+        INSERT INTO processed_webhook_events (provider, event_id, processed_at)
+        VALUES ($1, $2, now())
+        ON CONFLICT DO NOTHING
+        "#,
+        provider,
+        event.event_id
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to record the webhook event.")?
+    .rows_affected()
+        == 1;
+    if !is_new {
+        return Ok(());
+    }
+    let status = match event.kind {
+        EmailEventKind::Complaint => Some(SubscriptionStatus::Complained),
+        EmailEventKind::HardBounce => Some(SubscriptionStatus::Bounced),
+        EmailEventKind::SoftBounce => {
+            let n_soft_bounces = count_soft_bounce(&mut transaction, &event.email).await?;
+            if n_soft_bounces >= soft_bounce_threshold {
+                Some(SubscriptionStatus::Bounced)
+            } else {
+                None
+            }
+        }
+    };
+    if let Some(status) = status {
+        mark_address(&mut transaction, &event.email, status, provider).await?;
+        suppress_address(&mut transaction, &event.email, status.as_str(), provider).await?;
+        delete_pending_deliveries(&mut transaction, &event.email)
+            .await
+            .context("Failed to remove pending deliveries.")?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the webhook event.")?;
+    Ok(())
+}
+
+/// Returns the number of soft bounces of the address so far.
+#[tracing::instrument(skip(transaction))]
+async fn count_soft_bounce(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &str,
+) -> Result<i32, anyhow::Error> {
+    let n_soft_bounces = sqlx::query!(
+        r#"
+        INSERT INTO email_soft_bounces (email, n_soft_bounces, last_bounced_at)
+        VALUES (lower($1), 1, now())
+        ON CONFLICT (email) DO UPDATE
+        SET n_soft_bounces = email_soft_bounces.n_soft_bounces + 1,
+            last_bounced_at = now()
+        RETURNING n_soft_bounces
+        "#,
+        email
+    )
+    .fetch_one(transaction)
+    .await
+    .context("Failed to count a soft bounce.")?
+    .n_soft_bounces;
+    Ok(n_soft_bounces)
+}
+
+/// Subscriptions that cannot move to `status`, e.g. unsubscribed ones
+/// for a bounce, are left alone.
+#[tracing::instrument(skip(transaction))]
+async fn mark_address(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &str,
+    status: SubscriptionStatus,
+    provider: &str,
+) -> Result<(), anyhow::Error> {
+    let subscriber_ids = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET status = $2
+        WHERE lower(email) = lower($1) AND status::TEXT = ANY($3)
+        RETURNING id
+        "#,
+        email,
+        status as SubscriptionStatus,
+        &status.allowed_sources()
+    )
+    .fetch_all(&mut *transaction)
+    .await
+    .context("Failed to update the subscriptions of the address.")?;
+    let action = match status {
+        SubscriptionStatus::Complained => ConsentAction::Complained,
+        _ => ConsentAction::Bounced,
+    };
+    let consent = ConsentContext::default().with_source(provider);
+    for r in subscriber_ids {
+        record_consent_event(&mut *transaction, r.id, action, &consent).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,87 @@
+//! Postmark's bounce and spam complaint webhooks.
+//! See <https://postmarkapp.com/developer/webhooks/bounce-webhook> and
+//! <https://postmarkapp.com/developer/webhooks/spam-complaint-webhook>.
+use super::{EmailEvent, EmailEventKind};
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Payload {
+    record_type: String,
+    #[serde(rename = "ID")]
+    id: serde_json::Value,
+    #[serde(rename = "Type")]
+    kind: String,
+    email: String,
+}
+
+/// `None` for the events we do not act upon, e.g. deliveries or auto-responders.
+pub fn parse(body: &[u8]) -> Result<Option<EmailEvent>, serde_json::Error> {
+    let payload: Payload = serde_json::from_slice(body)?;
+    let kind = match (payload.record_type.as_str(), payload.kind.as_str()) {
+        ("SpamComplaint", _) => EmailEventKind::Complaint,
+        ("Bounce", "HardBounce" | "BadEmailAddress" | "ManuallyDeactivated") => {
+            EmailEventKind::HardBounce
+        }
+        ("Bounce", "SoftBounce" | "Transient" | "DnsError") => EmailEventKind::SoftBounce,
+        _ => return Ok(None),
+    };
+    // Postmark's ids are numbers, too large for some JSON parsers.
+    let event_id = match payload.id {
+        serde_json::Value::String(id) => id,
+        id => id.to_string(),
+    };
+    Ok(Some(EmailEvent {
+        event_id,
+        email: payload.email,
+        kind,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use crate::routes::webhooks::EmailEventKind;
+    use claim::{assert_err, assert_none};
+
+    #[test]
+    fn hard_bounces_are_recognised() {
+        let event = parse(
+            br#"{"RecordType": "Bounce", "ID": 4323372036854775807, "Type": "HardBounce",
+                "TypeCode": 1, "Email": "john@example.com", "BouncedAt": "2019-11-05T16:33:54Z"}"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(event.kind, EmailEventKind::HardBounce);
+        assert_eq!(event.event_id, "4323372036854775807");
+        assert_eq!(event.email, "john@example.com");
+    }
+
+    #[test]
+    fn soft_bounces_and_complaints_are_recognised() {
+        let soft_bounce = parse(
+            br#"{"RecordType": "Bounce", "ID": 1, "Type": "SoftBounce", "Email": "a@example.com"}"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(soft_bounce.kind, EmailEventKind::SoftBounce);
+        let complaint = parse(
+            br#"{"RecordType": "SpamComplaint", "ID": 2, "Type": "SpamComplaint", "Email": "a@example.com"}"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(complaint.kind, EmailEventKind::Complaint);
+    }
+
+    #[test]
+    fn other_events_are_ignored() {
+        assert_none!(parse(
+            br#"{"RecordType": "Bounce", "ID": 3, "Type": "AutoResponder", "Email": "a@example.com"}"#,
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn malformed_payloads_are_rejected() {
+        assert_err!(parse(br#"{"RecordType": "Bounce"}"#));
+    }
+}
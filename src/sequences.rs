@@ -0,0 +1,184 @@
+//! Sequences: emails sent one after the other to the subscribers of a list
+//! once they confirm.
+//!
+//! Steps are issues delivered by the issue delivery worker: each one is
+//! queued when the previous one leaves the queue, to go out `delay_hours`
+//! later.
+use anyhow::Context;
+use sqlx::{PgExecutor, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Enrol a subscriber who just confirmed in the sequences of their list and
+/// queue the first step of each.
+/// Subscribers who confirm again, e.g. after unsubscribing, do not start over.
+#[tracing::instrument(name = "Start sequences", skip(transaction))]
+pub async fn start_sequences(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        WITH enrolled AS (
+            INSERT INTO sequence_enrollments (sequence_id, subscriber_id, position, enrolled_at)
+            SELECT sequences.sequence_id, subscriptions.id, first_step.position, now()
+            FROM subscriptions
+            JOIN sequences ON sequences.list_id = subscriptions.list_id
+            JOIN LATERAL (
+                SELECT position FROM sequence_steps
+                WHERE sequence_steps.sequence_id = sequences.sequence_id
+                ORDER BY position
+                LIMIT 1
+            ) first_step ON true
+            WHERE subscriptions.id = $1
+            ON CONFLICT DO NOTHING
+            RETURNING sequence_id, position
+        )
+        INSERT INTO issue_delivery_queue (
+            newsletter_issue_id,
+            subscriber_email,
+            subscriber_id,
+            execute_after
+        )
+        SELECT
+            sequence_steps.newsletter_issue_id,
+            subscriptions.email,
+            subscriptions.id,
+            now() + make_interval(hours => sequence_steps.delay_hours)
+        FROM enrolled
+        JOIN sequence_steps USING (sequence_id, position)
+        JOIN subscriptions ON subscriptions.id = $1
+        ON CONFLICT DO NOTHING
+        "#,
+        subscriber_id
+    )
+    .execute(transaction)
+    .await
+    .context("Failed to start the sequences of a subscriber.")?;
+    Ok(())
+}
+
+/// Whether the issue is a sequence step and its recipient left the list
+/// since it was queued.
+#[tracing::instrument(name = "Check a sequence step is still due", skip(executor))]
+pub async fn is_stale_sequence_step(
+    executor: impl PgExecutor<'_>,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Option<Uuid>,
+) -> Result<bool, anyhow::Error> {
+    let is_stale = sqlx::query!(
+        r#"
+        SELECT
+            EXISTS (SELECT 1 FROM sequence_steps WHERE newsletter_issue_id = $1)
+            AND NOT EXISTS (
+                SELECT 1 FROM subscriptions WHERE id = $2 AND status = 'confirmed'
+            ) AS "is_stale!"
+        "#,
+        newsletter_issue_id,
+        subscriber_id
+    )
+    .fetch_one(executor)
+    .await
+    .context("Failed to check whether a sequence step is still due.")?
+    .is_stale;
+    Ok(is_stale)
+}
+
+/// Move a subscriber to the next step of a sequence once the current one
+/// left the queue, delivered or not.
+/// Subscribers who left the list stay where they were.
+#[tracing::instrument(name = "Advance a sequence", skip(transaction))]
+pub async fn advance_sequence(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Option<Uuid>,
+) -> Result<(), anyhow::Error> {
+    let subscriber_id = match subscriber_id {
+        Some(subscriber_id) => subscriber_id,
+        None => return Ok(()),
+    };
+    let current = sqlx::query!(
+        r#"
+        SELECT sequence_steps.sequence_id, sequence_steps.position
+        FROM sequence_steps
+        JOIN sequence_enrollments USING (sequence_id, position)
+        JOIN subscriptions ON subscriptions.id = sequence_enrollments.subscriber_id
+        WHERE sequence_steps.newsletter_issue_id = $1
+          AND sequence_enrollments.subscriber_id = $2
+          AND sequence_enrollments.completed_at IS NULL
+          AND subscriptions.status = 'confirmed'
+        "#,
+        newsletter_issue_id,
+        subscriber_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to look up the current step of a sequence.")?;
+    let current = match current {
+        Some(current) => current,
+        None => return Ok(()),
+    };
+    let next = sqlx::query!(
+        r#"
+        SELECT position, delay_hours, newsletter_issue_id
+        FROM sequence_steps
+        WHERE sequence_id = $1 AND position > $2
+        ORDER BY position
+        LIMIT 1
+        "#,
+        current.sequence_id,
+        current.position
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to look up the next step of a sequence.")?;
+    match next {
+        Some(next) => {
+            sqlx::query!(
+                r#"
+                UPDATE sequence_enrollments SET position = $3
+                WHERE sequence_id = $1 AND subscriber_id = $2
+                "#,
+                current.sequence_id,
+                subscriber_id,
+                next.position
+            )
+            .execute(&mut *transaction)
+            .await
+            .context("Failed to record the progress of a sequence.")?;
+            sqlx::query!(
+                r#"
+                INSERT INTO issue_delivery_queue (
+                    newsletter_issue_id,
+                    subscriber_email,
+                    subscriber_id,
+                    execute_after
+                )
+                SELECT $1, email, id, now() + make_interval(hours => $3)
+                FROM subscriptions
+                WHERE id = $2
+                ON CONFLICT DO NOTHING
+                "#,
+                next.newsletter_issue_id,
+                subscriber_id,
+                next.delay_hours
+            )
+            .execute(&mut *transaction)
+            .await
+            .context("Failed to queue the next step of a sequence.")?;
+        }
+        None => {
+            sqlx::query!(
+                r#"
+                UPDATE sequence_enrollments SET completed_at = now()
+                WHERE sequence_id = $1 AND subscriber_id = $2
+                "#,
+                current.sequence_id,
+                subscriber_id
+            )
+            .execute(&mut *transaction)
+            .await
+            .context("Failed to complete a sequence.")?;
+        }
+    }
+    Ok(())
+}
@@ -1,5 +1,7 @@
+use crate::abuse_protection::AbuseProtection;
 use crate::authentication::reject_anonymous_users;
-use crate::configuration::Settings;
+use crate::configuration::{ApplicationSettings, Settings, SubscriptionSettings, WebhookSettings};
+use crate::consent::TrustedProxies;
 use crate::email_client::EmailClient;
 use actix_session::storage::RedisSessionStore;
 use actix_session::SessionMiddleware;
@@ -10,8 +12,10 @@ use actix_web::{web, App, HttpServer};
 use actix_web_flash_messages::storage::CookieMessageStore;
 use actix_web_flash_messages::FlashMessagesFramework;
 use actix_web_lab::middleware::from_fn;
+use hmac::{Hmac, Mac};
 use secrecy::ExposeSecret;
 use secrecy::Secret;
+use sha2::Sha256;
 use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
 use std::net::TcpListener;
@@ -19,8 +23,20 @@ use tracing_actix_web::TracingLogger;
 
 use crate::configuration::DatabaseSettings;
 use crate::routes::{
-    admin_dashboard, change_password, change_password_form, confirm, health_check, home, log_out,
-    login, login_form, newsletter_form, publish_newsletter, subscribe,
+    add_sequence_step, add_subscriber_tag, admin_confirm_subscriber, admin_dashboard,
+    admin_delete_subscriber, admin_unsubscribe_subscriber, atom_feed, change_email,
+    change_password, change_password_form, check_your_inbox, confirm, confirm_email_change,
+    confirm_list_subscription, create_mailing_list, create_segment, create_sequence,
+    create_suppression, delete_segment, delete_suppression, email_template_form, email_templates,
+    erase_personal_data, export_personal_data, export_subscribers, follow_link, health_check, home,
+    import_subscribers, import_subscribers_form, issue_details, issue_page, list_issues,
+    list_subscribers, log_out, login, login_form, mailing_lists, newsletter_form, pause_delivery,
+    personal_data, preferences, privacy_form, publish_newsletter, published_issues,
+    receive_email_webhook, remove_subscriber_attribute, remove_subscriber_tag,
+    request_privacy_link, resend_confirmation_email, restore_email_template, rss_feed,
+    save_email_template, segments, sequences, set_issue_tracking, set_list_double_opt_in,
+    set_subscriber_attribute, subscribe, subscribe_to_list, subscriber_details, suppressions,
+    track_open, unsubscribe, update_preferences,
 };
 
 pub fn get_connection_pool(configuration: &DatabaseSettings) -> PgPool {
@@ -54,8 +70,9 @@ impl Application {
             listener,
             connection_pool,
             email_client,
-            configuration.application.base_url,
-            HmacSecret(configuration.application.hmac_secret),
+            configuration.application,
+            configuration.webhooks,
+            configuration.subscriptions,
             configuration.redis_uri,
         )
         .await?;
@@ -79,17 +96,50 @@ pub struct ApplicationBaseUrl(pub String);
 #[derive(Clone)]
 pub struct HmacSecret(pub Secret<String>);
 
+impl HmacSecret {
+    /// Sign `message` for `purpose`, e.g. `"unsubscribe"`.
+    /// The purpose is signed too, so a signature issued for one kind of link
+    /// is never accepted for another. Purposes must not contain a `/`.
+    pub fn sign(&self, purpose: &str, message: &[u8]) -> Vec<u8> {
+        self.mac(purpose, message).finalize().into_bytes().to_vec()
+    }
+
+    /// Whether `signature` was issued by `sign` for this purpose and message,
+    /// checked in constant time.
+    pub fn verify(&self, purpose: &str, message: &[u8], signature: &[u8]) -> bool {
+        self.mac(purpose, message).verify_slice(signature).is_ok()
+    }
+
+    fn mac(&self, purpose: &str, message: &[u8]) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.0.expose_secret().as_bytes()).unwrap();
+        mac.update(purpose.as_bytes());
+        mac.update(b"/");
+        mac.update(message);
+        mac
+    }
+}
+
 async fn run(
     listener: TcpListener,
     db_pool: PgPool,
     email_client: EmailClient,
-    base_url: String,
-    hmac_secret: HmacSecret,
+    application: ApplicationSettings,
+    webhook_settings: WebhookSettings,
+    subscription_settings: SubscriptionSettings,
     redis_uri: Secret<String>,
 ) -> Result<Server, anyhow::Error> {
     let db_pool = Data::new(db_pool);
     let email_client = Data::new(email_client);
-    let base_url = Data::new(ApplicationBaseUrl(base_url));
+    let base_url = Data::new(ApplicationBaseUrl(application.base_url));
+    let hmac_secret = HmacSecret(application.hmac_secret);
+    let trusted_proxies = Data::new(TrustedProxies(application.trusted_proxies));
+    let webhook_settings = Data::new(webhook_settings);
+    let abuse_protection = Data::new(AbuseProtection::new(
+        subscription_settings.abuse_protection.clone(),
+        hmac_secret.clone(),
+    ));
+    let email_policy = Data::new(subscription_settings.email_policy.policy()?);
+    let subscription_settings = Data::new(subscription_settings);
     let secret_key = Key::from(hmac_secret.0.expose_secret().as_bytes());
     let message_store = CookieMessageStore::builder(secret_key.clone()).build();
     let message_framework = FlashMessagesFramework::builder(message_store).build();
@@ -113,18 +163,167 @@ async fn run(
                     .route("/password", web::post().to(change_password))
                     .route("/logout", web::post().to(log_out))
                     .route("/newsletters", web::get().to(newsletter_form))
-                    .route("/newsletters", web::post().to(publish_newsletter)),
+                    .route("/newsletters", web::post().to(publish_newsletter))
+                    .route("/issues", web::get().to(published_issues))
+                    .route("/issues/{issue_id}", web::get().to(issue_details))
+                    .route(
+                        "/issues/{issue_id}/tracking",
+                        web::post().to(set_issue_tracking),
+                    )
+                    .route("/lists", web::get().to(mailing_lists))
+                    .route("/lists", web::post().to(create_mailing_list))
+                    .route(
+                        "/lists/{list_id}/double-opt-in",
+                        web::post().to(set_list_double_opt_in),
+                    )
+                    .route("/segments", web::get().to(segments))
+                    .route("/segments", web::post().to(create_segment))
+                    .route("/emails", web::get().to(email_templates))
+                    .route("/emails/{email}", web::get().to(email_template_form))
+                    .route("/emails/{email}", web::post().to(save_email_template))
+                    .route(
+                        "/emails/{email}/versions/{version}/restore",
+                        web::post().to(restore_email_template),
+                    )
+                    .route("/sequences", web::get().to(sequences))
+                    .route("/sequences", web::post().to(create_sequence))
+                    .route(
+                        "/sequences/{sequence_id}/steps",
+                        web::post().to(add_sequence_step),
+                    )
+                    .route(
+                        "/segments/{segment_id}/delete",
+                        web::post().to(delete_segment),
+                    )
+                    .route("/subscribers", web::get().to(list_subscribers))
+                    .route("/suppressions", web::get().to(suppressions))
+                    .route("/suppressions", web::post().to(create_suppression))
+                    .route(
+                        "/suppressions/{email_hash}/delete",
+                        web::post().to(delete_suppression),
+                    )
+                    .route(
+                        "/subscribers/import",
+                        web::get().to(import_subscribers_form),
+                    )
+                    .route("/subscribers/import", web::post().to(import_subscribers))
+                    .route("/subscribers/export", web::get().to(export_subscribers))
+                    .route(
+                        "/subscribers/{subscriber_id}",
+                        web::get().to(subscriber_details),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}/confirm",
+                        web::post().to(admin_confirm_subscriber),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}/unsubscribe",
+                        web::post().to(admin_unsubscribe_subscriber),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}/attributes",
+                        web::post().to(set_subscriber_attribute),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}/attributes/{key}/delete",
+                        web::post().to(remove_subscriber_attribute),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}/tags",
+                        web::post().to(add_subscriber_tag),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}/tags/{tag}/delete",
+                        web::post().to(remove_subscriber_tag),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}/delete",
+                        web::post().to(admin_delete_subscriber),
+                    ),
             )
             .route("/health_check", web::get().to(health_check))
+            .route("/feed.atom", web::get().to(atom_feed))
+            .route("/feed.rss", web::get().to(rss_feed))
+            .route("/issues", web::get().to(list_issues))
+            .route("/issues/{slug}", web::get().to(issue_page))
             // A new entry in our routing table for POST /subscriptions requests
             .route("/subscriptions", web::post().to(subscribe))
+            .route("/r/{token}", web::get().to(follow_link))
+            .route("/track/open", web::get().to(track_open))
+            .route(
+                "/webhooks/email/{provider}",
+                web::post().to(receive_email_webhook),
+            )
             .route("/subscriptions/confirm", web::get().to(confirm))
+            .route("/preferences", web::get().to(preferences))
+            .route("/preferences", web::post().to(update_preferences))
+            .route("/preferences/pause", web::post().to(pause_delivery))
+            .route("/preferences/email", web::post().to(change_email))
+            .route(
+                "/preferences/email/confirm",
+                web::get().to(confirm_email_change),
+            )
+            .route("/privacy", web::get().to(privacy_form))
+            .route("/privacy", web::post().to(request_privacy_link))
+            .route("/privacy/data", web::get().to(personal_data))
+            .route("/privacy/data/export", web::get().to(export_personal_data))
+            .route("/privacy/data/erase", web::post().to(erase_personal_data))
+            .route(
+                "/subscriptions/check-your-inbox",
+                web::get().to(check_your_inbox),
+            )
+            .route(
+                "/subscriptions/resend",
+                web::post().to(resend_confirmation_email),
+            )
+            .route(
+                "/lists/{list_slug}/subscriptions",
+                web::post().to(subscribe_to_list),
+            )
+            .route(
+                "/lists/{list_slug}/subscriptions/confirm",
+                web::get().to(confirm_list_subscription),
+            )
+            .route(
+                "/lists/{list_slug}/subscriptions/unsubscribe",
+                web::get().to(unsubscribe),
+            )
             .app_data(db_pool.clone())
             .app_data(email_client.clone())
             .app_data(base_url.clone())
             .app_data(Data::new(hmac_secret.clone()))
+            .app_data(webhook_settings.clone())
+            .app_data(subscription_settings.clone())
+            .app_data(abuse_protection.clone())
+            .app_data(email_policy.clone())
+            .app_data(trusted_proxies.clone())
     })
     .listen(listener)?
     .run();
     Ok(server)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::HmacSecret;
+    use secrecy::Secret;
+
+    fn hmac_secret() -> HmacSecret {
+        HmacSecret(Secret::new("secret".into()))
+    }
+
+    #[test]
+    fn signatures_are_verified() {
+        let hmac_secret = hmac_secret();
+        let signature = hmac_secret.sign("unsubscribe", b"list/subscriber");
+        assert!(hmac_secret.verify("unsubscribe", b"list/subscriber", &signature));
+        assert!(!hmac_secret.verify("unsubscribe", b"list/someone-else", &signature));
+    }
+
+    #[test]
+    fn signatures_are_only_valid_for_their_purpose() {
+        let hmac_secret = hmac_secret();
+        let signature = hmac_secret.sign("unsubscribe", b"subscriber");
+        assert!(!hmac_secret.verify("preferences", b"subscriber", &signature));
+    }
+}
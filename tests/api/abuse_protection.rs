@@ -0,0 +1,305 @@
+use crate::helpers::{spawn_app, spawn_app_with, TestApp};
+use secrecy::Secret;
+use zero2prod::abuse_protection::sign_form_token;
+use zero2prod::configuration::{ChallengeProvider, ChallengeSettings};
+
+fn body(email: &str, extra_fields: &[(&str, &str)]) -> String {
+    let mut fields = vec![("name", "le guin"), ("email", email)];
+    fields.extend_from_slice(extra_fields);
+    serde_urlencoded::to_string(fields).unwrap()
+}
+
+async fn n_subscriptions(app: &TestApp) -> i64 {
+    sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count
+}
+
+async fn home_page_html(app: &TestApp) -> String {
+    reqwest::get(&app.address)
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap()
+}
+
+/// The signed token embedded in the subscription form of the home page.
+async fn form_token(app: &TestApp) -> String {
+    let html_page = home_page_html(app).await;
+    let start = html_page.find(r#"name="form_token" value=""#).unwrap() + 25;
+    let end = start + html_page[start..].find('"').unwrap();
+    html_page[start..end].to_owned()
+}
+
+#[tokio::test]
+async fn requests_beyond_the_per_ip_limit_are_rejected_with_a_429() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_email_server(10).await;
+    for i in 0..10 {
+        app.post_subscriptions(body(&format!("reader{}@gmail.com", i), &[]))
+            .await
+            .error_for_status()
+            .unwrap();
+    }
+
+    // Act
+    let response = app
+        .post_subscriptions(body("reader10@gmail.com", &[]))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 429);
+    assert_eq!(n_subscriptions(&app).await, 10);
+}
+
+#[tokio::test]
+async fn forwarded_addresses_do_not_get_around_the_per_ip_limit() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.subscriptions
+            .abuse_protection
+            .max_requests_per_ip_per_hour = 2;
+    })
+    .await;
+    app.mock_email_server(2).await;
+
+    for i in 0..3 {
+        // Act
+        let response = app
+            .api_client
+            .post(format!("{}/subscriptions", &app.address))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Accept", "application/json")
+            .header("X-Forwarded-For", format!("203.0.113.{}", i))
+            .body(app.with_form_token(body(&format!("reader{}@gmail.com", i), &[])))
+            .send()
+            .await
+            .unwrap();
+
+        // Assert
+        let expected = if i < 2 { 200 } else { 429 };
+        assert_eq!(response.status().as_u16(), expected);
+    }
+}
+
+#[tokio::test]
+async fn requests_beyond_the_per_email_limit_are_rejected_with_a_429() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.subscriptions
+            .abuse_protection
+            .max_requests_per_email_per_hour = 2;
+    })
+    .await;
+    app.mock_email_server(3).await;
+    for email in ["victim@gmail.com", "VICTIM@gmail.com"] {
+        app.post_subscriptions(body(email, &[]))
+            .await
+            .error_for_status()
+            .unwrap();
+    }
+
+    // Act
+    let response = app.post_subscriptions(body("victim@gmail.com", &[])).await;
+    let other = app.post_subscriptions(body("other@gmail.com", &[])).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 429);
+    assert_eq!(other.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn bots_filling_in_the_honeypot_are_silently_ignored() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_email_server(0).await;
+
+    // Act
+    let response = app
+        .post_subscriptions(body(
+            "victim@gmail.com",
+            &[("website", "http://spam.example.com")],
+        ))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(n_subscriptions(&app).await, 0);
+}
+
+#[tokio::test]
+async fn forms_submitted_too_fast_are_silently_ignored() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_email_server(0).await;
+    let form_token = form_token(&app).await;
+
+    // Act
+    let response = app
+        .post_subscription_form(body("victim@gmail.com", &[("form_token", &form_token)]))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(n_subscriptions(&app).await, 0);
+}
+
+#[tokio::test]
+async fn forms_filled_in_at_a_human_pace_are_accepted() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_email_server(1).await;
+
+    // Act
+    app.post_subscription_form(body(
+        "reader@gmail.com",
+        &[("form_token", &app.form_token())],
+    ))
+    .await;
+
+    // Assert
+    assert_eq!(n_subscriptions(&app).await, 1);
+}
+
+#[tokio::test]
+async fn forms_without_a_token_are_silently_ignored() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_email_server(0).await;
+
+    // Act
+    let response = app
+        .post_subscription_form(body("victim@gmail.com", &[]))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(n_subscriptions(&app).await, 0);
+}
+
+#[tokio::test]
+async fn asking_for_json_does_not_get_around_the_form_token() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_email_server(0).await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions", &app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("Accept", "application/json")
+        .body(body("victim@gmail.com", &[]))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(n_subscriptions(&app).await, 0);
+}
+
+#[tokio::test]
+async fn forms_with_an_expired_token_are_silently_ignored() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_email_server(0).await;
+    let issued_at = chrono::Utc::now().timestamp() - 25 * 3600;
+    let form_token = sign_form_token(issued_at, &app.hmac_secret);
+
+    // Act
+    app.post_subscription_form(body("victim@gmail.com", &[("form_token", &form_token)]))
+        .await;
+
+    // Assert
+    assert_eq!(n_subscriptions(&app).await, 0);
+}
+
+#[tokio::test]
+async fn forms_with_a_forged_token_are_silently_ignored() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_email_server(0).await;
+    let issued_at = chrono::Utc::now().timestamp() - 10;
+    let form_token = format!("{}.{}", issued_at, "ab".repeat(32));
+
+    // Act
+    app.post_subscription_form(body("victim@gmail.com", &[("form_token", &form_token)]))
+        .await;
+
+    // Assert
+    assert_eq!(n_subscriptions(&app).await, 0);
+}
+
+async fn spawn_app_with_challenge() -> TestApp {
+    spawn_app_with(|c| {
+        c.subscriptions.abuse_protection.challenge = Some(ChallengeSettings {
+            provider: ChallengeProvider::Local,
+            secret: Secret::new("42".into()),
+        });
+    })
+    .await
+}
+
+#[tokio::test]
+async fn the_challenge_must_be_passed_when_configured() {
+    // Arrange
+    let app = spawn_app_with_challenge().await;
+    app.mock_email_server(0).await;
+    let test_cases = vec![
+        (body("victim@gmail.com", &[]), "no answer"),
+        (
+            body("victim@gmail.com", &[("challenge_response", "41")]),
+            "a wrong answer",
+        ),
+    ];
+
+    for (body, description) in test_cases {
+        // Act
+        let response = app.post_subscriptions(body).await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The request was not rejected with {}.",
+            description
+        );
+    }
+    assert_eq!(n_subscriptions(&app).await, 0);
+}
+
+#[tokio::test]
+async fn subscribers_passing_the_challenge_get_a_confirmation_email() {
+    // Arrange
+    let app = spawn_app_with_challenge().await;
+    app.mock_email_server(1).await;
+
+    // Act
+    let response = app
+        .post_subscriptions(body("reader@gmail.com", &[("challenge_response", "42")]))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(n_subscriptions(&app).await, 1);
+}
+
+#[tokio::test]
+async fn the_home_page_only_shows_the_challenge_when_configured() {
+    // Arrange
+    let app = spawn_app().await;
+    let app_with_challenge = spawn_app_with_challenge().await;
+
+    // Act
+    let html_page = home_page_html(&app).await;
+    let html_page_with_challenge = home_page_html(&app_with_challenge).await;
+
+    // Assert
+    assert!(!html_page.contains(r#"name="challenge_response""#));
+    assert!(html_page_with_challenge.contains(r#"name="challenge_response""#));
+}
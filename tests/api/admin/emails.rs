@@ -0,0 +1,248 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use reqwest::StatusCode;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+impl TestApp {
+    pub async fn get_email_template_html(&self, email: &str, query: &str) -> String {
+        self.api_client
+            .get(format!(
+                "{}/admin/emails/{}?{}",
+                &self.address, email, query
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_email_template<Body>(&self, email: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/emails/{}", &self.address, email))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Save a version of the confirmation email with the given subject.
+    async fn save_confirmation_subject(&self, subject: &str) {
+        let response = self
+            .post_email_template(
+                "confirmation",
+                &serde_json::json!({
+                    "locale": "en",
+                    "subject": subject,
+                    "html": r#"<a href="{{ link }}">Confirm</a>"#,
+                    "text": "Confirm: {{ link | safe }}",
+                }),
+            )
+            .await;
+        assert_is_redirect_to(&response, "/admin/emails/confirmation?locale=en");
+    }
+
+    /// Subscribe and return the subject of the confirmation email.
+    async fn confirmation_subject(&self) -> String {
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(StatusCode::OK))
+            .mount(&self.email_server)
+            .await;
+        self.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+            .await
+            .error_for_status()
+            .unwrap();
+        let email_request = self
+            .email_server
+            .received_requests()
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+        body["Subject"].as_str().unwrap().to_owned()
+    }
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_edit_emails() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/admin/emails", &app.address))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_form_starts_from_the_default_templates() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let html_page = app.get_email_template_html("confirmation", "").await;
+
+    // Assert
+    assert!(html_page.contains(r#"<input type="text" name="subject" value="Welcome!"#));
+    assert!(html_page.contains("<p>Subject: Welcome!</p>"));
+    assert!(html_page.contains("None, the default templates are used."));
+}
+
+#[tokio::test]
+async fn the_preview_renders_the_templates_with_sample_data() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let html_page = app
+        .get_email_template_html(
+            "welcome",
+            "locale=en&subject=Hello%20{{%20list%20}}&html=\
+             {{%20unsubscribe_link%20}}{{%20preferences_link%20}}&\
+             text={{%20unsubscribe_link%20}}{{%20preferences_link%20}}",
+        )
+        .await;
+
+    // Assert
+    assert!(html_page.contains("<p>Subject: Hello Newsletter</p>"));
+    assert!(html_page.contains("example.com&#x2F;preferences?sample"));
+}
+
+#[tokio::test]
+async fn templates_missing_a_required_variable_are_not_saved() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Save
+    let response = app
+        .post_email_template(
+            "confirmation",
+            &serde_json::json!({
+                "locale": "en",
+                "subject": "Please confirm",
+                "html": r#"<a href="{{ link }}">Confirm</a>"#,
+                "text": "Confirm by clicking the button.",
+            }),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let location = response.headers()["Location"].to_str().unwrap().to_owned();
+    assert!(location.starts_with("/admin/emails/confirmation?"));
+    let n_saved = sqlx::query!(r#"SELECT count(*) AS "count!" FROM email_templates"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_saved, 0);
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app
+        .api_client
+        .get(format!("{}{}", &app.address, location))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("<p><i>The plain text body must use {{ link }}.</i></p>"));
+    assert!(html_page.contains(r#"value="Please confirm""#));
+}
+
+#[tokio::test]
+async fn saved_templates_are_used_for_confirmation_emails() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    app.save_confirmation_subject("Please confirm").await;
+
+    // Assert
+    assert_eq!(app.confirmation_subject().await, "Please confirm");
+    let saved = sqlx::query!("SELECT version, locale, created_by FROM email_templates")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.version, 1);
+    assert_eq!(saved.locale, "en");
+    assert_eq!(saved.created_by, app.test_user.username);
+}
+
+#[tokio::test]
+async fn older_versions_can_be_restored() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.save_confirmation_subject("First").await;
+    app.save_confirmation_subject("Second").await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/emails/confirmation/versions/1/restore",
+            &app.address
+        ))
+        .form(&serde_json::json!({ "locale": "en" }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/emails/confirmation?locale=en");
+    let html_page = app
+        .get_email_template_html("confirmation", "locale=en")
+        .await;
+    assert!(html_page.contains(
+        "<p><i>Version 1 of the confirmation email has been restored as version 3.</i></p>"
+    ));
+    assert_eq!(app.confirmation_subject().await, "First");
+}
+
+#[tokio::test]
+async fn saved_templates_do_not_replace_other_languages() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.save_confirmation_subject("Please confirm").await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com&locale=fr".into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["Subject"], "Bienvenue !");
+}
@@ -0,0 +1,256 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use reqwest::StatusCode;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+/// Publish an issue to a single confirmed subscriber, with the given
+/// tracking checkboxes ticked, and return its id and the delivered HTML body.
+async fn deliver_issue(app: &TestApp, markdown: &str, tracking: &[&str]) -> (Uuid, String) {
+    app.create_confirmed_subscriber().await;
+    app.test_user.login(app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let mut body = serde_json::json!({
+        "title": "Newsletter title",
+        "markdown": markdown,
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    for checkbox in tracking {
+        body[*checkbox] = "on".into();
+    }
+    let response = app.post_newsletters(&body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    (issue_id, body["HtmlBody"].as_str().unwrap().to_owned())
+}
+
+/// The values of an attribute in an HTML body, unescaped.
+fn attribute_values(html: &str, attribute: &str) -> Vec<String> {
+    html.split(&format!("{}=\"", attribute))
+        .skip(1)
+        .map(|s| {
+            s.split('"')
+                .next()
+                .unwrap()
+                .replace("&#x2F;", "/")
+                .replace("&amp;", "&")
+        })
+        .collect()
+}
+
+fn pixel_url(html: &str) -> Option<String> {
+    attribute_values(html, "<img src").pop()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_published_issues() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_issues_page(None).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn opens_of_tracked_issues_are_recorded() {
+    // Arrange
+    let app = spawn_app().await;
+    let (issue_id, html) = deliver_issue(&app, "Hello", &["track_opens"]).await;
+    let pixel_url = pixel_url(&html).expect("The issue has no tracking pixel.");
+
+    // Act
+    for _ in 0..2 {
+        let response = reqwest::get(&pixel_url).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.headers()["Content-Type"], "image/gif");
+    }
+
+    // Assert
+    let opens = sqlx::query!("SELECT first_opened_at, last_opened_at FROM issue_opens")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(opens.len(), 1);
+    assert!(opens[0].first_opened_at < opens[0].last_opened_at);
+    let html_page = app.get_issues_page_html(None).await;
+    assert!(html_page.contains("<td>100.0%</td>"));
+    let html_page = app.get_issues_page_html(Some(issue_id)).await;
+    assert!(html_page.contains("Opened by: 1 subscriber(s)"));
+}
+
+#[tokio::test]
+async fn issues_are_not_tracked_unless_asked() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let (_, html) = deliver_issue(&app, "Hello", &[]).await;
+
+    // Assert
+    assert!(pixel_url(&html).is_none());
+}
+
+#[tokio::test]
+async fn forged_pixels_do_not_record_opens() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, html) = deliver_issue(&app, "Hello", &["track_opens"]).await;
+    let pixel_url = pixel_url(&html).unwrap();
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+    let forged_url = pixel_url.replace(&subscriber_id.to_string(), &Uuid::new_v4().to_string());
+
+    // Act
+    let response = reqwest::get(&forged_url).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let n_opens = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM issue_opens"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_opens, 0);
+}
+
+#[tokio::test]
+async fn opens_are_no_longer_recorded_once_tracking_is_disabled() {
+    // Arrange
+    let app = spawn_app().await;
+    let (issue_id, html) = deliver_issue(&app, "Hello", &["track_opens"]).await;
+
+    // Act - Part 1 - Disable tracking
+    let response = app.post_open_tracking(issue_id, false).await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", issue_id));
+
+    // Act - Part 2 - Follow redirect
+    let html_page = app.get_issues_page_html(Some(issue_id)).await;
+    assert!(html_page.contains("<p><i>Opens of this issue are no longer tracked.</i></p>"));
+    assert!(html_page.contains("<button type=\"submit\">Track opens</button>"));
+
+    // Act - Part 3 - Open the issue
+    reqwest::get(pixel_url(&html).unwrap()).await.unwrap();
+
+    // Assert
+    let n_opens = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM issue_opens"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_opens, 0);
+}
+
+fn no_redirect_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn links_of_tracked_issues_redirect_and_record_clicks() {
+    // Arrange
+    let app = spawn_app().await;
+    let (issue_id, html) = deliver_issue(
+        &app,
+        "[Read](https://example.com/article?a=1&b=2) or [watch](https://example.com/video)",
+        &["track_clicks"],
+    )
+    .await;
+    let links: Vec<String> = attribute_values(&html, "href")
+        .into_iter()
+        .filter(|url| url.contains("/r/"))
+        .collect();
+    assert_eq!(links.len(), 2);
+    let client = no_redirect_client();
+
+    // Act
+    for link in [&links[0], &links[0], &links[1]] {
+        let response = client.get(link).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 302);
+    }
+    let response = client.get(&links[0]).send().await.unwrap();
+
+    // Assert
+    assert_eq!(
+        response.headers()["Location"],
+        "https://example.com/article?a=1&b=2"
+    );
+    let clicks = sqlx::query!("SELECT url, n_clicks FROM issue_clicks ORDER BY url")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(clicks.len(), 2);
+    assert_eq!(clicks[0].url, "https://example.com/article?a=1&b=2");
+    assert_eq!(clicks[0].n_clicks, 3);
+    let html_page = app.get_issues_page_html(Some(issue_id)).await;
+    assert!(html_page.contains("Clicked by: 1 subscriber(s)"));
+    assert!(html_page.contains("Click-through rate: 100.0%"));
+    assert!(html_page.contains("<td>https:&#x2F;&#x2F;example.com&#x2F;article?a=1&amp;b=2</td>"));
+}
+
+#[tokio::test]
+async fn links_are_not_rewritten_unless_asked() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let (_, html) = deliver_issue(&app, "[Read](https://example.com/article)", &[]).await;
+
+    // Assert
+    assert!(attribute_values(&html, "href").contains(&"https://example.com/article".to_string()));
+}
+
+#[tokio::test]
+async fn tampered_links_do_not_redirect() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, html) = deliver_issue(&app, "[Read](https://example.com)", &["track_clicks"]).await;
+    let link = attribute_values(&html, "href")
+        .into_iter()
+        .find(|url| url.contains("/r/"))
+        .unwrap();
+    let (payload, signature) = link.rsplit_once('.').unwrap();
+    let tampered_link = format!("{}A.{}", payload, signature);
+
+    // Act
+    let response = no_redirect_client()
+        .get(&tampered_link)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+    let n_clicks = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM issue_clicks"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_clicks, 0);
+}
@@ -0,0 +1,117 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_lists_page() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_lists().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_default_list_is_shown() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let html_page = app.get_lists_html().await;
+
+    // Assert
+    assert!(html_page.contains("<td>newsletter</td>"));
+}
+
+#[tokio::test]
+async fn an_admin_can_create_a_list() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_lists(&serde_json::json!({
+            "name": "Weekly digest",
+            "slug": "weekly-digest",
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/lists");
+    let html_page = app.get_lists_html().await;
+    assert!(html_page.contains("<p><i>The list weekly-digest has been created.</i></p>"));
+    assert!(html_page.contains("<td>Weekly digest</td>"));
+}
+
+#[tokio::test]
+async fn list_slugs_must_be_valid_and_unique() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let test_cases = vec![
+        ("Not a slug", "<p><i>"),
+        (
+            "newsletter",
+            "<p><i>A list named newsletter already exists.</i></p>",
+        ),
+    ];
+
+    for (slug, expected_message) in test_cases {
+        // Act
+        let response = app
+            .post_lists(&serde_json::json!({
+                "name": "Another list",
+                "slug": slug,
+            }))
+            .await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/lists");
+        let html_page = app.get_lists_html().await;
+        assert!(html_page.contains(expected_message));
+        assert!(!html_page.contains("<td>Another list</td>"));
+    }
+}
+
+#[tokio::test]
+async fn an_admin_can_choose_whether_a_list_takes_double_opt_in() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.post_lists(&serde_json::json!({
+        "name": "Internal",
+        "slug": "internal",
+        "double_opt_in": "off",
+    }))
+    .await;
+    let list = sqlx::query!("SELECT list_id, double_opt_in FROM lists WHERE slug = 'internal'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(list.double_opt_in, Some(false));
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/lists/{}/double-opt-in",
+            &app.address, list.list_id
+        ))
+        .form(&serde_json::json!({ "double_opt_in": "" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/lists");
+    let html_page = app.get_lists_html().await;
+    assert!(html_page.contains("<p><i>The double opt-in setting has been saved.</i></p>"));
+    let list = sqlx::query!("SELECT double_opt_in FROM lists WHERE slug = 'internal'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(list.double_opt_in, None);
+}
@@ -1,13 +1,20 @@
-use crate::helpers::TestApp;
+use crate::helpers::{assert_is_redirect_to, TestApp};
 
 mod change_password;
 mod dashboard;
+mod emails;
+mod issues;
+mod lists;
 mod newsletter;
+mod segments;
+mod sequences;
+mod subscribers;
+mod suppressions;
 
 impl TestApp {
     pub async fn get_admin_dashboard(&self) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}/admin/dashboard", &self.address))
+            .get(format!("{}/admin/dashboard", &self.address))
             .send()
             .await
             .expect("Failed to execute request")
@@ -19,7 +26,7 @@ impl TestApp {
 
     pub async fn get_change_password(&self) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}/admin/password", &self.address))
+            .get(format!("{}/admin/password", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -34,7 +41,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/admin/password", &self.address))
+            .post(format!("{}/admin/password", &self.address))
             .form(body)
             .send()
             .await
@@ -43,7 +50,7 @@ impl TestApp {
 
     pub async fn post_logout(&self) -> reqwest::Response {
         self.api_client
-            .post(&format!("{}/admin/logout", &self.address))
+            .post(format!("{}/admin/logout", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -54,16 +61,34 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/admin/newsletters", &self.address))
+            .post(format!("{}/admin/newsletters", &self.address))
             .form(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    /// Publish an issue authored in Markdown and return its slug.
+    pub async fn publish_issue(&self, title: &str, visibility: &str) -> String {
+        let response = self
+            .post_newsletters(&serde_json::json!({
+                "title": title,
+                "markdown": format!("Welcome to *{}*", title),
+                "visibility": visibility,
+                "idempotency_key": uuid::Uuid::new_v4().to_string()
+            }))
+            .await;
+        assert_is_redirect_to(&response, "/admin/newsletters");
+        sqlx::query!("SELECT slug FROM newsletter_issues WHERE title = $1", title)
+            .fetch_one(&self.db_pool)
+            .await
+            .unwrap()
+            .slug
+    }
+
     pub async fn get_newsletters(&self) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}/admin/newsletters", &self.address))
+            .get(format!("{}/admin/newsletters", &self.address))
             .send()
             .await
             .expect("Failed to execute request")
@@ -72,4 +97,256 @@ impl TestApp {
     pub async fn get_newsletters_html(&self) -> String {
         self.get_newsletters().await.text().await.unwrap()
     }
+
+    pub async fn get_issues_page(&self, issue_id: Option<uuid::Uuid>) -> reqwest::Response {
+        let path = match issue_id {
+            Some(issue_id) => format!("/admin/issues/{}", issue_id),
+            None => "/admin/issues".into(),
+        };
+        self.api_client
+            .get(format!("{}{}", &self.address, path))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_issues_page_html(&self, issue_id: Option<uuid::Uuid>) -> String {
+        self.get_issues_page(issue_id).await.text().await.unwrap()
+    }
+
+    pub async fn post_open_tracking(
+        &self,
+        issue_id: uuid::Uuid,
+        track_opens: bool,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/issues/{}/tracking",
+                &self.address, issue_id
+            ))
+            .form(&serde_json::json!({ "track_opens": track_opens }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_lists(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/lists", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_lists_html(&self) -> String {
+        self.get_lists().await.text().await.unwrap()
+    }
+
+    pub async fn post_lists<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/lists", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_newsletters_preview_html(&self, query: &str) -> String {
+        self.api_client
+            .get(format!("{}/admin/newsletters?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_segments(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/segments", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_segments_html(&self) -> String {
+        self.get_segments().await.text().await.unwrap()
+    }
+
+    pub async fn post_segments<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/segments", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscribers(&self, query: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_subscribers_html(&self, query: &str) -> String {
+        self.get_subscribers(query).await.text().await.unwrap()
+    }
+
+    pub async fn get_subscriber_details(&self, subscriber_id: &uuid::Uuid) -> reqwest::Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/subscribers/{}",
+                &self.address, subscriber_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_subscriber_details_html(&self, subscriber_id: &uuid::Uuid) -> String {
+        self.get_subscriber_details(subscriber_id)
+            .await
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_subscriber_action(
+        &self,
+        subscriber_id: &uuid::Uuid,
+        action: &str,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/subscribers/{}/{}",
+                &self.address, subscriber_id, action
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_subscribers_import(
+        &self,
+        csv: &str,
+        mode: &str,
+        consent_source: &str,
+    ) -> reqwest::Response {
+        let form = reqwest::multipart::Form::new()
+            .part(
+                "file",
+                reqwest::multipart::Part::text(csv.to_owned()).file_name("subscribers.csv"),
+            )
+            .text("mode", mode.to_owned())
+            .text("consent_source", consent_source.to_owned());
+        self.api_client
+            .post(format!("{}/admin/subscribers/import", &self.address))
+            .multipart(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_subscriber_tag(
+        &self,
+        subscriber_id: &uuid::Uuid,
+        tag: &str,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/subscribers/{}/tags",
+                &self.address, subscriber_id
+            ))
+            .form(&serde_json::json!({ "tag": tag }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_remove_subscriber_tag(
+        &self,
+        subscriber_id: &uuid::Uuid,
+        tag: &str,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/subscribers/{}/tags/{}/delete",
+                &self.address, subscriber_id, tag
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_subscriber_attribute(
+        &self,
+        subscriber_id: &uuid::Uuid,
+        key: &str,
+        value: &str,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/subscribers/{}/attributes",
+                &self.address, subscriber_id
+            ))
+            .form(&serde_json::json!({ "key": key, "value": value }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_suppressions(&self, query: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/suppressions?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_suppressions_html(&self, query: &str) -> String {
+        self.get_suppressions(query).await.text().await.unwrap()
+    }
+
+    pub async fn post_suppressions<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/suppressions", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_delete_suppression(&self, email_hash: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/suppressions/{}/delete",
+                &self.address, email_hash
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscribers_export(&self, query: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/subscribers/export?{}",
+                &self.address, query
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
 }
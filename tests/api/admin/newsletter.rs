@@ -130,7 +130,7 @@ async fn newsletters_returns_400_for_invalid_data() {
 async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
     let name: String = Name().fake();
     let email: String = SafeEmail().fake();
-    let body = serde_urlencoded::to_string(&serde_json::json!({
+    let body = serde_urlencoded::to_string(serde_json::json!({
         "name": name,
         "email": email
     }))
@@ -299,3 +299,372 @@ async fn concurrent_form_submission_is_handled_gracefully() {
     app.dispatch_all_pending_emails().await;
     // Mock verifies on Drop that we have sent the newsletter email **once**
 }
+
+#[tokio::test]
+async fn newsletters_are_delivered_once_to_the_members_of_the_selected_lists() {
+    // Arrange
+    let app = spawn_app().await;
+    let digest_id = app.create_list("weekly-digest").await;
+    let announcements_id = app.create_list("announcements").await;
+    app.create_list("other").await;
+    for (list_slug, email) in [
+        ("weekly-digest", "ursula_le_guin%40gmail.com"),
+        ("announcements", "ursula_le_guin%40gmail.com"),
+        ("announcements", "butler%40gmail.com"),
+        // The same address, whatever its case
+        ("weekly-digest", "Butler%40gmail.com"),
+        ("other", "jemisin%40gmail.com"),
+    ] {
+        let _mock_guard = Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(StatusCode::OK))
+            .mount_as_scoped(&app.email_server)
+            .await;
+        app.post_list_subscriptions(list_slug, format!("name=someone&email={}", email))
+            .await
+            .error_for_status()
+            .unwrap();
+        let email_request = &app
+            .email_server
+            .received_requests()
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+        reqwest::get(app.get_confirmation_links(email_request).html)
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+    }
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.test_user.login(&app).await;
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text": "Newsletter body as plain text",
+        "html": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+        "list_ids[0]": digest_id,
+        "list_ids[1]": announcements_id,
+    });
+    let response = app.post_newsletters(&newsletter_request_body).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+    // Mock verifies on Drop that each address got the issue exactly once
+}
+
+#[tokio::test]
+async fn newsletters_sent_to_a_segment_only_reach_matching_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let tagged_subscriber_id = sqlx::query!("SELECT id FROM subscriptions LIMIT 1")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+    app.test_user.login(&app).await;
+    app.post_subscriber_tag(&tagged_subscriber_id, "beta").await;
+    app.post_segments(&serde_json::json!({
+        "name": "Beta testers",
+        "tags": "beta",
+    }))
+    .await;
+    let segment_id = sqlx::query!("SELECT segment_id FROM segments")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .segment_id;
+
+    // Act - Part 1 - Preview
+    let everybody_html = app.get_newsletters_html().await;
+    let segment_html = app
+        .get_newsletters_preview_html(&format!("segment_id={}", segment_id))
+        .await;
+
+    // Assert - Part 1
+    assert!(everybody_html.contains("This issue will be sent to 2 subscriber(s)."));
+    assert!(segment_html.contains("This issue will be sent to 1 subscriber(s)."));
+
+    // Act - Part 2 - Send
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text": "Newsletter body as plain text",
+        "html": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+        "segment_id": segment_id,
+    });
+    let response = app.post_newsletters(&newsletter_request_body).await;
+
+    // Assert - Part 2
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+    // Mock verifies on Drop that only the tagged subscriber got the issue
+}
+
+#[tokio::test]
+async fn newsletters_sent_to_an_unknown_segment_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text": "Newsletter body as plain text",
+        "html": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+        "segment_id": uuid::Uuid::new_v4(),
+    });
+    let response = app.post_newsletters(&newsletter_request_body).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn newsletters_are_personalised_for_each_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let subscriber = sqlx::query!("SELECT id, name FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    app.test_user.login(&app).await;
+    app.post_subscriber_attribute(&subscriber.id, "company", "Earthsea")
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        // The issue, then the confirmation of the unsubscription
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "News for {{ subscriber.name }}",
+        "text": "Unsubscribe: {{ unsubscribe_url }}",
+        "html": "<p>{{ subscriber.attributes.company | default(value=\"-\") }} \
+            {{ subscriber.attributes.city | default(value=\"nowhere\") }}</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_newsletters(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["Subject"], format!("News for {}", subscriber.name));
+    assert!(body["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .contains("<p>Earthsea nowhere</p>"));
+    let unsubscribe_url = body["TextBody"]
+        .as_str()
+        .unwrap()
+        .split("Unsubscribe: ")
+        .nth(1)
+        .unwrap()
+        .lines()
+        .next()
+        .unwrap()
+        .to_owned();
+    reqwest::get(unsubscribe_url)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let saved = sqlx::query!(r#"SELECT status::TEXT AS "status!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "unsubscribed");
+}
+
+#[tokio::test]
+async fn newsletters_with_invalid_merge_tags_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let test_cases = vec![
+        (
+            "{{ subscriber.name",
+            "<p>Hi</p>",
+            "a template that does not compile",
+        ),
+        (
+            "Hi",
+            "<p>{{ subscriber.attributes.company }}</p>",
+            "an attribute without a default",
+        ),
+    ];
+
+    for (title, html, description) in test_cases {
+        // Act
+        let response = app
+            .post_newsletters(&serde_json::json!({
+                "title": title,
+                "text": "Hi",
+                "html": html,
+                "idempotency_key": uuid::Uuid::new_v4().to_string()
+            }))
+            .await;
+
+        // Assert
+        assert_eq!(
+            response.status(),
+            StatusCode::BAD_REQUEST,
+            "The API did not reject an issue with {}.",
+            description
+        );
+    }
+}
+
+#[tokio::test]
+async fn newsletters_authored_in_markdown_get_generated_html_and_text() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let markdown = "# Hello {{ subscriber.name }}\n\n\
+        Read [the archive](https://example.com).\n\n\
+        <script>alert(1)</script>";
+
+    // Act
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "markdown": markdown,
+            "idempotency_key": uuid::Uuid::new_v4().to_string()
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let saved = sqlx::query!("SELECT markdown_content FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.markdown_content.as_deref(), Some(markdown));
+    let name = sqlx::query!("SELECT name FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .name;
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let html = body["HtmlBody"].as_str().unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains(&format!("<h1>Hello {}</h1>", name)));
+    assert!(html.contains("<a href=\"https://example.com\""));
+    assert!(!html.contains("<script>"));
+    let text = body["TextBody"].as_str().unwrap();
+    assert!(text.contains(&format!(
+        "Hello {}\n\nRead the archive (https://example.com).",
+        name
+    )));
+    assert!(text.contains("Unsubscribe: http://"));
+}
+
+#[tokio::test]
+async fn explicit_bodies_override_the_ones_generated_from_markdown() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "markdown": "Some *Markdown*",
+            "text": "Hand-written plain text",
+            "html": "",
+            "idempotency_key": uuid::Uuid::new_v4().to_string()
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert!(body["TextBody"]
+        .as_str()
+        .unwrap()
+        .contains("\n\nHand-written plain text\n\n"));
+    assert!(body["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .contains("<em>Markdown</em>"));
+}
+
+#[tokio::test]
+async fn newsletters_without_markdown_need_both_bodies() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "html": "<p>Only HTML</p>",
+            "idempotency_key": uuid::Uuid::new_v4().to_string()
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,111 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_segments_page() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_segments().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn an_admin_can_create_and_delete_a_segment() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Create
+    let response = app
+        .post_segments(&serde_json::json!({
+            "name": "Recent beta testers",
+            "tags": "beta, Early-Adopter,beta",
+            "status": "confirmed",
+            "subscribed_from": "",
+            "subscribed_until": "",
+            "subscribed_within_days": "30",
+        }))
+        .await;
+
+    // Assert - Part 1
+    assert_is_redirect_to(&response, "/admin/segments");
+    let html_page = app.get_segments_html().await;
+    assert!(html_page.contains("<p><i>The segment Recent beta testers has been created.</i></p>"));
+    let segment =
+        sqlx::query!("SELECT segment_id, tags, status, subscribed_within_days FROM segments")
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch saved segment.");
+    assert_eq!(segment.tags, vec!["beta", "early-adopter"]);
+    assert_eq!(segment.status.as_deref(), Some("confirmed"));
+    assert_eq!(segment.subscribed_within_days, Some(30));
+
+    // Act - Part 2 - Delete
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/segments/{}/delete",
+            &app.address, segment.segment_id
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert - Part 2
+    assert_is_redirect_to(&response, "/admin/segments");
+    let html_page = app.get_segments_html().await;
+    assert!(html_page.contains("<p><i>The segment has been deleted.</i></p>"));
+    assert!(!html_page.contains("<td>Recent beta testers</td>"));
+}
+
+#[tokio::test]
+async fn invalid_segment_definitions_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let test_cases = vec![
+        (
+            serde_json::json!({"name": "", "tags": "beta"}),
+            "The segment name cannot be empty.",
+        ),
+        (
+            serde_json::json!({"name": "Testers", "tags": "beta testers"}),
+            "beta testers is not a valid tag.",
+        ),
+        (
+            serde_json::json!({"name": "Testers", "status": "vip"}),
+            "vip is not a valid status.",
+        ),
+        (
+            serde_json::json!({"name": "Testers", "subscribed_from": "yesterday"}),
+            "yesterday is not a valid date, expected YYYY-MM-DD.",
+        ),
+        (
+            serde_json::json!({"name": "Testers", "subscribed_within_days": "-3"}),
+            "-3 is not a valid number of days.",
+        ),
+    ];
+
+    for (body, expected_message) in test_cases {
+        // Act
+        let response = app.post_segments(&body).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/segments");
+        let html_page = app.get_segments_html().await;
+        assert!(
+            html_page.contains(&format!("<p><i>{}</i></p>", expected_message)),
+            "The segments page did not show `{}`.",
+            expected_message
+        );
+    }
+    let n_segments = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM segments")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_segments, 0);
+}
@@ -0,0 +1,310 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use reqwest::StatusCode;
+use uuid::Uuid;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+/// The id of the default list.
+const NEWSLETTER_LIST_ID: &str = "8c1c6c3e-3f9f-4a5e-9d4b-1f2a7b6e0d11";
+
+impl TestApp {
+    pub async fn get_sequences(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/sequences", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sequences_html(&self) -> String {
+        self.get_sequences().await.text().await.unwrap()
+    }
+
+    /// Create a sequence on the default list with steps sent after the
+    /// given delays, returning its id.
+    pub async fn create_sequence(&self, delays_in_hours: &[i32]) -> Uuid {
+        let response = self
+            .api_client
+            .post(format!("{}/admin/sequences", &self.address))
+            .form(&serde_json::json!({
+                "name": "Onboarding",
+                "list_id": NEWSLETTER_LIST_ID,
+            }))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_is_redirect_to(&response, "/admin/sequences");
+        let sequence_id = sqlx::query!("SELECT sequence_id FROM sequences")
+            .fetch_one(&self.db_pool)
+            .await
+            .unwrap()
+            .sequence_id;
+        for (i, delay_hours) in delays_in_hours.iter().enumerate() {
+            let response = self
+                .api_client
+                .post(format!(
+                    "{}/admin/sequences/{}/steps",
+                    &self.address, sequence_id
+                ))
+                .form(&serde_json::json!({
+                    "title": format!("Step {}", i + 1),
+                    "delay_hours": delay_hours.to_string(),
+                    "markdown": "Hi {{ subscriber.name }}!",
+                }))
+                .send()
+                .await
+                .expect("Failed to execute request.");
+            assert_is_redirect_to(&response, "/admin/sequences");
+        }
+        sequence_id
+    }
+}
+
+struct Enrollment {
+    position: Option<i32>,
+    completed: bool,
+}
+
+async fn enrollment(app: &TestApp) -> Enrollment {
+    sqlx::query_as!(
+        Enrollment,
+        r#"
+        SELECT position, completed_at IS NOT NULL AS "completed!"
+        FROM sequence_enrollments
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+async fn sent_subjects(app: &TestApp) -> Vec<String> {
+    app.email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            body["Subject"].as_str().unwrap().to_owned()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_sequences_page() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_sequences().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn an_admin_can_create_a_sequence_and_add_steps() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    app.create_sequence(&[0, 48]).await;
+
+    // Assert
+    let html_page = app.get_sequences_html().await;
+    assert!(html_page.contains("<h2>Onboarding</h2>"));
+    assert!(html_page.contains("Step 1</a>"));
+    assert!(html_page.contains("<td>48 hours</td>"));
+    let n_public = sqlx::query!(
+        r#"SELECT COUNT(*) AS "count!" FROM newsletter_issues WHERE visibility = 'public'"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .count;
+    assert_eq!(n_public, 0);
+}
+
+#[tokio::test]
+async fn invalid_steps_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let sequence_id = app.create_sequence(&[]).await;
+    let test_cases = vec![
+        (
+            "-1",
+            "Hi",
+            "<p><i>-1 is not a valid delay, use a number of hours.</i></p>",
+        ),
+        ("24", "{{ subscriber.name", "<p><i>"),
+    ];
+
+    for (delay_hours, markdown, expected_message) in test_cases {
+        // Act
+        let response = app
+            .api_client
+            .post(format!(
+                "{}/admin/sequences/{}/steps",
+                &app.address, sequence_id
+            ))
+            .form(&serde_json::json!({
+                "title": "Broken",
+                "delay_hours": delay_hours,
+                "markdown": markdown,
+            }))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/sequences");
+        let html_page = app.get_sequences_html().await;
+        assert!(html_page.contains(expected_message));
+        assert!(!html_page.contains("Broken</a>"));
+    }
+}
+
+#[tokio::test]
+async fn confirmed_subscribers_go_through_the_sequence() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.create_sequence(&[0, 0]).await;
+    app.create_confirmed_subscriber().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let subjects = sent_subjects(&app).await;
+    assert_eq!(subjects[1..], ["Step 1", "Step 2"]);
+    let enrollment = enrollment(&app).await;
+    assert_eq!(enrollment.position, Some(2));
+    assert!(enrollment.completed);
+}
+
+#[tokio::test]
+async fn subscribers_confirmed_by_an_admin_go_through_the_sequence() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.create_sequence(&[0]).await;
+    app.mock_email_server(2).await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+    app.post_subscriber_action(&subscriber_id, "confirm").await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let subjects = sent_subjects(&app).await;
+    assert_eq!(subjects[1..], ["Step 1"]);
+    let enrollment = enrollment(&app).await;
+    assert!(enrollment.completed);
+}
+
+#[tokio::test]
+async fn subscribers_imported_as_confirmed_go_through_the_sequence() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.create_sequence(&[0]).await;
+    app.mock_email_server(1).await;
+    app.post_subscribers_import(
+        "email,name\nursula_le_guin@gmail.com,le guin\n",
+        "confirmed",
+        "Previous provider",
+    )
+    .await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(sent_subjects(&app).await, ["Step 1"]);
+    assert!(enrollment(&app).await.completed);
+}
+
+#[tokio::test]
+async fn steps_wait_for_their_delay() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.create_sequence(&[0, 24]).await;
+    app.create_confirmed_subscriber().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let enrollment = enrollment(&app).await;
+    assert_eq!(enrollment.position, Some(2));
+    assert!(!enrollment.completed);
+    let queued = sqlx::query!(
+        r#"
+        SELECT execute_after > now() + interval '23 hours' AS "is_delayed!"
+        FROM issue_delivery_queue
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert!(queued.is_delayed);
+}
+
+#[tokio::test]
+async fn subscribers_who_leave_the_list_leave_the_sequence() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.create_sequence(&[24, 0]).await;
+    app.create_confirmed_subscriber().await;
+    sqlx::query!("UPDATE subscriptions SET status = 'unsubscribed'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    sqlx::query!("UPDATE issue_delivery_queue SET execute_after = now()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let enrollment = enrollment(&app).await;
+    assert_eq!(enrollment.position, Some(1));
+    assert!(!enrollment.completed);
+    let outcome = sqlx::query!("SELECT outcome FROM issue_delivery_log")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .outcome;
+    assert_eq!(outcome, "skipped");
+}
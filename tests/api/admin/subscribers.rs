@@ -0,0 +1,522 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use reqwest::StatusCode;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn create_subscriber(app: &TestApp, name: &str, email: &str) -> Uuid {
+    let body = serde_urlencoded::to_string(serde_json::json!({
+        "name": name,
+        "email": email
+    }))
+    .unwrap();
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions(body)
+        .await
+        .error_for_status()
+        .unwrap();
+    sqlx::query!("SELECT id FROM subscriptions WHERE email = $1", email)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.")
+        .id
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_subscribers("").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn subscribers_can_be_searched_by_email_or_name() {
+    // Arrange
+    let app = spawn_app().await;
+    create_subscriber(&app, "le guin", "ursula_le_guin@gmail.com").await;
+    create_subscriber(&app, "octavia", "butler@gmail.com").await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Search by email
+    let html_page = app.get_subscribers_html("search=ursula").await;
+    assert!(html_page.contains("ursula_le_guin@gmail.com"));
+    assert!(!html_page.contains("butler@gmail.com"));
+
+    // Act - Part 2 - Search by name
+    let html_page = app.get_subscribers_html("search=OCTAVIA").await;
+    assert!(!html_page.contains("ursula_le_guin@gmail.com"));
+    assert!(html_page.contains("butler@gmail.com"));
+}
+
+#[tokio::test]
+async fn pages_past_the_end_of_the_subscriber_list_show_the_last_page() {
+    // Arrange
+    let app = spawn_app().await;
+    create_subscriber(&app, "le guin", "ursula_le_guin@gmail.com").await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_subscribers("page=9223372036854775807").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("Page 1 of 1"));
+    assert!(html_page.contains("ursula_le_guin@gmail.com"));
+}
+
+#[tokio::test]
+async fn subscribers_can_be_filtered_by_status() {
+    // Arrange
+    let app = spawn_app().await;
+    create_subscriber(&app, "le guin", "ursula_le_guin@gmail.com").await;
+    let subscriber_id = create_subscriber(&app, "octavia", "butler@gmail.com").await;
+    app.test_user.login(&app).await;
+    app.post_subscriber_action(&subscriber_id, "confirm").await;
+
+    // Act
+    let html_page = app.get_subscribers_html("status=confirmed").await;
+
+    // Assert
+    assert!(!html_page.contains("ursula_le_guin@gmail.com"));
+    assert!(html_page.contains("butler@gmail.com"));
+}
+
+#[tokio::test]
+async fn unknown_subscribers_return_a_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_subscriber_details(&Uuid::new_v4()).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn admins_can_confirm_a_pending_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_subscriber(&app, "le guin", "ursula_le_guin@gmail.com").await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Confirm
+    let response = app.post_subscriber_action(&subscriber_id, "confirm").await;
+    assert_is_redirect_to(&response, &format!("/admin/subscribers/{}", subscriber_id));
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app.get_subscriber_details_html(&subscriber_id).await;
+    assert!(html_page.contains("<p><i>The subscriber has been confirmed.</i></p>"));
+
+    // Assert
+    let saved =
+        sqlx::query!(r#"SELECT status::TEXT AS "status!", confirmed_at FROM subscriptions"#,)
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, "confirmed");
+    assert!(saved.confirmed_at.is_some());
+}
+
+#[tokio::test]
+async fn admins_can_unsubscribe_a_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_subscriber(&app, "le guin", "ursula_le_guin@gmail.com").await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_subscriber_action(&subscriber_id, "unsubscribe")
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, &format!("/admin/subscribers/{}", subscriber_id));
+    let saved = sqlx::query!(r#"SELECT status::TEXT AS "status!" FROM subscriptions"#,)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, "unsubscribed");
+}
+
+#[tokio::test]
+async fn admins_can_delete_a_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_subscriber(&app, "le guin", "ursula_le_guin@gmail.com").await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.post_subscriber_action(&subscriber_id, "delete").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/subscribers");
+    let saved = sqlx::query!("SELECT id FROM subscriptions",)
+        .fetch_optional(&app.db_pool)
+        .await
+        .expect("Failed to query subscriptions.");
+    assert!(saved.is_none());
+    let events = sqlx::query!(
+        "SELECT action, actor FROM consent_events WHERE subscriber_id = $1 ORDER BY occurred_at",
+        subscriber_id
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("Failed to fetch consent events.");
+    let last = events.last().expect("The consent events were deleted.");
+    assert_eq!(events.len(), 2);
+    assert_eq!(last.action, "admin_deleted");
+    assert_eq!(last.actor.as_deref(), Some(app.test_user.username.as_str()));
+}
+
+#[tokio::test]
+async fn subscriber_details_show_the_delivery_history() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_subscriber(&app, "le guin", "ursula_le_guin@gmail.com").await;
+    app.test_user.login(&app).await;
+    app.post_subscriber_action(&subscriber_id, "confirm").await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.post_newsletters(&serde_json::json!({
+        "title": "Newsletter title",
+        "text": "Newsletter body as plain text",
+        "html": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Act
+    let html_page = app.get_subscriber_details_html(&subscriber_id).await;
+
+    // Assert
+    assert!(html_page.contains("<td>Newsletter title</td>"));
+    assert!(html_page.contains("<td>delivered</td>"));
+}
+
+#[tokio::test]
+async fn confirming_a_subscriber_uses_up_their_confirmation_link() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_subscriber(&app, "le guin", "ursula_le_guin@gmail.com").await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    app.test_user.login(&app).await;
+    app.post_subscriber_action(&subscriber_id, "confirm").await;
+    app.post_subscriber_action(&subscriber_id, "unsubscribe")
+        .await;
+
+    // Act
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(app.subscriber_status().await, "unsubscribed");
+}
+
+#[tokio::test]
+async fn importing_as_confirmed_stores_valid_rows_and_reports_invalid_ones() {
+    // Arrange
+    let app = spawn_app().await;
+    create_subscriber(&app, "le guin", "ursula_le_guin@gmail.com").await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let csv = "email,name\n\
+               butler@gmail.com,octavia\n\
+               definitely-not-an-email,someone\n\
+               ursula_le_guin@gmail.com,le guin\n\
+               butler@gmail.com,octavia again\n";
+
+    // Act
+    let response = app
+        .post_subscribers_import(csv, "confirmed", "Previous provider")
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("1 subscriber(s) imported, 2 duplicate(s) skipped."));
+    assert!(html_page.contains("<td>3</td>"));
+    assert!(html_page.contains("definitely-not-an-email is not a valid subscriber email."));
+    let saved = sqlx::query!(
+        r#"SELECT status::TEXT AS "status!", consent_source FROM subscriptions WHERE email = 'butler@gmail.com'"#,
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, "confirmed");
+    assert_eq!(saved.consent_source.as_deref(), Some("Previous provider"));
+}
+
+#[tokio::test]
+async fn imported_addresses_go_through_the_email_policy() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let csv = "email,name\n\
+               ursula@mailinator.com,le guin\n\
+               info@butler.com,octavia\n";
+
+    // Act
+    let response = app
+        .post_subscribers_import(csv, "confirmed", "Previous provider")
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("1 subscriber(s) imported"));
+    assert!(html_page.contains("ursula@mailinator.com is a disposable address"));
+    let saved = sqlx::query!(
+        r#"
+        SELECT email AS "email!", tag AS "tag!"
+        FROM subscriptions
+        JOIN subscriber_tags ON subscriber_tags.subscriber_id = subscriptions.id
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch the flagged subscriber.");
+    assert_eq!(saved.email, "info@butler.com");
+    assert_eq!(saved.tag, "role-address");
+}
+
+#[tokio::test]
+async fn importing_as_confirmed_requires_a_consent_source() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Import without consent source
+    let response = app
+        .post_subscribers_import("email,name\nbutler@gmail.com,octavia\n", "confirmed", "")
+        .await;
+    assert_is_redirect_to(&response, "/admin/subscribers/import");
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app
+        .api_client
+        .get(format!("{}/admin/subscribers/import", &app.address))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains(
+        "<p><i>A consent source is required to import subscribers as confirmed.</i></p>"
+    ));
+    let saved = sqlx::query!("SELECT id FROM subscriptions",)
+        .fetch_optional(&app.db_pool)
+        .await
+        .expect("Failed to query subscriptions.");
+    assert!(saved.is_none());
+}
+
+#[tokio::test]
+async fn importing_with_confirmation_queues_a_confirmation_email_for_new_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    let csv = "email,name\n\
+               butler@gmail.com,octavia\n\
+               ursula_le_guin@gmail.com,le guin\n";
+
+    // Act
+    app.post_subscribers_import(csv, "send_confirmation", "")
+        .await
+        .error_for_status()
+        .unwrap();
+    let sent_during_upload = app.email_server.received_requests().await.unwrap().len();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(sent_during_upload, 0);
+    let saved = sqlx::query!(r#"SELECT status::TEXT AS "status!" FROM subscriptions"#,)
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscriptions.");
+    assert_eq!(saved.len(), 2);
+    assert!(saved.iter().all(|s| s.status == "pending_confirmation"));
+}
+
+#[tokio::test]
+async fn export_returns_subscribers_filtered_by_status() {
+    // Arrange
+    let app = spawn_app().await;
+    create_subscriber(&app, "le guin", "ursula_le_guin@gmail.com").await;
+    let subscriber_id = create_subscriber(&app, "octavia", "butler@gmail.com").await;
+    app.test_user.login(&app).await;
+    app.post_subscriber_action(&subscriber_id, "confirm").await;
+
+    // Act
+    let response = app.get_subscribers_export("status=confirmed").await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["Content-Type"], "text/csv");
+    let csv = response.text().await.unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("email,name,status,subscribed_at,confirmed_at,consent_source,list,consent_events")
+    );
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("butler@gmail.com,octavia,confirmed,"));
+    assert_eq!(lines.next(), None);
+}
+
+#[tokio::test]
+async fn admin_can_tag_and_untag_a_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_subscriber(&app, "le guin", "ursula_le_guin@gmail.com").await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Tag
+    let response = app.post_subscriber_tag(&subscriber_id, " Beta ").await;
+
+    // Assert - Part 1
+    assert_is_redirect_to(&response, &format!("/admin/subscribers/{}", subscriber_id));
+    let html_page = app.get_subscriber_details_html(&subscriber_id).await;
+    assert!(html_page.contains("<p><i>The tag beta has been added.</i></p>"));
+    let tags = sqlx::query!("SELECT tag FROM subscriber_tags")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(tags.len(), 1);
+    assert_eq!(tags[0].tag, "beta");
+
+    // Act - Part 2 - Untag
+    let response = app.post_remove_subscriber_tag(&subscriber_id, "beta").await;
+
+    // Assert - Part 2
+    assert_is_redirect_to(&response, &format!("/admin/subscribers/{}", subscriber_id));
+    let html_page = app.get_subscriber_details_html(&subscriber_id).await;
+    assert!(html_page.contains("<p><i>The tag beta has been removed.</i></p>"));
+    assert!(html_page.contains("This subscriber has no tags."));
+}
+
+#[tokio::test]
+async fn invalid_tags_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_subscriber(&app, "le guin", "ursula_le_guin@gmail.com").await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_subscriber_tag(&subscriber_id, "early adopter")
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, &format!("/admin/subscribers/{}", subscriber_id));
+    let html_page = app.get_subscriber_details_html(&subscriber_id).await;
+    assert!(html_page.contains("<p><i>early adopter is not a valid tag.</i></p>"));
+    let n_tags = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM subscriber_tags")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_tags, 0);
+}
+
+#[tokio::test]
+async fn admin_can_set_and_remove_a_subscriber_attribute() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_subscriber(&app, "le guin", "ursula_le_guin@gmail.com").await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Set twice
+    app.post_subscriber_attribute(&subscriber_id, "company", "Ekumen")
+        .await;
+    let response = app
+        .post_subscriber_attribute(&subscriber_id, "company", "Earthsea")
+        .await;
+
+    // Assert - Part 1
+    assert_is_redirect_to(&response, &format!("/admin/subscribers/{}", subscriber_id));
+    let html_page = app.get_subscriber_details_html(&subscriber_id).await;
+    assert!(html_page.contains("<p><i>The attribute company has been saved.</i></p>"));
+    assert!(html_page.contains("<td>Earthsea</td>"));
+    assert!(!html_page.contains("<td>Ekumen</td>"));
+
+    // Act - Part 2 - Remove
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/subscribers/{}/attributes/company/delete",
+            &app.address, subscriber_id
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert - Part 2
+    assert_is_redirect_to(&response, &format!("/admin/subscribers/{}", subscriber_id));
+    let html_page = app.get_subscriber_details_html(&subscriber_id).await;
+    assert!(html_page.contains("This subscriber has no attributes."));
+}
+
+#[tokio::test]
+async fn import_stores_extra_columns_as_attributes() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let csv = "email,name,company\n\
+        butler@gmail.com,octavia,Xenogenesis\n\
+        ursula_le_guin@gmail.com,le guin,\n";
+
+    // Act
+    app.post_subscribers_import(csv, "confirmed", "Previous provider")
+        .await;
+
+    // Assert
+    let attributes = sqlx::query!(
+        r#"
+        SELECT
+            subscriptions.email AS "email!",
+            subscriber_attributes.key AS "key!",
+            subscriber_attributes.value AS "value!"
+        FROM subscriptions
+        JOIN subscriber_attributes ON subscriber_attributes.subscriber_id = subscriptions.id
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(attributes.len(), 1);
+    assert_eq!(attributes[0].email, "butler@gmail.com");
+    assert_eq!(attributes[0].key, "company");
+    assert_eq!(attributes[0].value, "Xenogenesis");
+}
@@ -0,0 +1,189 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+
+async fn suppress(app: &TestApp, email: &str) {
+    let response = app
+        .post_suppressions(&serde_json::json!({ "email": email, "reason": "legal_request" }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/suppressions");
+}
+
+async fn email_hash(app: &TestApp) -> String {
+    sqlx::query!("SELECT email_hash FROM suppressions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .email_hash
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_suppression_list() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_suppressions("").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_suppress_an_address() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_suppressions(&serde_json::json!({ "email": EMAIL, "reason": "manual" }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn an_admin_can_suppress_look_up_and_remove_an_address() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Suppress
+    suppress(&app, " Ursula_Le_Guin@gmail.com").await;
+
+    // Assert - Part 1
+    let html_page = app.get_suppressions_html("").await;
+    assert!(html_page.contains("<p><i>The address will not be mailed anymore.</i></p>"));
+    assert!(html_page.contains("<td>legal_request</td>"));
+    assert!(html_page.contains("<td>admin</td>"));
+    // Only the hash is stored.
+    assert!(!html_page.contains(EMAIL));
+    let email_hash = email_hash(&app).await;
+
+    // Act - Part 2 - Look up
+    let found = app
+        .get_suppressions_html("email=ursula_le_guin%40gmail.com")
+        .await;
+    let not_found = app
+        .get_suppressions_html("email=someone%40example.com")
+        .await;
+
+    // Assert - Part 2
+    assert!(found.contains(&format!("/admin/suppressions/{}/delete", email_hash)));
+    assert!(not_found.contains("someone@example.com is not suppressed."));
+
+    // Act - Part 3 - Remove
+    let response = app.post_delete_suppression(&email_hash).await;
+
+    // Assert - Part 3
+    assert_is_redirect_to(&response, "/admin/suppressions");
+    let html_page = app.get_suppressions_html("").await;
+    assert!(html_page.contains("<p><i>The address can be mailed again.</i></p>"));
+    assert!(html_page.contains("No address is suppressed."));
+}
+
+#[tokio::test]
+async fn invalid_suppressions_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let test_cases = vec![("not-an-email", "manual"), (EMAIL, "because")];
+
+    for (email, reason) in test_cases {
+        // Act
+        let response = app
+            .post_suppressions(&serde_json::json!({ "email": email, "reason": reason }))
+            .await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/suppressions");
+        let html_page = app.get_suppressions_html("").await;
+        assert!(html_page.contains("<p><i>"));
+        assert!(html_page.contains("No address is suppressed."));
+    }
+}
+
+#[tokio::test]
+async fn suppressed_addresses_do_not_receive_confirmation_emails() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    suppress(&app, EMAIL).await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+
+    // Assert
+    // The response does not reveal that the address is suppressed.
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn suppressed_addresses_are_left_out_of_newsletters() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    app.test_user.login(&app).await;
+    suppress(&app, EMAIL).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let html_page = app.get_newsletters_html().await;
+    app.post_newsletters(&serde_json::json!({
+        "title": "Newsletter title",
+        "markdown": "Hello",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert!(html_page.contains("This issue will be sent to 0 subscriber(s)."));
+}
+
+#[tokio::test]
+async fn addresses_suppressed_after_an_issue_is_enqueued_are_skipped() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    app.test_user.login(&app).await;
+    app.post_newsletters(&serde_json::json!({
+        "title": "Newsletter title",
+        "markdown": "Hello",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+    suppress(&app, EMAIL).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let outcome = sqlx::query!("SELECT outcome FROM issue_delivery_log")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .outcome;
+    assert_eq!(outcome, "suppressed");
+}
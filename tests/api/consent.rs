@@ -0,0 +1,174 @@
+use crate::helpers::{spawn_app, TestApp};
+use reqwest::StatusCode;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::domain::ListSlug;
+use zero2prod::routes::unsubscribe_link;
+
+struct ConsentEvent {
+    action: String,
+    source: Option<String>,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    actor: Option<String>,
+}
+
+async fn consent_events(app: &TestApp) -> Vec<ConsentEvent> {
+    sqlx::query_as!(
+        ConsentEvent,
+        "SELECT action, source, ip_address, user_agent, actor FROM consent_events ORDER BY occurred_at"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+async fn subscriber_id(app: &TestApp) -> Uuid {
+    sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id
+}
+
+#[tokio::test]
+async fn subscribing_records_the_request_with_its_origin() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.api_client
+        .post(format!("{}/subscriptions", &app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("User-Agent", "integration-test/1.0")
+        .body(format!(
+            "name=le%20guin&email=ursula_le_guin%40gmail.com&source=landing_page&form_token={}",
+            app.form_token()
+        ))
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let events = consent_events(&app).await;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].action, "subscribe_requested");
+    assert_eq!(events[0].source.as_deref(), Some("landing_page"));
+    assert_eq!(events[0].ip_address.as_deref(), Some("127.0.0.1"));
+    assert_eq!(
+        events[0].user_agent.as_deref(),
+        Some("integration-test/1.0")
+    );
+    assert_eq!(events[0].actor, None);
+}
+
+#[tokio::test]
+async fn subscriptions_without_a_source_are_attributed_to_the_subscription_form() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    app.create_confirmed_subscriber().await;
+
+    // Assert
+    let events = consent_events(&app).await;
+    assert_eq!(events[0].source.as_deref(), Some("subscription_form"));
+}
+
+#[tokio::test]
+async fn confirming_and_unsubscribing_are_recorded() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    let list_slug = ListSlug::parse("newsletter".into()).unwrap();
+    let link = unsubscribe_link(
+        &app.address,
+        &list_slug,
+        subscriber_id(&app).await,
+        &app.hmac_secret,
+    );
+
+    // Act
+    reqwest::get(link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let actions: Vec<_> = consent_events(&app)
+        .await
+        .into_iter()
+        .map(|e| e.action)
+        .collect();
+    assert_eq!(
+        actions,
+        vec!["subscribe_requested", "confirmed", "unsubscribed"]
+    );
+}
+
+#[tokio::test]
+async fn admin_changes_are_recorded_with_the_admin_username() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    let subscriber_id = subscriber_id(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    app.post_subscriber_action(&subscriber_id, "unsubscribe")
+        .await;
+
+    // Assert
+    let event = consent_events(&app).await.pop().unwrap();
+    assert_eq!(event.action, "admin_unsubscribed");
+    assert_eq!(
+        event.actor.as_deref(),
+        Some(app.test_user.username.as_str())
+    );
+}
+
+#[tokio::test]
+async fn consent_events_cannot_be_rewritten() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+
+    // Act
+    let result = sqlx::query!("UPDATE consent_events SET source = 'somewhere else'")
+        .execute(&app.db_pool)
+        .await;
+
+    // Assert
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn the_consent_history_is_shown_on_the_subscriber_page_and_exported() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    let subscriber_id = subscriber_id(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let html_page = app.get_subscriber_details_html(&subscriber_id).await;
+    let csv = app.get_subscribers_export("").await.text().await.unwrap();
+
+    // Assert
+    assert!(html_page.contains("<h2>Consent history</h2>"));
+    assert!(html_page.contains("<td>subscribe_requested</td>"));
+    assert!(html_page.contains("<td>confirmed</td>"));
+    let row = csv.lines().nth(1).unwrap();
+    assert!(row.contains("subscribe_requested "));
+    assert!(row.contains("source=subscription_form"));
+    assert!(row.contains("; confirmed "));
+}
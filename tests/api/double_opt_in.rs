@@ -0,0 +1,138 @@
+use crate::helpers::{spawn_app, spawn_app_with, TestApp};
+use reqwest::StatusCode;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const BODY: &str = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+async fn status(app: &TestApp) -> String {
+    sqlx::query!(r#"SELECT status::TEXT AS "status!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .status
+}
+
+async fn set_double_opt_in(app: &TestApp, slug: &str, double_opt_in: bool) {
+    sqlx::query!(
+        "UPDATE lists SET double_opt_in = $2 WHERE slug = $1",
+        slug,
+        double_opt_in
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn subscribers_are_confirmed_right_away_without_double_opt_in() {
+    // Arrange
+    let app = spawn_app_with(|c| c.subscriptions.double_opt_in = false).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_subscriptions(BODY.into()).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "confirmed");
+    assert_eq!(status(&app).await, "confirmed");
+    let email = app.last_email().await;
+    assert_eq!(email["Subject"], "Welcome to Newsletter!");
+    let text = email["TextBody"].as_str().unwrap();
+    assert!(text.contains("/preferences?subscriber_id="));
+    assert!(text.contains("/subscriptions/unsubscribe?"));
+    let events: Vec<(String, Option<String>)> =
+        sqlx::query!("SELECT action, source FROM consent_events ORDER BY occurred_at")
+            .fetch_all(&app.db_pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.action, r.source))
+            .collect();
+    assert_eq!(
+        events,
+        vec![
+            (
+                "subscribe_requested".to_owned(),
+                Some("subscription_form".to_owned())
+            ),
+            ("confirmed".to_owned(), Some("single_opt_in".to_owned())),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn lists_can_turn_off_double_opt_in() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_list("internal").await;
+    set_double_opt_in(&app, "internal", false).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_list_subscriptions("internal", BODY.into()).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(status(&app).await, "confirmed");
+    assert_eq!(app.last_email().await["Subject"], "Welcome to internal!");
+}
+
+#[tokio::test]
+async fn lists_can_require_double_opt_in_when_it_is_off_by_default() {
+    // Arrange
+    let app = spawn_app_with(|c| c.subscriptions.double_opt_in = false).await;
+    set_double_opt_in(&app, "newsletter", true).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriptions(BODY.into()).await;
+
+    // Assert
+    assert_eq!(status(&app).await, "pending_confirmation");
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    app.get_confirmation_links(&email_request);
+}
+
+#[tokio::test]
+async fn confirmed_subscribers_are_not_welcomed_twice() {
+    // Arrange
+    let app = spawn_app_with(|c| c.subscriptions.double_opt_in = false).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriptions(BODY.into()).await;
+    let response = app.post_subscriptions(BODY.into()).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(status(&app).await, "confirmed");
+}
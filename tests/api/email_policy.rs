@@ -0,0 +1,108 @@
+use crate::helpers::{spawn_app, spawn_app_with};
+use zero2prod::configuration::PolicyAction;
+
+fn body(email: &str) -> String {
+    serde_urlencoded::to_string([("name", "le guin"), ("email", email)]).unwrap()
+}
+
+async fn error_message(response: reqwest::Response) -> String {
+    let body: serde_json::Value = response.json().await.unwrap();
+    body["error"].as_str().unwrap().to_owned()
+}
+
+#[tokio::test]
+async fn disposable_addresses_are_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_email_server(0).await;
+
+    // Act
+    let response = app.post_subscriptions(body("ursula@mailinator.com")).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    assert!(error_message(response).await.contains("disposable address"));
+}
+
+#[tokio::test]
+async fn misspelled_domains_are_rejected_with_a_suggestion() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_email_server(0).await;
+
+    // Act
+    let response = app
+        .post_subscriptions(body("ursula_le_guin@gmial.com"))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        error_message(response).await,
+        "ursula_le_guin@gmial.com looks misspelled, did you mean ursula_le_guin@gmail.com?"
+    );
+}
+
+#[tokio::test]
+async fn suggestions_keep_the_local_part_as_it_was_typed() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_email_server(0).await;
+
+    // Act
+    let response = app.post_subscriptions(body("Ursula+news@gmial.com")).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    assert!(error_message(response)
+        .await
+        .ends_with("did you mean Ursula+news@gmail.com?"));
+}
+
+#[tokio::test]
+async fn role_addresses_are_accepted_but_flagged_for_review() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_email_server(1).await;
+
+    // Act
+    let response = app.post_subscriptions(body("noreply@gmail.com")).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let tags: Vec<String> = sqlx::query!("SELECT tag FROM subscriber_tags")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| r.tag)
+        .collect();
+    assert_eq!(tags, vec!["role-address"]);
+}
+
+#[tokio::test]
+async fn the_policy_can_be_relaxed_in_the_configuration() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.subscriptions.email_policy.disposable = PolicyAction::Allow;
+        c.subscriptions.email_policy.typos = PolicyAction::Flag;
+    })
+    .await;
+    app.mock_email_server(2).await;
+
+    // Act
+    let disposable = app.post_subscriptions(body("ursula@mailinator.com")).await;
+    let misspelled = app.post_subscriptions(body("ursula@gmial.com")).await;
+
+    // Assert
+    assert_eq!(disposable.status().as_u16(), 200);
+    assert_eq!(misspelled.status().as_u16(), 200);
+    let tags: Vec<String> = sqlx::query!("SELECT tag FROM subscriber_tags")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| r.tag)
+        .collect();
+    assert_eq!(tags, vec!["possible-typo"]);
+}
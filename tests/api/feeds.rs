@@ -0,0 +1,117 @@
+use crate::helpers::spawn_app;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+
+#[tokio::test]
+async fn feeds_list_public_issues_only() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let slug = app.publish_issue("Fish & chips", "public").await;
+    app.publish_issue("Members only", "private").await;
+    let issue_id = sqlx::query!(
+        "SELECT newsletter_issue_id FROM newsletter_issues WHERE slug = $1",
+        slug
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .newsletter_issue_id;
+
+    for (path, content_type) in [
+        ("/feed.rss", "application/rss+xml; charset=utf-8"),
+        ("/feed.atom", "application/atom+xml; charset=utf-8"),
+    ] {
+        // Act
+        let response = app.get_issues(path).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.headers()["Content-Type"], content_type);
+        let feed = response.text().await.unwrap();
+        assert!(feed.contains("Fish &amp; chips"));
+        assert!(feed.contains(&format!("urn:uuid:{}", issue_id)));
+        assert!(feed.contains(&format!("/issues/{}", slug)));
+        assert!(feed.contains("&lt;em&gt;Fish &amp;amp; chips&lt;&#x2F;em&gt;"));
+        assert!(!feed.contains("Members only"));
+    }
+}
+
+#[tokio::test]
+async fn feeds_use_the_publication_date() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.publish_issue("Dated issue", "public").await;
+    sqlx::query!("UPDATE newsletter_issues SET published_at = '2022-04-28 10:16:15+00'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let rss_response = app.get_issues("/feed.rss").await;
+    let atom = app.get_issues("/feed.atom").await.text().await.unwrap();
+
+    // Assert
+    assert_eq!(
+        rss_response.headers()[LAST_MODIFIED],
+        "Thu, 28 Apr 2022 10:16:15 GMT"
+    );
+    let rss = rss_response.text().await.unwrap();
+    assert!(rss.contains("<pubDate>Thu, 28 Apr 2022 10:16:15 +0000</pubDate>"));
+    assert!(atom.contains("<published>2022-04-28T10:16:15+00:00</published>"));
+}
+
+#[tokio::test]
+async fn unchanged_feeds_are_not_sent_again() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.publish_issue("First issue", "public").await;
+    let response = app.get_issues("/feed.atom").await;
+    let etag = response.headers()[ETAG].clone();
+    let last_modified = response.headers()[LAST_MODIFIED].clone();
+
+    // Act
+    let by_etag = app
+        .api_client
+        .get(format!("{}/feed.atom", app.address))
+        .header(IF_NONE_MATCH, etag.clone())
+        .send()
+        .await
+        .unwrap();
+    let by_date = app
+        .api_client
+        .get(format!("{}/feed.atom", app.address))
+        .header(IF_MODIFIED_SINCE, last_modified)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(by_etag.status().as_u16(), 304);
+    assert_eq!(by_date.status().as_u16(), 304);
+}
+
+#[tokio::test]
+async fn feeds_change_when_an_issue_is_published() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.publish_issue("First issue", "public").await;
+    let etag = app.get_issues("/feed.rss").await.headers()[ETAG].clone();
+    app.publish_issue("Second issue", "public").await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/feed.rss", app.address))
+        .header(IF_NONE_MATCH, etag.clone())
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_ne!(response.headers()[ETAG], etag);
+    assert!(response.text().await.unwrap().contains("Second issue"));
+}
@@ -2,15 +2,20 @@ use argon2::password_hash::SaltString;
 use argon2::{Algorithm, Argon2, Params, PasswordHasher, Version};
 use once_cell::sync::Lazy;
 use reqwest::StatusCode;
+use secrecy::ExposeSecret;
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use uuid::Uuid;
-use wiremock::MockServer;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+use zero2prod::abuse_protection::sign_form_token;
+use zero2prod::confirmation_email_worker::try_send_confirmation_email;
+use zero2prod::domain::ListSlug;
 use zero2prod::email_client::EmailClient;
 use zero2prod::issue_delivery_worker::{try_execute_task, ExecutionOutcome};
-use zero2prod::startup::get_connection_pool;
+use zero2prod::startup::{get_connection_pool, HmacSecret};
 use zero2prod::telemetry::{get_subscriber, init_subscriber};
 use zero2prod::{
-    configuration::{get_configuration, DatabaseSettings},
+    configuration::{get_configuration, DatabaseSettings, Settings, WebhookSettings},
     startup::Application,
 };
 
@@ -45,12 +50,15 @@ pub struct TestApp {
     pub test_user: TestUser,
     pub api_client: reqwest::Client,
     pub email_client: EmailClient,
+    pub hmac_secret: HmacSecret,
+    pub webhook_settings: WebhookSettings,
+    pub confirmation_token_ttl: chrono::Duration,
 }
 
 impl TestApp {
     pub async fn get_login_html(&self) -> String {
         self.api_client
-            .get(&format!("{}/login", &self.address))
+            .get(format!("{}/login", &self.address))
             .send()
             .await
             .expect("Failed to execute request")
@@ -64,7 +72,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             // This `reqwest` method makes sure that the body is URL-encoded // and the `Content-Type` header is set accordingly.
             .form(body)
             .send()
@@ -72,17 +80,183 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    /// Subscribe as an API client with a valid form token, see
+    /// `post_subscription_form` to send the form as it is.
     pub async fn post_subscriptions(&self, body: String) -> reqwest::Response {
         self.api_client
-            .post(&format!("{}/subscriptions", &self.address))
+            .post(format!("{}/subscriptions", &self.address))
             .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Accept", "application/json")
+            .body(self.with_form_token(body))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Subscribe from the subscription form, which browsers must send with a
+    /// form token, see `form_token`.
+    pub async fn post_subscription_form(&self, body: String) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/subscriptions", &self.address))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Accept", "text/html")
             .body(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    /// A form token for a subscription form filled in at a human pace.
+    pub fn form_token(&self) -> String {
+        sign_form_token(chrono::Utc::now().timestamp() - 10, &self.hmac_secret)
+    }
+
+    /// Add a form token to the body of a subscription request.
+    pub fn with_form_token(&self, body: String) -> String {
+        if body.is_empty() {
+            format!("form_token={}", self.form_token())
+        } else {
+            format!("{}&form_token={}", body, self.form_token())
+        }
+    }
+
+    pub async fn post_list_subscriptions(
+        &self,
+        list_slug: &str,
+        body: String,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/lists/{}/subscriptions",
+                &self.address, list_slug
+            ))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Accept", "application/json")
+            .body(self.with_form_token(body))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Insert a mailing list directly in the database, returning its id.
+    pub async fn create_list(&self, slug: &str) -> Uuid {
+        let list_id = Uuid::new_v4();
+        sqlx::query!(
+            "INSERT INTO lists (list_id, slug, name, created_at) VALUES ($1, $2, $2, now())",
+            list_id,
+            slug
+        )
+        .execute(&self.db_pool)
+        .await
+        .expect("Failed to create list.");
+        list_id
+    }
+
     /// Extract the confirmation links embedded in the request to the email API.
+    pub async fn get_issues(&self, path_and_query: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}{}", &self.address, path_and_query))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Subscribe to the default list and follow the confirmation link.
+    pub async fn create_confirmed_subscriber(&self) -> Uuid {
+        self.create_confirmed_list_subscriber(ListSlug::default().as_ref())
+            .await
+    }
+
+    /// Subscribe to `list_slug` and follow the confirmation link.
+    pub async fn create_confirmed_list_subscriber(&self, list_slug: &str) -> Uuid {
+        let _mock_guard = Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(StatusCode::OK))
+            .expect(1)
+            .mount_as_scoped(&self.email_server)
+            .await;
+        self.post_list_subscriptions(
+            list_slug,
+            "name=le%20guin&email=ursula_le_guin%40gmail.com".into(),
+        )
+        .await
+        .error_for_status()
+        .unwrap();
+        let email_request = self
+            .email_server
+            .received_requests()
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+        let confirmation_links = self.get_confirmation_links(&email_request);
+        reqwest::get(confirmation_links.html)
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+        sqlx::query!(
+            r#"
+            SELECT id FROM subscriptions
+            JOIN lists USING (list_id)
+            WHERE lists.slug = $1
+            "#,
+            list_slug
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .unwrap()
+        .id
+    }
+
+    /// Accept emails, expecting exactly `n_emails` of them.
+    pub async fn mock_email_server(&self, n_emails: u64) {
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(StatusCode::OK))
+            .expect(n_emails)
+            .mount(&self.email_server)
+            .await;
+    }
+
+    /// The body of the last request to the email API.
+    pub async fn last_email(&self) -> serde_json::Value {
+        let email_request = self
+            .email_server
+            .received_requests()
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+        serde_json::from_slice(&email_request.body).unwrap()
+    }
+
+    /// The status of the only subscription.
+    pub async fn subscriber_status(&self) -> String {
+        sqlx::query!(r#"SELECT status::TEXT AS "status!" FROM subscriptions"#)
+            .fetch_one(&self.db_pool)
+            .await
+            .unwrap()
+            .status
+    }
+
+    pub async fn post_email_webhook(
+        &self,
+        provider: &str,
+        payload: &serde_json::Value,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/webhooks/email/{}", &self.address, provider))
+            .basic_auth(
+                &self.webhook_settings.username,
+                Some(self.webhook_settings.password.expose_secret()),
+            )
+            .json(payload)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub fn get_confirmation_links(&self, email_request: &wiremock::Request) -> ConfirmationLinks {
         let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
 
@@ -107,11 +281,28 @@ impl TestApp {
     }
 
     pub async fn dispatch_all_pending_emails(&self) {
+        while let ExecutionOutcome::TaskCompleted = try_send_confirmation_email(
+            &self.db_pool,
+            &self.email_client,
+            &self.address,
+            self.confirmation_token_ttl,
+            0,
+            0,
+        )
+        .await
+        .unwrap()
+        {}
         loop {
-            if let ExecutionOutcome::EmptyQueue =
-                try_execute_task(&self.db_pool, &self.email_client, 0, 0)
-                    .await
-                    .unwrap()
+            if let ExecutionOutcome::EmptyQueue = try_execute_task(
+                &self.db_pool,
+                &self.email_client,
+                &self.address,
+                &self.hmac_secret,
+                0,
+                0,
+            )
+            .await
+            .unwrap()
             {
                 break;
             }
@@ -140,6 +331,11 @@ async fn configure_database(config: &DatabaseSettings) -> PgPool {
 }
 
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Spawn the application after adjusting its configuration.
+pub async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
     Lazy::force(&TRACING);
 
     // Launch a mock server to stand in for Postmark's API
@@ -154,6 +350,7 @@ pub async fn spawn_app() -> TestApp {
         c.application.port = 0;
         // Use the mock server as email API
         c.email_client.base_url = email_server.uri();
+        configure(&mut c);
         c
     };
 
@@ -166,7 +363,7 @@ pub async fn spawn_app() -> TestApp {
     // Get the port before spawning the application
     let application_port = application.port();
     let address = format!("http://localhost:{}", application_port);
-    let _ = tokio::spawn(application.run_until_stopped());
+    drop(tokio::spawn(application.run_until_stopped()));
 
     let api_client = reqwest::Client::builder()
         .redirect(reqwest::redirect::Policy::none())
@@ -182,6 +379,9 @@ pub async fn spawn_app() -> TestApp {
         test_user: TestUser::generate(),
         api_client,
         email_client: configuration.email_client.client(),
+        hmac_secret: HmacSecret(configuration.application.hmac_secret.clone()),
+        webhook_settings: configuration.webhooks.clone(),
+        confirmation_token_ttl: configuration.subscriptions.confirmation_token_ttl(),
     };
     test_app.test_user.store(&test_app.db_pool).await;
     test_app
@@ -0,0 +1,177 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+use reqwest::StatusCode;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn public_issues_are_listed_and_viewable() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let slug = app.publish_issue("Our first issue!", "public").await;
+
+    // Act
+    let list_html = app.get_issues("/issues").await.text().await.unwrap();
+    let issue_response = app.get_issues(&format!("/issues/{}", slug)).await;
+
+    // Assert
+    assert!(slug.starts_with("our-first-issue-"));
+    assert!(list_html.contains(&format!(
+        "<a href=\"/issues/{}\">Our first issue!</a>",
+        slug
+    )));
+    assert_eq!(issue_response.status().as_u16(), 200);
+    let issue_html = issue_response.text().await.unwrap();
+    assert!(issue_html.contains("<h1>Our first issue!</h1>"));
+    assert!(issue_html.contains("Welcome to <em>Our first issue!</em>"));
+}
+
+#[tokio::test]
+async fn private_issues_are_neither_listed_nor_viewable() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let slug = app.publish_issue("Members only", "private").await;
+
+    // Act
+    let list_html = app.get_issues("/issues").await.text().await.unwrap();
+    let issue_response = app.get_issues(&format!("/issues/{}", slug)).await;
+    let forged_response = app
+        .get_issues(&format!("/issues/{}?signature=00ff", slug))
+        .await;
+
+    // Assert
+    assert!(!list_html.contains("Members only"));
+    assert_eq!(issue_response.status().as_u16(), 404);
+    assert_eq!(forged_response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn issues_are_private_unless_asked_otherwise() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "markdown": "Hello",
+            "idempotency_key": uuid::Uuid::new_v4().to_string()
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let saved = sqlx::query!("SELECT visibility FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.visibility, "private");
+}
+
+#[tokio::test]
+async fn an_invalid_visibility_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "markdown": "Hello",
+            "visibility": "everyone",
+            "idempotency_key": uuid::Uuid::new_v4().to_string()
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn the_archive_is_paginated() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    for i in 0..11 {
+        app.publish_issue(&format!("Issue number {}", i), "public")
+            .await;
+    }
+
+    // Act
+    let first_page = app.get_issues("/issues").await.text().await.unwrap();
+    let second_page = app.get_issues("/issues?page=2").await.text().await.unwrap();
+
+    // Assert
+    assert_eq!(first_page.matches("<li>").count(), 10);
+    assert!(first_page.contains("Page 1 of 2"));
+    assert!(first_page.contains("href=\"/issues?page=2\""));
+    assert_eq!(second_page.matches("<li>").count(), 1);
+    assert!(second_page.contains("href=\"/issues?page=1\""));
+}
+
+#[tokio::test]
+async fn pages_past_the_end_of_the_archive_show_the_last_page() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.publish_issue("Issue number 1", "public").await;
+
+    // Act
+    let response = app.get_issues("/issues?page=9223372036854775807").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("Page 1 of 1"));
+    assert_eq!(html_page.matches("<li>").count(), 1);
+}
+
+#[tokio::test]
+async fn delivered_emails_link_to_the_issue_in_the_browser() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.publish_issue("Members only", "private").await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let html = body["HtmlBody"].as_str().unwrap();
+    assert!(html.contains("View this email in your browser</a>"));
+    let link = body["TextBody"]
+        .as_str()
+        .unwrap()
+        .strip_prefix("View this email in your browser: ")
+        .unwrap()
+        .lines()
+        .next()
+        .unwrap()
+        .to_owned();
+    assert!(link.contains("?signature="));
+    let response = app.api_client.get(&link).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("<h1>Members only</h1>"));
+}
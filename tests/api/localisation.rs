@@ -0,0 +1,220 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use reqwest::StatusCode;
+use uuid::Uuid;
+use zero2prod::domain::ListSlug;
+use zero2prod::routes::unsubscribe_link;
+
+const BODY: &str = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+impl TestApp {
+    async fn post_subscriptions_in(&self, body: &str, accept_language: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/subscriptions", &self.address))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Accept", "application/json")
+            .header("Accept-Language", accept_language)
+            .body(self.with_form_token(body.to_owned()))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+}
+
+async fn stored_locale(app: &TestApp) -> String {
+    sqlx::query!("SELECT locale FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .locale
+}
+
+/// Make the only subscriber a French speaker.
+async fn set_french_locale(app: &TestApp) -> Uuid {
+    sqlx::query!("UPDATE subscriptions SET locale = 'fr' RETURNING id")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id
+}
+
+#[tokio::test]
+async fn subscribers_are_emailed_in_the_language_of_their_browser() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_email_server(1).await;
+
+    // Act
+    let response = app
+        .post_subscriptions_in(BODY, "fr-CA, fr;q=0.9, en;q=0.5")
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(stored_locale(&app).await, "fr-CA");
+    let email = app.last_email().await;
+    assert_eq!(email["Subject"], "Bienvenue !");
+    assert!(email["TextBody"]
+        .as_str()
+        .unwrap()
+        .contains("pour confirmer votre abonnement"));
+}
+
+#[tokio::test]
+async fn the_locale_field_of_the_form_wins_over_the_browser() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_email_server(1).await;
+
+    // Act
+    app.post_subscriptions_in(&format!("{}&locale=fr", BODY), "de")
+        .await;
+
+    // Assert
+    assert_eq!(stored_locale(&app).await, "fr");
+    assert_eq!(app.last_email().await["Subject"], "Bienvenue !");
+}
+
+#[tokio::test]
+async fn languages_without_templates_fall_back_to_english() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_email_server(1).await;
+
+    // Act
+    app.post_subscriptions_in(&format!("{}&locale=not%20a%20locale", BODY), "de-AT")
+        .await;
+
+    // Assert
+    assert_eq!(stored_locale(&app).await, "de-AT");
+    assert_eq!(app.last_email().await["Subject"], "Welcome!");
+}
+
+#[tokio::test]
+async fn the_home_page_follows_the_language_of_the_browser() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let html_page = app
+        .api_client
+        .get(&app.address)
+        .header("Accept-Language", "fr")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(html_page.contains(r#"<html lang="fr">"#));
+    assert!(html_page.contains(r#"<input type="hidden" name="locale" value="fr">"#));
+    assert!(html_page.contains("S'abonner"));
+}
+
+#[tokio::test]
+async fn error_pages_follow_the_language_of_the_browser() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!(
+            "{}/subscriptions/confirm?subscription_token=f",
+            &app.address
+        ))
+        .header("Accept-Language", "fr")
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("<h1>Lien invalide</h1>"));
+    assert!(html_page.contains("Retour à l'accueil"));
+}
+
+#[tokio::test]
+async fn email_changes_are_confirmed_in_the_language_of_the_browser() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    app.mock_email_server(1).await;
+    let link = app.get_preferences_link().await;
+    let mut parameters: Vec<(String, String)> = link.query_pairs().into_owned().collect();
+    parameters.push(("email".into(), "ursula@gmail.com".into()));
+
+    // Act
+    app.api_client
+        .post(format!("{}/preferences/email", &app.address))
+        .header("Accept-Language", "fr")
+        .form(&parameters)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    let email = app.last_email().await;
+    assert_eq!(email["Subject"], "Confirmez votre nouvelle adresse");
+    assert!(email["TextBody"]
+        .as_str()
+        .unwrap()
+        .contains("pour confirmer qu'elle vous appartient"));
+}
+
+#[tokio::test]
+async fn issues_are_wrapped_in_the_language_of_the_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    set_french_locale(&app).await;
+    app.mock_email_server(1).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email = app.last_email().await;
+    let text = email["TextBody"].as_str().unwrap();
+    assert!(text.starts_with("Afficher cet email dans votre navigateur : "));
+    assert!(text.contains("\n\nGérer vos préférences d'abonnement : "));
+    assert!(email["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .contains("Gérer vos préférences d'abonnement"));
+}
+
+#[tokio::test]
+async fn the_unsubscribe_page_is_in_the_language_of_the_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    let subscriber_id = set_french_locale(&app).await;
+    let link = unsubscribe_link(
+        &app.address,
+        &ListSlug::default(),
+        subscriber_id,
+        &app.hmac_secret,
+    );
+
+    // Act
+    let response = reqwest::get(link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains(r#"<html lang="fr">"#));
+    assert!(html_page.contains("Vous ne recevrez plus de numéros de Newsletter."));
+}
@@ -1,6 +1,17 @@
+mod abuse_protection;
 mod admin;
+mod consent;
+mod double_opt_in;
+mod email_policy;
+mod feeds;
 mod health_check;
 mod helpers;
+mod issues;
+mod localisation;
 mod login;
+mod preferences;
+mod privacy;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_unsubscribe;
+mod webhooks;
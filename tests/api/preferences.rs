@@ -0,0 +1,362 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use reqwest::StatusCode;
+use uuid::Uuid;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::routes::preferences_link;
+
+async fn subscriber_id(app: &TestApp) -> Uuid {
+    sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id
+}
+
+impl TestApp {
+    /// The preferences link of the only subscriber.
+    pub async fn get_preferences_link(&self) -> reqwest::Url {
+        let link = preferences_link(&self.address, subscriber_id(self).await, &self.hmac_secret);
+        reqwest::Url::parse(&link).unwrap()
+    }
+
+    pub async fn get_preferences_html(&self) -> String {
+        self.api_client
+            .get(self.get_preferences_link().await)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    /// Submit one of the forms of the preferences page.
+    pub async fn post_preferences(&self, form: &str, fields: &[(&str, &str)]) -> reqwest::Response {
+        let link = self.get_preferences_link().await;
+        let mut parameters: Vec<(String, String)> = link.query_pairs().into_owned().collect();
+        parameters.extend(fields.iter().map(|(k, v)| (k.to_string(), v.to_string())));
+        self.api_client
+            .post(format!("{}{}", &self.address, form))
+            .form(&parameters)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    async fn send_newsletter(&self) {
+        self.test_user.login(self).await;
+        let newsletter_request_body = serde_json::json!({
+            "title": "Newsletter title",
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        });
+        let response = self.post_newsletters(&newsletter_request_body).await;
+        assert_is_redirect_to(&response, "/admin/newsletters");
+        self.dispatch_all_pending_emails().await;
+    }
+}
+
+#[tokio::test]
+async fn the_preferences_page_shows_the_subscription() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+
+    // Act
+    let html_page = app.get_preferences_html().await;
+
+    // Assert
+    assert!(html_page.contains(r#"value="ursula_le_guin@gmail.com""#));
+    assert!(html_page.contains(r#"value="le guin""#));
+    assert!(html_page.contains(r#"<option value="immediate" selected>"#));
+}
+
+#[tokio::test]
+async fn tampered_preferences_links_are_rejected_with_a_401() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    let mut link = app.get_preferences_link().await;
+    link.set_query(Some(&format!(
+        "subscriber_id={}&signature={}",
+        Uuid::new_v4(),
+        link.query_pairs()
+            .find(|(k, _)| k == "signature")
+            .unwrap()
+            .1
+    )));
+
+    // Act
+    let response = reqwest::get(link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn subscribers_can_change_their_name_and_frequency() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+
+    // Act
+    let response = app
+        .post_preferences(
+            "/preferences",
+            &[("name", "Ursula"), ("delivery_frequency", "weekly")],
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let html_page = app.get_preferences_html().await;
+    assert!(html_page.contains("<p><i>Your preferences have been saved.</i></p>"));
+    let saved =
+        sqlx::query!("SELECT name, delivery_frequency::TEXT AS frequency FROM subscriptions")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(saved.name, "Ursula");
+    assert_eq!(saved.frequency.as_deref(), Some("weekly"));
+}
+
+#[tokio::test]
+async fn invalid_preferences_are_not_saved() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+
+    // Act
+    app.post_preferences(
+        "/preferences",
+        &[("name", "Ursula"), ("delivery_frequency", "hourly")],
+    )
+    .await;
+
+    // Assert
+    let html_page = app.get_preferences_html().await;
+    assert!(html_page.contains("<p><i>hourly is not a valid delivery frequency.</i></p>"));
+    let saved = sqlx::query!("SELECT name FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.name, "le guin");
+}
+
+#[tokio::test]
+async fn digest_subscribers_get_issues_later() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    app.post_preferences(
+        "/preferences",
+        &[("name", "le guin"), ("delivery_frequency", "daily")],
+    )
+    .await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.send_newsletter().await;
+
+    // Assert
+    let queued =
+        sqlx::query!(r#"SELECT execute_after > now() AS "later!" FROM issue_delivery_queue"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert!(queued.later);
+}
+
+#[tokio::test]
+async fn digest_subscribers_get_the_issues_of_the_period_in_a_single_email() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    app.post_preferences(
+        "/preferences",
+        &[("name", "le guin"), ("delivery_frequency", "weekly")],
+    )
+    .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.test_user.login(&app).await;
+    for title in ["First issue", "Second issue"] {
+        let response = app
+            .post_newsletters(&serde_json::json!({
+                "title": title,
+                "text": format!("{} as plain text", title),
+                "html": format!("<p>{} as HTML</p>", title),
+                "idempotency_key": Uuid::new_v4().to_string()
+            }))
+            .await;
+        assert_is_redirect_to(&response, "/admin/newsletters");
+    }
+
+    // Act
+    // The start of the next week has come.
+    sqlx::query!("UPDATE issue_delivery_queue SET execute_after = now()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["Subject"], "2 new issues of the newsletter");
+    let html = body["HtmlBody"].as_str().unwrap();
+    let text = body["TextBody"].as_str().unwrap();
+    let position = |body: &str, needle: &str| body.find(needle).unwrap();
+    assert!(
+        position(html, "<p>First issue as HTML</p>")
+            < position(html, "<p>Second issue as HTML</p>")
+    );
+    assert!(
+        position(text, "First issue as plain text") < position(text, "Second issue as plain text")
+    );
+    let outcomes = sqlx::query!("SELECT outcome FROM issue_delivery_log")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(outcomes.len(), 2);
+    assert!(outcomes.iter().all(|r| r.outcome == "delivered"));
+}
+
+#[tokio::test]
+async fn paused_subscribers_do_not_get_issues() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    let tomorrow = (chrono::Utc::now() + chrono::Duration::days(2))
+        .format("%Y-%m-%d")
+        .to_string();
+    let response = app
+        .post_preferences("/preferences/pause", &[("until", &tomorrow)])
+        .await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.send_newsletter().await;
+
+    // Assert
+    let html_page = app.get_preferences_html().await;
+    assert!(html_page.contains(&format!("Delivery is paused until {}.", tomorrow)));
+    let n_queued = sqlx::query!(r#"SELECT count(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_queued, 0);
+}
+
+#[tokio::test]
+async fn changing_email_takes_effect_once_the_new_address_is_confirmed() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Ask for the change
+    let response = app
+        .post_preferences("/preferences/email", &[("email", "ursula@gmail.com")])
+        .await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["To"], "ursula@gmail.com");
+    let unchanged = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(unchanged.email, "ursula_le_guin@gmail.com");
+
+    // Act - Part 2 - Follow the link
+    let link = app.get_confirmation_links(&email_request).html;
+    let response = app.api_client.get(link).send().await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let html_page = app.get_preferences_html().await;
+    assert!(html_page.contains("<p><i>Your email address is now ursula@gmail.com.</i></p>"));
+    let changed = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(changed.email, "ursula@gmail.com");
+    let actions: Vec<String> =
+        sqlx::query!("SELECT action FROM consent_events ORDER BY occurred_at")
+            .fetch_all(&app.db_pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.action)
+            .collect();
+    assert_eq!(actions.last().unwrap(), "email_changed");
+}
+
+#[tokio::test]
+async fn issues_link_to_the_preferences_page() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.send_newsletter().await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let link = app.get_preferences_link().await;
+    assert!(body["TextBody"]
+        .as_str()
+        .unwrap()
+        .ends_with(&format!("Manage your subscription preferences: {}", link)));
+    assert!(body["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .contains("Manage your subscription preferences"));
+}
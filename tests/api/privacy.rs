@@ -0,0 +1,378 @@
+use crate::helpers::{spawn_app, TestApp};
+use reqwest::StatusCode;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::routes::privacy_link;
+
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+
+impl TestApp {
+    pub async fn post_privacy(&self, email: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/privacy", &self.address))
+            .form(&serde_json::json!({ "email": email }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Request a privacy link for `email` and return the one we emailed.
+    pub async fn get_privacy_link(&self, email: &str) -> reqwest::Url {
+        let _mock_guard = Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(StatusCode::OK))
+            .expect(1)
+            .mount_as_scoped(&self.email_server)
+            .await;
+        self.post_privacy(email).await.error_for_status().unwrap();
+        let email_request = self
+            .email_server
+            .received_requests()
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+        self.get_confirmation_links(&email_request).html
+    }
+}
+
+fn export_link(link: &reqwest::Url) -> reqwest::Url {
+    let mut link = link.clone();
+    link.set_path("/privacy/data/export");
+    link
+}
+
+async fn erase(app: &TestApp, link: &reqwest::Url) -> reqwest::Response {
+    let parameters: Vec<(String, String)> = link.query_pairs().into_owned().collect();
+    app.api_client
+        .post(format!("{}/privacy/data/erase", &app.address))
+        .form(&parameters)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn the_privacy_page_has_a_form_to_request_a_link() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = reqwest::get(format!("{}/privacy", app.address))
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains(r#"<form action="/privacy" method="post">"#));
+}
+
+#[tokio::test]
+async fn a_signed_link_is_emailed_to_known_addresses() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+
+    // Act
+    let link = app.get_privacy_link(EMAIL).await;
+    let response = reqwest::get(link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains(EMAIL));
+    assert!(html_page.contains("Erase my data"));
+}
+
+#[tokio::test]
+async fn unknown_addresses_get_the_same_page_but_no_email() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        // Only the known address gets a link.
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let known = app.post_privacy(EMAIL).await.text().await.unwrap();
+    let unknown = app.post_privacy("nobody@example.com").await;
+
+    // Assert
+    assert_eq!(unknown.status().as_u16(), 200);
+    assert_eq!(unknown.text().await.unwrap(), known);
+}
+
+#[tokio::test]
+async fn privacy_links_are_rate_limited() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    app.mock_email_server(3).await;
+
+    // Act
+    for _ in 0..4 {
+        let response = app.post_privacy(EMAIL).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 200);
+    }
+}
+
+#[tokio::test]
+async fn suppressed_addresses_get_the_same_page_but_no_email() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    app.test_user.login(&app).await;
+    app.post_suppressions(&serde_json::json!({ "email": EMAIL, "reason": "legal_request" }))
+        .await;
+    app.mock_email_server(0).await;
+
+    // Act
+    let response = app.post_privacy(EMAIL).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn privacy_links_are_sent_in_the_language_of_the_browser() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    app.mock_email_server(1).await;
+
+    // Act
+    app.api_client
+        .post(format!("{}/privacy", &app.address))
+        .header("Accept-Language", "fr-FR,fr;q=0.9")
+        .form(&serde_json::json!({ "email": EMAIL }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    let email = app.last_email().await;
+    assert_eq!(email["Subject"], "Vos données personnelles");
+    assert!(email["TextBody"]
+        .as_str()
+        .unwrap()
+        .contains("Le lien est valable 24 heures."));
+}
+
+#[tokio::test]
+async fn privacy_requests_with_an_invalid_email_are_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_privacy("definitely-not-an-email").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn privacy_links_that_were_tampered_with_are_rejected_with_a_401() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    let link = app.get_privacy_link(EMAIL).await;
+    let parameters: Vec<(String, String)> = link.query_pairs().into_owned().collect();
+    let tamper = |name: &str, value: &str| {
+        let mut link = link.clone();
+        link.query_pairs_mut().clear().extend_pairs(
+            parameters
+                .iter()
+                .map(|(k, v)| (k.as_str(), if k == name { value } else { v.as_str() })),
+        );
+        link
+    };
+    let test_cases = vec![
+        (tamper("email", "someone_else@gmail.com"), "another address"),
+        (tamper("expires", "99999999999"), "a later expiry"),
+    ];
+
+    for (link, description) in test_cases {
+        // Act
+        let response = reqwest::get(export_link(&link)).await.unwrap();
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            401,
+            "The link was accepted with {}.",
+            description
+        );
+    }
+}
+
+#[tokio::test]
+async fn expired_privacy_links_are_rejected_with_a_410() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    let link = privacy_link(
+        &app.address,
+        EMAIL,
+        chrono::Utc::now() - chrono::Duration::hours(1),
+        &app.hmac_secret,
+    );
+
+    // Act
+    let response = reqwest::get(link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 410);
+}
+
+#[tokio::test]
+async fn the_export_contains_subscriptions_tokens_and_deliveries() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    let link = app.get_privacy_link(EMAIL).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+    app.publish_issue("Issue one", "public").await;
+    app.dispatch_all_pending_emails().await;
+
+    // Act
+    let response = reqwest::get(export_link(&link)).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Content-Disposition"],
+        r#"attachment; filename="personal-data.json""#
+    );
+    let data: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(data["email"], EMAIL);
+    assert_eq!(data["subscriptions"][0]["list"], "newsletter");
+    assert_eq!(data["subscriptions"][0]["status"], "confirmed");
+    assert!(!data["subscriptions"][0]["tokens"][0]["consumed_at"].is_null());
+    assert_eq!(data["deliveries"][0]["issue"], "Issue one");
+    assert_eq!(data["deliveries"][0]["outcome"], "delivered");
+}
+
+#[tokio::test]
+async fn erasure_leaves_only_a_suppression_record() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    app.test_user.login(&app).await;
+    app.publish_issue("Issue one", "public").await;
+    let link = app.get_privacy_link(EMAIL).await;
+
+    // Act
+    let response = erase(&app, &link).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let counts = sqlx::query!(
+        r#"
+        SELECT
+          (SELECT COUNT(*) FROM subscriptions WHERE email = $1) AS "subscriptions!",
+          (SELECT COUNT(*) FROM subscription_tokens) AS "tokens!",
+          (SELECT COUNT(*) FROM issue_delivery_queue WHERE subscriber_email = $1) AS "queued!",
+          (SELECT COUNT(*) FROM issue_delivery_log WHERE subscriber_email = $1) AS "logged!",
+          (SELECT COUNT(*) FROM confirmation_emails WHERE email = $1) AS "confirmation_emails!",
+          (SELECT COUNT(*) FROM privacy_emails WHERE email = $1) AS "privacy_emails!",
+          (SELECT COUNT(*) FROM consent_events) AS "consent_events!"
+        "#,
+        EMAIL
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(counts.subscriptions, 0);
+    assert_eq!(counts.tokens, 0);
+    assert_eq!(counts.queued, 0);
+    assert_eq!(counts.logged, 0);
+    assert_eq!(counts.confirmation_emails, 0);
+    assert_eq!(counts.privacy_emails, 0);
+    assert_eq!(counts.consent_events, 0);
+    let suppression = sqlx::query!(
+        "SELECT reason, source FROM suppressions WHERE email_hash = email_hash($1)",
+        EMAIL
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("The address was not suppressed.");
+    assert_eq!(suppression.reason, "legal_request");
+    assert_eq!(suppression.source, "erasure");
+}
+
+#[tokio::test]
+async fn erasure_keeps_issue_statistics_without_the_address() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    let link = app.get_privacy_link(EMAIL).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+    app.publish_issue("Issue one", "public").await;
+    app.dispatch_all_pending_emails().await;
+
+    // Act
+    erase(&app, &link).await.error_for_status().unwrap();
+
+    // Assert
+    let saved = sqlx::query!("SELECT subscriber_email, outcome FROM issue_delivery_log")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_ne!(saved.subscriber_email, EMAIL);
+    assert_eq!(saved.outcome, "delivered");
+}
+
+#[tokio::test]
+async fn erasure_requires_a_valid_signature() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    let mut link = app.get_privacy_link(EMAIL).await;
+    let parameters: Vec<(String, String)> = link
+        .query_pairs()
+        .into_owned()
+        .map(|(k, v)| {
+            if k == "signature" {
+                (k, "ab".repeat(32))
+            } else {
+                (k, v)
+            }
+        })
+        .collect();
+    link.query_pairs_mut().clear().extend_pairs(parameters);
+
+    // Act
+    let response = erase(&app, &link).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    let n_subscriptions = sqlx::query!(
+        r#"SELECT COUNT(*) AS "count!" FROM subscriptions WHERE email = $1"#,
+        EMAIL
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .count;
+    assert_eq!(n_subscriptions, 1);
+}
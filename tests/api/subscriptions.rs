@@ -1,4 +1,4 @@
-use crate::helpers::spawn_app;
+use crate::helpers::{assert_is_redirect_to, spawn_app};
 
 #[tokio::test]
 async fn subscribe_returns_a_200_for_valid_form_data() {
@@ -34,10 +34,11 @@ async fn subscribe_persists_the_new_subscriber() {
     // Act
     app.post_subscriptions(body.into()).await;
 
-    let saved = sqlx::query!("SELECT email, name, status FROM subscriptions",)
-        .fetch_one(&app.db_pool)
-        .await
-        .expect("Failed to fetch saved subscription.");
+    let saved =
+        sqlx::query!(r#"SELECT email, name, status::TEXT AS "status!" FROM subscriptions"#,)
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch saved subscription.");
 
     assert_eq!(saved.email, "ursula_le_guin@gmail.com");
     assert_eq!(saved.name, "le guin");
@@ -91,7 +92,7 @@ async fn subscribe_returns_a_400_when_fields_are_present_but_invalid() {
 }
 
 use reqwest::StatusCode;
-use wiremock::matchers::{method, path};
+use wiremock::matchers::{any, method, path};
 use wiremock::{Mock, ResponseTemplate};
 #[tokio::test]
 async fn subscribe_sends_a_confirmation_email_for_valid_data() {
@@ -134,6 +135,85 @@ async fn two_subscribes_sends_two_confirmation_emails_for_valid_data() {
     // Mock asserts on drop
 }
 
+#[tokio::test]
+async fn subscribing_again_resends_the_same_confirmation_link() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriptions(body.into()).await;
+    app.post_subscriptions(body.into()).await;
+
+    // Assert
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    assert_eq!(
+        app.get_confirmation_links(&email_requests[0]).html,
+        app.get_confirmation_links(&email_requests[1]).html
+    );
+    let n_tokens = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM subscription_tokens"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_tokens, 1);
+}
+
+#[tokio::test]
+async fn subscribing_again_after_the_link_expired_sends_a_new_one() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions(body.into()).await;
+    sqlx::query!("UPDATE subscription_tokens SET created_at = now() - interval '1 year'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    app.post_subscriptions(body.into()).await;
+
+    // Assert
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let old_link = app.get_confirmation_links(&email_requests[0]).html;
+    let new_link = app.get_confirmation_links(&email_requests[1]).html;
+    assert_ne!(old_link, new_link);
+    let response = reqwest::get(old_link).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn confirmation_emails_are_rate_limited_per_address() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    for _ in 0..5 {
+        // Act
+        let response = app.post_subscriptions(body.into()).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    // Mock asserts on drop
+}
+
 #[tokio::test]
 async fn subscribe_sends_a_confirmation_email_with_a_link() {
     // Arrange
@@ -175,3 +255,231 @@ async fn subscribe_fails_if_there_is_a_fatal_database_error() {
     // Assert
     assert_eq!(response.status(), 500);
 }
+
+#[tokio::test]
+async fn subscribe_to_a_named_list_persists_the_subscriber_on_that_list() {
+    // Arrange
+    let app = spawn_app().await;
+    let list_id = app.create_list("weekly-digest").await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_list_subscriptions("weekly-digest", body.into())
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    let saved = sqlx::query!(r#"SELECT list_id, status::TEXT AS "status!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.list_id, list_id);
+    assert_eq!(saved.status, "pending_confirmation");
+}
+
+#[tokio::test]
+async fn subscribe_to_an_unknown_list_returns_a_404() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_list_subscriptions("no-such-list", body.into())
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn the_same_email_can_subscribe_to_two_lists() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_list("weekly-digest").await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriptions(body.into()).await;
+    app.post_list_subscriptions("weekly-digest", body.into())
+        .await;
+
+    // Assert
+    let saved = sqlx::query!(
+        "SELECT COUNT(*) AS \"count!\" FROM subscriptions WHERE email = 'ursula_le_guin@gmail.com'"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved subscriptions.");
+    assert_eq!(saved.count, 2);
+}
+
+#[tokio::test]
+async fn addresses_differing_only_by_case_are_the_same_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriptions("name=le%20guin&email=%20Ursula_Le_Guin%40GMail.com%20".into())
+        .await
+        .error_for_status()
+        .unwrap();
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let saved: Vec<String> = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscriptions.")
+        .into_iter()
+        .map(|r| r.email)
+        .collect();
+    assert_eq!(saved, vec!["Ursula_Le_Guin@gmail.com"]);
+}
+
+#[tokio::test]
+async fn the_database_rejects_addresses_differing_only_by_case() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+
+    // Act
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, list_id, email, name, subscribed_at, status)
+        SELECT gen_random_uuid(), list_id, upper(email), name, now(), status
+        FROM subscriptions
+        "#
+    )
+    .execute(&app.db_pool)
+    .await;
+
+    // Assert
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn the_home_page_has_a_subscription_form() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let html_page = reqwest::get(&app.address)
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(html_page.contains(r#"<form action="/subscriptions" method="post">"#));
+}
+
+#[tokio::test]
+async fn subscribing_from_a_browser_redirects_to_the_check_your_inbox_page() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_subscription_form(format!(
+            "name=le%20guin&email=ursula_le_guin%40gmail.com&form_token={}",
+            app.form_token()
+        ))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/subscriptions/check-your-inbox");
+    let html_page = reqwest::get(format!("{}/subscriptions/check-your-inbox", &app.address))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("<h1>Check your inbox</h1>"));
+}
+
+#[tokio::test]
+async fn invalid_subscriptions_from_a_browser_get_an_error_page() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_subscription_form(format!(
+            "name=le%20guin&email=definitely-not-an-email&form_token={}",
+            app.form_token()
+        ))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("<h1>We could not subscribe you</h1>"));
+    assert!(html_page.contains("definitely-not-an-email is not a valid subscriber email."));
+}
+
+#[tokio::test]
+async fn api_clients_get_json_responses() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let success = app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    let failure = app
+        .post_subscriptions("name=le%20guin&email=definitely-not-an-email".into())
+        .await;
+
+    // Assert
+    assert_eq!(success.status().as_u16(), 200);
+    let body: serde_json::Value = success.json().await.unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({ "status": "pending_confirmation" })
+    );
+    assert_eq!(failure.status().as_u16(), 400);
+    let body: serde_json::Value = failure.json().await.unwrap();
+    assert_eq!(
+        body["error"],
+        "definitely-not-an-email is not a valid subscriber email."
+    );
+}
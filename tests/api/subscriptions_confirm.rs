@@ -1,4 +1,4 @@
-use crate::helpers::spawn_app;
+use crate::helpers::{spawn_app, spawn_app_with};
 
 #[tokio::test]
 async fn confirmations_without_token_are_rejected_with_a_400() {
@@ -82,12 +82,370 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
         .unwrap();
 
     // Assert
-    let saved = sqlx::query!("SELECT email, name, status FROM subscriptions",)
-        .fetch_one(&app.db_pool)
-        .await
-        .expect("Failed to fetch saved subscription.");
+    let saved =
+        sqlx::query!(r#"SELECT email, name, status::TEXT AS "status!" FROM subscriptions"#,)
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch saved subscription.");
 
     assert_eq!(saved.email, "ursula_le_guin@gmail.com");
     assert_eq!(saved.name, "le guin");
     assert_eq!(saved.status, "confirmed");
 }
+
+#[tokio::test]
+async fn the_confirmation_link_of_a_named_list_confirms_that_subscription_only() {
+    // Arrange
+    let app = spawn_app().await;
+    let list_id = app.create_list("weekly-digest").await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions(body.into()).await;
+    app.post_list_subscriptions("weekly-digest", body.into())
+        .await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[1];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    assert_eq!(
+        confirmation_links.html.path(),
+        "/lists/weekly-digest/subscriptions/confirm"
+    );
+
+    // Act
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let saved = sqlx::query!(
+        r#"SELECT list_id, status::TEXT AS "status!" FROM subscriptions WHERE status = 'confirmed'"#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved subscriptions.");
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].list_id, list_id);
+}
+
+#[tokio::test]
+async fn a_confirmation_link_used_with_another_list_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_list("weekly-digest").await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let mut confirmation_link = app.get_confirmation_links(email_request).html;
+    confirmation_link.set_path("/lists/weekly-digest/subscriptions/confirm");
+
+    // Act
+    let response = reqwest::get(confirmation_link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+/// Pretend the pending confirmation tokens were issued a year ago.
+async fn expire_tokens(app: &crate::helpers::TestApp) {
+    sqlx::query!("UPDATE subscription_tokens SET created_at = now() - interval '1 year'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn expired_confirmation_links_offer_to_resend_the_email() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_link = app.get_confirmation_links(email_request).html;
+    expire_tokens(&app).await;
+
+    // Act
+    let response = reqwest::get(confirmation_link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::GONE);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("This confirmation link has expired"));
+    assert!(html_page.contains(r#"<form action="/subscriptions/resend" method="post">"#));
+    let status = sqlx::query!(r#"SELECT status::TEXT AS "status!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .status;
+    assert_eq!(status, "pending_confirmation");
+}
+
+#[tokio::test]
+async fn resending_replaces_an_expired_confirmation_link() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let old_link = app.get_confirmation_links(email_request).html;
+    expire_tokens(&app).await;
+    let old_token = old_link
+        .query_pairs()
+        .find(|(key, _)| key == "subscription_token")
+        .unwrap()
+        .1
+        .into_owned();
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions/resend", &app.address))
+        .form(&serde_json::json!({ "subscription_token": old_token }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.text().await.unwrap().contains("Check your inbox"));
+    let email_request = &app.email_server.received_requests().await.unwrap()[1];
+    let new_link = app.get_confirmation_links(email_request).html;
+    assert_ne!(new_link, old_link);
+    let response = reqwest::get(old_link).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    reqwest::get(new_link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn rate_limited_resends_keep_the_current_link_working() {
+    // Arrange
+    let app = spawn_app_with(|c| c.subscriptions.max_confirmation_emails_per_hour = 1).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_link = app.get_confirmation_links(email_request).html;
+    let token = confirmation_link
+        .query_pairs()
+        .find(|(key, _)| key == "subscription_token")
+        .unwrap()
+        .1
+        .into_owned();
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions/resend", &app.address))
+        .form(&serde_json::json!({ "subscription_token": token }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    reqwest::get(confirmation_link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn confirmed_subscribers_are_not_sent_a_new_link() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_link = app.get_confirmation_links(email_request).html;
+    reqwest::get(confirmation_link.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    expire_tokens(&app).await;
+    let token = confirmation_link
+        .query_pairs()
+        .find(|(key, _)| key == "subscription_token")
+        .unwrap()
+        .1
+        .into_owned();
+
+    // Act
+    let confirm_response = reqwest::get(confirmation_link).await.unwrap();
+    let resend_response = app
+        .api_client
+        .post(format!("{}/subscriptions/resend", &app.address))
+        .form(&serde_json::json!({ "subscription_token": token }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(confirm_response.status(), StatusCode::OK);
+    assert_eq!(resend_response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn confirmation_links_render_confirmation_pages() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_link = app.get_confirmation_links(email_request).html;
+
+    // Act
+    let first = reqwest::get(confirmation_link.clone()).await.unwrap();
+    let second = reqwest::get(confirmation_link).await.unwrap();
+
+    // Assert
+    assert_eq!(first.status(), StatusCode::OK);
+    assert!(first
+        .text()
+        .await
+        .unwrap()
+        .contains("<h1>Subscription confirmed</h1>"));
+    assert_eq!(second.status(), StatusCode::OK);
+    assert!(second
+        .text()
+        .await
+        .unwrap()
+        .contains("<h1>Already confirmed</h1>"));
+}
+
+#[tokio::test]
+async fn invalid_confirmation_links_render_an_error_page() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = "a".repeat(25);
+
+    // Act
+    let response = reqwest::get(&format!(
+        "{}/subscriptions/confirm?subscription_token={}",
+        app.address, token
+    ))
+    .await
+    .unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("<h1>Invalid link</h1>"));
+}
+
+#[tokio::test]
+async fn api_clients_get_json_confirmation_responses() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_link = app.get_confirmation_links(email_request).html;
+    let mut invalid_link = confirmation_link.clone();
+    invalid_link.set_query(Some(&format!("subscription_token={}", "a".repeat(25))));
+
+    // Act
+    let confirmed = app
+        .api_client
+        .get(confirmation_link)
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .unwrap();
+    let invalid = app
+        .api_client
+        .get(invalid_link)
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(confirmed.status(), StatusCode::OK);
+    let body: serde_json::Value = confirmed.json().await.unwrap();
+    assert_eq!(body, serde_json::json!({ "status": "confirmed" }));
+    assert_eq!(invalid.status(), StatusCode::UNAUTHORIZED);
+    let body: serde_json::Value = invalid.json().await.unwrap();
+    assert!(body["error"].is_string());
+}
+
+#[tokio::test]
+async fn confirmation_tokens_are_consumed() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_link = app.get_confirmation_links(email_request).html;
+
+    // Act
+    reqwest::get(confirmation_link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let n_unused_tokens = sqlx::query!(
+        r#"SELECT COUNT(*) AS "count!" FROM subscription_tokens WHERE consumed_at IS NULL"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .count;
+    assert_eq!(n_unused_tokens, 0);
+}
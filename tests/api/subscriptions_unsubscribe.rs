@@ -0,0 +1,235 @@
+use crate::helpers::{spawn_app, TestApp};
+use reqwest::StatusCode;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::domain::ListSlug;
+use zero2prod::routes::unsubscribe_link;
+
+fn link(app: &TestApp, list_slug: &str, subscriber_id: Uuid) -> String {
+    let list_slug = ListSlug::parse(list_slug.into()).unwrap();
+    unsubscribe_link(&app.address, &list_slug, subscriber_id, &app.hmac_secret)
+}
+
+#[tokio::test]
+async fn a_signed_unsubscribe_link_unsubscribes_the_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = app.create_confirmed_subscriber().await;
+
+    // Act
+    let response = reqwest::get(link(&app, "newsletter", subscriber_id))
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    let saved = sqlx::query!(r#"SELECT status::TEXT AS "status!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, "unsubscribed");
+}
+
+#[tokio::test]
+async fn unsubscribed_subscribers_are_sent_a_confirmation() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = app.create_confirmed_subscriber().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Unsubscribe
+    reqwest::get(link(&app, "newsletter", subscriber_id))
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    // Act - Part 2 - Follow the link again
+    reqwest::get(link(&app, "newsletter", subscriber_id))
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(
+        body["Subject"],
+        "You have been unsubscribed from Newsletter"
+    );
+}
+
+#[tokio::test]
+async fn unsubscribing_from_one_list_keeps_the_other_subscriptions() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_list("weekly-digest").await;
+    let subscriber_id = app.create_confirmed_subscriber().await;
+    app.create_confirmed_list_subscriber("weekly-digest").await;
+
+    // Act
+    reqwest::get(link(&app, "newsletter", subscriber_id))
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let saved = sqlx::query!(
+        r#"
+        SELECT lists.slug, subscriptions.status::TEXT AS "status!"
+        FROM subscriptions
+        JOIN lists USING (list_id)
+        ORDER BY lists.slug
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved subscriptions.");
+    assert_eq!(saved[0].slug, "newsletter");
+    assert_eq!(saved[0].status, "unsubscribed");
+    assert_eq!(saved[1].slug, "weekly-digest");
+    assert_eq!(saved[1].status, "confirmed");
+}
+
+#[tokio::test]
+async fn unsubscribe_links_with_an_invalid_signature_are_rejected_with_a_401() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = app.create_confirmed_subscriber().await;
+    let test_cases = vec![
+        (
+            format!(
+                "{}/lists/newsletter/subscriptions/unsubscribe?subscriber_id={}&signature={}",
+                app.address,
+                subscriber_id,
+                "ab".repeat(32)
+            ),
+            "a forged signature",
+        ),
+        (
+            // A valid signature for a different list
+            link(&app, "weekly-digest", subscriber_id).replace("weekly-digest", "newsletter"),
+            "a signature for another list",
+        ),
+    ];
+
+    for (url, description) in test_cases {
+        // Act
+        let response = reqwest::get(url).await.unwrap();
+
+        // Assert
+        assert_eq!(
+            response.status(),
+            StatusCode::UNAUTHORIZED,
+            "The API did not reject an unsubscribe link with {}.",
+            description
+        );
+    }
+    let saved = sqlx::query!(r#"SELECT status::TEXT AS "status!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn an_old_confirmation_link_does_not_resubscribe_an_unsubscribed_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = app.create_confirmed_subscriber().await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_link = app.get_confirmation_links(email_request).html;
+    reqwest::get(link(&app, "newsletter", subscriber_id))
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = reqwest::get(confirmation_link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(app.subscriber_status().await, "unsubscribed");
+}
+
+#[tokio::test]
+async fn subscribing_again_after_unsubscribing_restarts_the_confirmation() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = app.create_confirmed_subscriber().await;
+    reqwest::get(link(&app, "newsletter", subscriber_id))
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Subscribe again
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Assert - Part 1
+    assert_eq!(app.subscriber_status().await, "pending_confirmation");
+
+    // Act - Part 2 - Confirm
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_link = app.get_confirmation_links(&email_request).html;
+    let response = reqwest::get(confirmation_link).await.unwrap();
+
+    // Assert - Part 2
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(app.subscriber_status().await, "confirmed");
+}
+
+#[tokio::test]
+async fn subscribers_who_complained_cannot_subscribe_again() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    sqlx::query!("UPDATE subscriptions SET status = 'complained'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(app.subscriber_status().await, "complained");
+}
@@ -0,0 +1,233 @@
+use crate::helpers::{spawn_app, TestApp};
+
+fn bounce(id: u64, kind: &str, email: &str) -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "Bounce",
+        "ID": id,
+        "Type": kind,
+        "TypeCode": 1,
+        "Email": email,
+        "BouncedAt": "2022-04-28T10:16:15Z"
+    })
+}
+
+/// A confirmed subscriber with an issue waiting to be sent to them.
+async fn create_subscriber_with_pending_issue(app: &TestApp) {
+    app.create_confirmed_subscriber().await;
+    app.test_user.login(app).await;
+    app.post_newsletters(&serde_json::json!({
+        "title": "Newsletter title",
+        "markdown": "Hello",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+}
+
+async fn n_pending_deliveries(app: &TestApp) -> i64 {
+    sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count
+}
+
+#[tokio::test]
+async fn webhooks_require_credentials() {
+    // Arrange
+    let app = spawn_app().await;
+
+    for (username, password) in [
+        (None, None),
+        (Some(app.webhook_settings.username.as_str()), Some("wrong")),
+    ] {
+        let mut request = app
+            .api_client
+            .post(format!("{}/webhooks/email/postmark", &app.address))
+            .json(&bounce(1, "HardBounce", "ursula_le_guin@gmail.com"));
+        if let Some(username) = username {
+            request = request.basic_auth(username, password);
+        }
+
+        // Act
+        let response = request.send().await.unwrap();
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 401);
+        assert_eq!(
+            response.headers()["WWW-Authenticate"],
+            r#"Basic realm="webhooks""#
+        );
+    }
+}
+
+#[tokio::test]
+async fn unknown_providers_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_email_webhook("mailchimp", &bounce(1, "HardBounce", "a@example.com"))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn malformed_payloads_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_email_webhook("postmark", &serde_json::json!({ "RecordType": "Bounce" }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn hard_bounces_mark_the_subscriber_and_drop_pending_deliveries() {
+    // Arrange
+    let app = spawn_app().await;
+    create_subscriber_with_pending_issue(&app).await;
+    assert_eq!(n_pending_deliveries(&app).await, 1);
+
+    // Act
+    let response = app
+        .post_email_webhook(
+            "postmark",
+            &bounce(1, "HardBounce", "ursula_le_guin@gmail.com"),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(app.subscriber_status().await, "bounced");
+    assert_eq!(n_pending_deliveries(&app).await, 0);
+}
+
+#[tokio::test]
+async fn hard_bounces_suppress_the_address() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    app.post_email_webhook(
+        "postmark",
+        &bounce(1, "HardBounce", "Ursula_Le_Guin@gmail.com"),
+    )
+    .await;
+
+    // Assert
+    let suppression = sqlx::query!(
+        "SELECT reason, source FROM suppressions WHERE email_hash = email_hash($1)",
+        "ursula_le_guin@gmail.com"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(suppression.reason, "bounced");
+    assert_eq!(suppression.source, "postmark");
+}
+
+#[tokio::test]
+async fn spam_complaints_mark_the_subscriber_and_drop_pending_deliveries() {
+    // Arrange
+    let app = spawn_app().await;
+    create_subscriber_with_pending_issue(&app).await;
+
+    // Act
+    let response = app
+        .post_email_webhook(
+            "postmark",
+            &serde_json::json!({
+                "RecordType": "SpamComplaint",
+                "ID": 42,
+                "Type": "SpamComplaint",
+                "TypeCode": 512,
+                "Email": "ursula_le_guin@gmail.com"
+            }),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(app.subscriber_status().await, "complained");
+    assert_eq!(n_pending_deliveries(&app).await, 0);
+}
+
+#[tokio::test]
+async fn soft_bounces_are_escalated_after_the_threshold() {
+    // Arrange
+    let app = spawn_app().await;
+    create_subscriber_with_pending_issue(&app).await;
+    let threshold = app.webhook_settings.soft_bounce_threshold as u64;
+
+    // Act - Part 1 - Stay below the threshold
+    for id in 1..threshold {
+        let response = app
+            .post_email_webhook(
+                "postmark",
+                &bounce(id, "SoftBounce", "ursula_le_guin@gmail.com"),
+            )
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    assert_eq!(app.subscriber_status().await, "confirmed");
+    assert_eq!(n_pending_deliveries(&app).await, 1);
+
+    // Act - Part 2 - Reach it
+    app.post_email_webhook(
+        "postmark",
+        &bounce(threshold, "SoftBounce", "ursula_le_guin@gmail.com"),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(app.subscriber_status().await, "bounced");
+    assert_eq!(n_pending_deliveries(&app).await, 0);
+}
+
+#[tokio::test]
+async fn retried_events_are_counted_once() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+    let threshold = app.webhook_settings.soft_bounce_threshold;
+
+    // Act
+    for _ in 0..threshold {
+        let response = app
+            .post_email_webhook(
+                "postmark",
+                &bounce(1, "SoftBounce", "ursula_le_guin@gmail.com"),
+            )
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    // Assert
+    assert_eq!(app.subscriber_status().await, "confirmed");
+}
+
+#[tokio::test]
+async fn other_events_are_acknowledged_and_ignored() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber().await;
+
+    // Act
+    let response = app
+        .post_email_webhook(
+            "postmark",
+            &bounce(1, "AutoResponder", "ursula_le_guin@gmail.com"),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(app.subscriber_status().await, "confirmed");
+}